@@ -1,32 +1,325 @@
 //! Agent orchestration
 
-use matw_ai::{AIProvider, CompletionRequest, ToolDefinition};
-use matw_core::{Message, Role, Session};
-use matw_tools::Tool;
-use std::sync::Arc;
+use crate::config::{AgentConfig, Limit, Pricing};
+use crate::events::{AgentEvent, AgentObserver};
+use crate::hooks::{HookEvent, HookInput, HookOutcome, HookRunner};
+use crate::permission::{
+    ApprovalDecision, ApprovalHandler, ApprovalRequest, PermissionAction, PermissionEngine,
+};
+use crate::instructions;
+use crate::plan::{self, PlanDecision, PlanReviewer, EXIT_PLAN_TOOL_NAME};
+use crate::prompt::SystemPromptBuilder;
+use crate::streaming::collect_stream;
+use futures::stream::{self, StreamExt};
+use matw_ai::{AIError, AIProvider, CompletionRequest, CompletionResponse, ToolDefinition, ToolUse, Usage};
+use matw_core::{Message, Role, Session, SessionMode};
+use matw_tools::{
+    CheckpointStore, FileStateTracker, PermissionState, ProgressSink, TodoStore, Tool, ToolContext, ToolError, ToolOutput,
+    ToolRegistry,
+};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+/// When the agent gives up on a turn whose tool calls keep failing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FailurePolicy {
+    /// Failed tool calls in a row before the turn is aborted; `None` never aborts
+    pub max_consecutive_failures: Option<usize>,
+}
+
+impl FailurePolicy {
+    fn is_exceeded(&self, failures: usize) -> bool {
+        self.max_consecutive_failures.is_some_and(|max| failures >= max)
+    }
+}
+
+impl Default for FailurePolicy {
+    fn default() -> Self {
+        Self {
+            max_consecutive_failures: Some(3),
+        }
+    }
+}
+
+/// What `Agent::rewind` undoes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RewindMode {
+    /// Restore files, keeping the conversation
+    Files,
+    /// Drop messages, keeping files as they are
+    Conversation,
+    /// Restore files and drop messages
+    #[default]
+    Both,
+}
+
+impl RewindMode {
+    fn restores_files(self) -> bool {
+        matches!(self, RewindMode::Files | RewindMode::Both)
+    }
+
+    fn truncates_conversation(self) -> bool {
+        matches!(self, RewindMode::Conversation | RewindMode::Both)
+    }
+}
+
+/// What a rewind changed
+#[derive(Debug, Default)]
+pub struct Rewound {
+    /// Files restored or, if the agent created them, deleted
+    pub files: Vec<PathBuf>,
+    /// Messages removed from the session, starting with the chosen user message
+    pub messages: Vec<Message>,
+}
+
+/// Result of a single tool call
+enum ToolCallOutcome {
+    Completed(ToolOutput),
+    /// Refused by the permission rules or the user; not counted as a failure
+    Refused(String),
+    Failed(String),
+    /// Cancelled before it finished
+    Interrupted,
+    /// The user approved this plan, ending plan mode
+    PlanApproved(String),
+}
+
+/// Tool result recorded for calls cut short by cancellation or the time limit
+const INTERRUPTED: &str = "Interrupted before this tool call finished.";
+
+/// Aborts a spawned task when dropped
+struct AbortOnDrop(tokio::task::JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
 
 pub struct Agent<P: AIProvider> {
     provider: P,
-    tools: Vec<Arc<dyn Tool>>,
-    max_iterations: usize,
+    tools: Arc<ToolRegistry>,
+    config: AgentConfig,
+    max_concurrent_tools: usize,
+    failure_policy: FailurePolicy,
+    permissions: Option<Arc<PermissionEngine>>,
+    approval: Option<Arc<dyn ApprovalHandler>>,
+    plan_reviewer: Option<Arc<dyn PlanReviewer>>,
+    hooks: Option<Arc<HookRunner>>,
+    /// Sessions whose SessionStart hooks have run
+    started_sessions: Mutex<HashSet<Uuid>>,
+    /// Concurrent tool calls ask for approval one at a time
+    approval_lock: tokio::sync::Mutex<()>,
+    observers: Vec<Arc<dyn AgentObserver>>,
+    system_prompt: SystemPromptBuilder,
+    file_states: Mutex<HashMap<Uuid, Arc<FileStateTracker>>>,
+    todo_lists: Mutex<HashMap<Uuid, Arc<TodoStore>>>,
+    checkpoints: Mutex<HashMap<Uuid, Arc<CheckpointStore>>>,
+    /// Set for sub-agents, whose changes belong to the parent's checkpoint
+    shared_checkpoints: Option<Arc<CheckpointStore>>,
 }
 
 impl<P: AIProvider> Agent<P> {
-    pub fn new(provider: P, tools: Vec<Arc<dyn Tool>>) -> Self {
+    pub fn new(provider: P, tools: Arc<ToolRegistry>) -> Self {
         Self {
             provider,
             tools,
-            max_iterations: 10,
+            config: AgentConfig::default(),
+            max_concurrent_tools: 4,
+            failure_policy: FailurePolicy::default(),
+            permissions: None,
+            approval: None,
+            plan_reviewer: None,
+            hooks: None,
+            started_sessions: Mutex::new(HashSet::new()),
+            approval_lock: tokio::sync::Mutex::new(()),
+            observers: Vec::new(),
+            system_prompt: SystemPromptBuilder::new(),
+            file_states: Mutex::new(HashMap::new()),
+            todo_lists: Mutex::new(HashMap::new()),
+            checkpoints: Mutex::new(HashMap::new()),
+            shared_checkpoints: None,
         }
     }
 
-    pub async fn process(&self, session: &mut Session) -> Result<(), AgentError> {
-        let mut iteration = 0;
+    /// Use this model, sampling settings and limits
+    pub fn with_config(mut self, config: AgentConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Limit how many concurrency-safe tool calls from one response run at once
+    pub fn with_max_concurrent_tools(mut self, limit: usize) -> Self {
+        self.max_concurrent_tools = limit;
+        self
+    }
+
+    pub fn with_failure_policy(mut self, policy: FailurePolicy) -> Self {
+        self.failure_policy = policy;
+        self
+    }
+
+    /// Check every tool call against the given permission rules. Pass an
+    /// `Arc` to share the rules, and approvals made during the session, with
+    /// sub-agents.
+    pub fn with_permissions(mut self, permissions: impl Into<Arc<PermissionEngine>>) -> Self {
+        self.permissions = Some(permissions.into());
+        self
+    }
+
+    /// Ask this handler about tool calls that the rules resolve to "ask"
+    pub fn with_approval_handler(mut self, approval: Arc<dyn ApprovalHandler>) -> Self {
+        self.approval = Some(approval);
+        self
+    }
+
+    /// Ask this reviewer about plans proposed in plan mode
+    pub fn with_plan_reviewer(mut self, reviewer: Arc<dyn PlanReviewer>) -> Self {
+        self.plan_reviewer = Some(reviewer);
+        self
+    }
+
+    /// Build the system prompt with this builder, e.g. to apply user overrides
+    pub fn with_system_prompt(mut self, system_prompt: SystemPromptBuilder) -> Self {
+        self.system_prompt = system_prompt;
+        self
+    }
+
+    /// Run these hooks around prompts, tool calls and the end of turns
+    pub fn with_hooks(mut self, hooks: impl Into<Arc<HookRunner>>) -> Self {
+        self.hooks = Some(hooks.into());
+        self
+    }
+
+    /// Back up files into this store, under whatever checkpoint its owner
+    /// began, instead of keeping checkpoints per session. Used by sub-agents.
+    pub fn with_checkpoints(mut self, checkpoints: Arc<CheckpointStore>) -> Self {
+        self.shared_checkpoints = Some(checkpoints);
+        self
+    }
+
+    /// Report the events of every turn to this observer
+    pub fn with_observer(mut self, observer: Arc<dyn AgentObserver>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    /// Add the user's prompt to the session once the hooks allow it.
+    ///
+    /// SessionStart hooks run until a first prompt of the session gets
+    /// through, then UserPromptSubmit hooks; context they add follows the
+    /// prompt in the same message. A blocked prompt is not added and fails
+    /// with `AgentError::PromptBlocked`.
+    pub async fn submit_prompt(&self, session: &mut Session, prompt: String) -> Result<(), AgentError> {
+        // The session starts with its first accepted prompt
+        let mut context = Vec::new();
+        let starting = !self.started_sessions.lock().unwrap().contains(&session.id());
+        if starting {
+            context.extend(self.run_hooks(HookInput::new(HookEvent::SessionStart, session)).await.context);
+        }
+
+        let outcome = self
+            .run_hooks(HookInput::new(HookEvent::UserPromptSubmit, session).with_prompt(&prompt))
+            .await;
+        if let Some(reason) = outcome.blocked {
+            return Err(AgentError::PromptBlocked(reason));
+        }
+        context.extend(outcome.context);
+        if starting {
+            self.started_sessions.lock().unwrap().insert(session.id());
+        }
+
+        let mut text = prompt;
+        for context in context {
+            text.push_str("\n\n");
+            text.push_str(&context);
+        }
+        session.add_message(Message::new_user(text));
+        Ok(())
+    }
+
+    /// Run a turn: complete, execute any requested tools and repeat until the
+    /// model stops asking for tools.
+    ///
+    /// Cancelling `cancel` stops the turn with `AgentError::Cancelled`, and
+    /// exceeding a limit of the `AgentConfig` with `AgentError::LimitReached`.
+    /// Tool calls that were requested but did not finish get an "interrupted"
+    /// result, so the session stays valid for the next turn.
+    pub async fn process(&self, session: &mut Session, cancel: &CancellationToken) -> Result<(), AgentError> {
+        self.run_turn(session, false, cancel).await
+    }
+
+    /// Run a turn like `process`, streaming each response and reporting its
+    /// text as `AgentEvent::TextDelta` as it arrives
+    pub async fn process_streaming(&self, session: &mut Session, cancel: &CancellationToken) -> Result<(), AgentError> {
+        self.run_turn(session, true, cancel).await
+    }
+
+    async fn run_turn(&self, session: &mut Session, streaming: bool, cancel: &CancellationToken) -> Result<(), AgentError> {
+        self.emit(AgentEvent::TurnStarted {
+            session_id: session.id(),
+        });
+
+        let mut usage = Usage::default();
+        let result = self.run_steps(session, streaming, cancel, &mut usage).await;
+
+        if let Err(e) = &result {
+            self.emit(AgentEvent::Error { message: e.to_string() });
+        }
+        self.emit(AgentEvent::TurnCompleted { usage });
+        result
+    }
+
+    async fn run_steps(
+        &self,
+        session: &mut Session,
+        streaming: bool,
+        cancel: &CancellationToken,
+        total: &mut Usage,
+    ) -> Result<(), AgentError> {
+        let mut responses = 0;
+        let mut failures = 0;
+        let mut stop_hook_active = false;
+
+        // Tools see the session's list, which may have been loaded or edited since the last turn
+        let todos = self.todo_store(session);
+        todos.replace(session.context().todos().to_vec());
+
+        // Files changed during the turn can be rewound to before the user's message
+        if self.shared_checkpoints.is_none() {
+            if let Some(message) = session.messages().iter().rev().find(|m| m.role() == Role::User) {
+                self.checkpoint_store(session).begin(message.id());
+            }
+        }
+
+        let pricing = self.config.resolved_pricing();
+        if self.config.cost_budget.is_some() && pricing.is_none() {
+            return Err(AgentError::Config(format!(
+                "a cost budget is set but prices for model {} are unknown; set them in the config",
+                self.config.model
+            )));
+        }
+
+        // The time limit cancels the turn like the user would
+        let turn = cancel.child_token();
+        let _timer = self.config.max_duration.map(|limit| {
+            let turn = turn.clone();
+            AbortOnDrop(tokio::spawn(async move {
+                tokio::time::sleep(limit).await;
+                turn.cancel();
+            }))
+        });
 
         loop {
-            // Check max iterations
-            if iteration >= self.max_iterations {
-                return Err(AgentError::MaxIterationsReached);
+            // Limits are checked between responses, once the previous tool results are recorded
+            if responses > 0 {
+                if let Some(limit) = self.exceeded_limit(responses, total, pricing) {
+                    return Err(AgentError::LimitReached(limit));
+                }
             }
 
             // Get last user message
@@ -37,76 +330,552 @@ impl<P: AIProvider> Agent<P> {
                 .ok_or(AgentError::NoUserMessage)?;
 
             // Prepare completion request
-            let tool_defs: Vec<_> = self.tools.iter()
-                .map(|t| ToolDefinition {
-                    name: t.name().to_string(),
-                    description: t.description().to_string(),
-                    parameters: t.parameters_schema().clone(),
-                })
-                .collect();
-
+            let mode = session.context().mode();
+            let tools = self.available_tools(mode);
             let request = CompletionRequest {
                 messages: session.messages().to_vec(),
-                tools: tool_defs,
-                model: "default".to_string(),
-                max_tokens: Some(4096),
-                temperature: Some(0.7),
-                system_prompt: Some(self.get_system_prompt()),
+                tools: self.tool_definitions(&tools, mode),
+                model: self.config.model.clone(),
+                max_tokens: self.config.max_tokens,
+                temperature: self.config.temperature,
+                system_prompt: Some(self.system_prompt.build(session.context(), &tools)),
+            };
+
+            // Get AI response; dropping the request on cancellation aborts it
+            let response = tokio::select! {
+                biased;
+                _ = turn.cancelled() => return Err(self.interruption(cancel)),
+                response = self.next_response(request, streaming) => {
+                    response.map_err(|e| AgentError::AIProvider(e.to_string()))?
+                }
             };
 
-            // Get AI response
-            let response = self.provider.complete(request).await
-                .map_err(|e| AgentError::AIProvider(e.to_string()))?;
+            responses += 1;
+            self.add_usage(total, response.usage);
 
             // Add assistant message
             session.add_message(Message::new_assistant(response.content.clone()));
 
             // Check for tool uses
             if !response.tool_uses.is_empty() {
-                for tool_use in response.tool_uses {
-                    // Add tool use message
-                    session.add_message(Message::new_tool_use(
-                        tool_use.id.clone(),
-                        tool_use.name.clone(),
-                        tool_use.input.clone(),
-                    ));
-
-                    // Execute tool
-                    let tool = self.tools.iter()
-                        .find(|t| t.name() == tool_use.name)
-                        .ok_or_else(|| AgentError::ToolNotFound(tool_use.name.clone()))?;
-
-                    let output = tool.execute(tool_use.input).await
-                        .map_err(|e| AgentError::ToolExecution(e.to_string()))?;
-
-                    // Add tool result message
-                    session.add_message(Message::new_tool_result(
-                        tool_use.id,
-                        output.content,
-                        output.is_error,
-                    ));
+                for tool_use in &response.tool_uses {
+                    self.emit(AgentEvent::ToolCallRequested {
+                        id: tool_use.id.clone(),
+                        name: tool_use.name.clone(),
+                        input: tool_use.input.clone(),
+                    });
+                }
+
+                for batch in self.batch_tool_uses(response.tool_uses) {
+                    // Calls come back with the input they ran with, which hooks may have rewritten
+                    let outcomes = if turn.is_cancelled() {
+                        batch.into_iter().map(|tool_use| (tool_use, ToolCallOutcome::Interrupted)).collect()
+                    } else {
+                        self.call_batch(session, &batch, &turn).await
+                    };
+
+                    // The whole batch has run, so its results are recorded before giving up
+                    let mut too_many_failures = None;
+                    for (tool_use, outcome) in outcomes {
+                        let read_path = match &outcome {
+                            ToolCallOutcome::Completed(output) if !output.is_error => self.read_path(&tool_use),
+                            _ => None,
+                        };
+
+                        // Add tool use message
+                        session.add_message(Message::new_tool_use(
+                            tool_use.id.clone(),
+                            tool_use.name,
+                            tool_use.input,
+                        ));
+
+                        let (content, is_error) = match outcome {
+                            ToolCallOutcome::Completed(output) => {
+                                failures = if output.is_error { failures + 1 } else { 0 };
+                                if let Some(usage) = output.usage {
+                                    self.add_usage(total, usage);
+                                }
+                                (output.content, output.is_error)
+                            }
+                            ToolCallOutcome::PlanApproved(plan) => {
+                                failures = 0;
+                                let content = plan::approved_message(&plan);
+                                session.context_mut().set_plan(Some(plan));
+                                session.context_mut().set_mode(SessionMode::Normal);
+                                self.emit(AgentEvent::ModeChanged {
+                                    mode: SessionMode::Normal,
+                                });
+                                (content, false)
+                            }
+                            ToolCallOutcome::Refused(refusal) => (refusal, true),
+                            ToolCallOutcome::Interrupted => (INTERRUPTED.to_string(), true),
+                            ToolCallOutcome::Failed(error) => {
+                                failures += 1;
+                                (error, true)
+                            }
+                        };
+
+                        // Every tool use gets a result, so the session stays valid even if we stop here
+                        session.add_message(Message::new_tool_result(tool_use.id, content.clone(), is_error));
+
+                        if let Some(path) = read_path {
+                            self.load_nested_instructions(session, &path);
+                        }
+
+                        let current = todos.get();
+                        if current != session.context().todos() {
+                            session.context_mut().set_todos(current.clone());
+                            self.emit(AgentEvent::TodosUpdated { todos: current });
+                        }
+
+                        if self.failure_policy.is_exceeded(failures) {
+                            too_many_failures = Some(AgentError::TooManyToolFailures {
+                                count: failures,
+                                last_error: content,
+                            });
+                        }
+                    }
+
+                    if let Some(err) = too_many_failures {
+                        return Err(err);
+                    }
+                }
+
+                if turn.is_cancelled() {
+                    return Err(self.interruption(cancel));
                 }
 
-                iteration += 1;
                 continue;
             }
 
-            // No more tool uses, done
-            break;
+            // No more tool uses; Stop hooks may send the model back to work
+            let stop = HookInput::new(HookEvent::Stop, session).with_stop_hook_active(stop_hook_active);
+            let outcome = tokio::select! {
+                biased;
+                _ = turn.cancelled() => return Err(self.interruption(cancel)),
+                outcome = self.run_hooks(stop) => outcome,
+            };
+            match outcome.blocked {
+                Some(reason) => {
+                    stop_hook_active = true;
+                    session.add_message(Message::new_user(format!("Stop hook feedback: {}", reason)));
+                }
+                None => break,
+            }
         }
 
         Ok(())
     }
 
-    fn get_system_prompt(&self) -> String {
-        "You are a helpful AI coding assistant with access to tools.".to_string()
+    /// Tools offered in this mode: read-only ones while planning
+    fn available_tools(&self, mode: SessionMode) -> Vec<Arc<dyn Tool>> {
+        let tools = self.tools.tools();
+        match mode {
+            SessionMode::Normal => tools,
+            SessionMode::Plan => tools.into_iter().filter(|tool| tool.is_read_only()).collect(),
+        }
+    }
+
+    fn tool_definitions(&self, tools: &[Arc<dyn Tool>], mode: SessionMode) -> Vec<ToolDefinition> {
+        let mut definitions: Vec<ToolDefinition> = tools
+            .iter()
+            .map(|tool| ToolDefinition {
+                name: tool.name().to_string(),
+                description: tool.description().to_string(),
+                parameters: tool.parameters_schema(),
+            })
+            .collect();
+        if mode == SessionMode::Plan {
+            definitions.push(plan::exit_plan_definition());
+        }
+        definitions
+    }
+
+    /// Count usage toward the turn, whether from a response or from a tool
+    fn add_usage(&self, total: &mut Usage, usage: Usage) {
+        total.input_tokens += usage.input_tokens;
+        total.output_tokens += usage.output_tokens;
+        self.emit(AgentEvent::UsageUpdated { usage, total: *total });
+    }
+
+    /// The limit a turn has run into, if any
+    fn exceeded_limit(&self, responses: usize, total: &Usage, pricing: Option<Pricing>) -> Option<Limit> {
+        if responses >= self.config.max_turns {
+            return Some(Limit::Turns(self.config.max_turns));
+        }
+        let used = total.input_tokens as u64 + total.output_tokens as u64;
+        if let Some(budget) = self.config.token_budget.filter(|budget| used >= *budget) {
+            return Some(Limit::Tokens { used, budget });
+        }
+        if let (Some(budget), Some(pricing)) = (self.config.cost_budget, pricing) {
+            let spent = pricing.cost(total);
+            if spent >= budget {
+                return Some(Limit::Cost { spent, budget });
+            }
+        }
+        None
+    }
+
+    /// Why a turn whose token was cancelled stopped: the user, or the time limit
+    fn interruption(&self, cancel: &CancellationToken) -> AgentError {
+        match self.config.max_duration {
+            Some(limit) if !cancel.is_cancelled() => AgentError::LimitReached(Limit::Duration(limit)),
+            _ => AgentError::Cancelled,
+        }
+    }
+
+    async fn next_response(&self, request: CompletionRequest, streaming: bool) -> Result<CompletionResponse, AIError> {
+        if streaming {
+            self.stream_response(request).await
+        } else {
+            self.provider.complete(request).await
+        }
+    }
+
+    async fn stream_response(&self, request: CompletionRequest) -> Result<CompletionResponse, AIError> {
+        let stream = self.provider.stream_completion(request).await?;
+        collect_stream(stream, &|text| self.emit(AgentEvent::TextDelta { text: text.to_string() })).await
+    }
+
+    fn emit(&self, event: AgentEvent) {
+        for observer in &self.observers {
+            observer.on_event(&event);
+        }
+    }
+
+    /// Path inspected by a read-only tool call, if any
+    fn read_path(&self, tool_use: &ToolUse) -> Option<PathBuf> {
+        let tool = self.tools.get(&tool_use.name)?;
+        let path = tool_use.input.get("path")?.as_str()?;
+        tool.is_read_only().then(|| PathBuf::from(path))
+    }
+
+    /// Add instruction files from directories the model has just looked into
+    fn load_nested_instructions(&self, session: &mut Session, path: &Path) {
+        for file in instructions::nested(session.context(), path) {
+            let loaded = file.path.clone();
+            if session.context_mut().add_instructions(file) {
+                self.emit(AgentEvent::InstructionsLoaded { path: loaded });
+            }
+        }
+    }
+
+    /// Split tool calls into batches that may run together: runs of
+    /// concurrency-safe calls, and each other or unknown call on its own
+    fn batch_tool_uses(&self, tool_uses: Vec<ToolUse>) -> Vec<Vec<ToolUse>> {
+        let mut batches: Vec<Vec<ToolUse>> = Vec::new();
+        let mut previous_concurrent = false;
+
+        for tool_use in tool_uses {
+            let concurrent = self.tools.get(&tool_use.name).is_some_and(|t| t.is_concurrency_safe());
+            match batches.last_mut() {
+                Some(batch) if concurrent && previous_concurrent => batch.push(tool_use),
+                _ => batches.push(vec![tool_use]),
+            }
+            previous_concurrent = concurrent;
+        }
+
+        batches
+    }
+
+    /// Run a batch of tool calls, at most `max_concurrent_tools` at a time,
+    /// returning each call as it ran, with its outcome, in call order
+    async fn call_batch(
+        &self,
+        session: &Session,
+        batch: &[ToolUse],
+        cancel: &CancellationToken,
+    ) -> Vec<(ToolUse, ToolCallOutcome)> {
+        // Collected first: a lazily mapped stream would make the turn future non-Send
+        let calls: Vec<_> = batch
+            .iter()
+            .map(|tool_use| async move {
+                let mut tool_use = tool_use.clone();
+                // Tools that ignore the context's token are dropped mid-call
+                let outcome = tokio::select! {
+                    biased;
+                    _ = cancel.cancelled() => ToolCallOutcome::Interrupted,
+                    outcome = self.call_tool(session, &mut tool_use, cancel) => outcome,
+                };
+                (tool_use, outcome)
+            })
+            .collect();
+        stream::iter(calls)
+            .buffered(self.max_concurrent_tools.max(1))
+            .collect()
+            .await
+    }
+
+    /// Run one tool call. Failures are reported back to the model rather than
+    /// ending the turn, so it can correct the call or try something else.
+    /// Input rewritten by a PreToolUse hook replaces the call's input.
+    async fn call_tool(&self, session: &Session, tool_use: &mut ToolUse, cancel: &CancellationToken) -> ToolCallOutcome {
+        let planning = session.context().mode() == SessionMode::Plan;
+        if planning && tool_use.name == EXIT_PLAN_TOOL_NAME {
+            return self.review_plan(tool_use).await;
+        }
+
+        let Some(tool) = self.tools.get(&tool_use.name) else {
+            return ToolCallOutcome::Failed(format!(
+                "Tool not found: {}. Available tools: {}",
+                tool_use.name,
+                self.tools.names().join(", ")
+            ));
+        };
+
+        if planning && !tool.is_read_only() {
+            return ToolCallOutcome::Refused(format!(
+                "{} is not available in plan mode. Explore with read-only tools, then call {} with your plan.",
+                tool_use.name, EXIT_PLAN_TOOL_NAME
+            ));
+        }
+
+        let pre = self
+            .run_hooks(HookInput::new(HookEvent::PreToolUse, session).with_tool(&tool_use.name, &tool_use.input))
+            .await;
+        if let Some(reason) = pre.blocked {
+            return ToolCallOutcome::Refused(format!("Blocked by hook: {}", reason));
+        }
+        if let Some(input) = pre.tool_input {
+            tool_use.input = input;
+        }
+
+        if let Err(e) = tool.validate(&tool_use.input) {
+            return ToolCallOutcome::Failed(e.to_string());
+        }
+
+        let ctx = self.tool_context(session).with_cancellation(cancel.child_token());
+        let ctx = match self.authorize(tool_use, ctx).await {
+            Ok(ctx) => ctx,
+            Err(refusal) => return ToolCallOutcome::Refused(refusal),
+        };
+        let ctx = if planning { ctx.with_permission(PermissionState::ReadOnly) } else { ctx };
+
+        let ctx = self.with_progress_events(ctx, &tool_use.id);
+        self.emit(AgentEvent::ToolStarted {
+            id: tool_use.id.clone(),
+            name: tool_use.name.clone(),
+        });
+
+        let started = Instant::now();
+        let outcome = match tool.execute(tool_use.input.clone(), &ctx).await {
+            Ok(output) => ToolCallOutcome::Completed(output),
+            Err(ToolError::Cancelled) => ToolCallOutcome::Interrupted,
+            Err(e) => ToolCallOutcome::Failed(e.to_string()),
+        };
+
+        self.emit(AgentEvent::ToolFinished {
+            id: tool_use.id.clone(),
+            name: tool_use.name.clone(),
+            is_error: !matches!(&outcome, ToolCallOutcome::Completed(output) if !output.is_error),
+            duration_ms: started.elapsed().as_millis() as u64,
+        });
+
+        let (content, is_error) = match &outcome {
+            ToolCallOutcome::Completed(output) => (output.content.as_str(), output.is_error),
+            ToolCallOutcome::Failed(error) => (error.as_str(), true),
+            _ => return outcome,
+        };
+        let post = self
+            .run_hooks(
+                HookInput::new(HookEvent::PostToolUse, session)
+                    .with_tool(&tool_use.name, &tool_use.input)
+                    .with_tool_response(content, is_error),
+            )
+            .await;
+
+        // Hook context and feedback go to the model with the result
+        let mut notes = pre.context;
+        notes.extend(post.context);
+        notes.extend(post.blocked.map(|reason| format!("Hook feedback: {}", reason)));
+        if notes.is_empty() {
+            return outcome;
+        }
+        let append = |content: &mut String| {
+            for note in &notes {
+                content.push_str("\n\n");
+                content.push_str(note);
+            }
+        };
+        match outcome {
+            ToolCallOutcome::Completed(mut output) => {
+                append(&mut output.content);
+                ToolCallOutcome::Completed(output)
+            }
+            ToolCallOutcome::Failed(mut error) => {
+                append(&mut error);
+                ToolCallOutcome::Failed(error)
+            }
+            other => other,
+        }
+    }
+
+    /// Run the hooks for an event, reporting those that failed
+    async fn run_hooks(&self, input: HookInput) -> HookOutcome {
+        let Some(hooks) = &self.hooks else {
+            return HookOutcome::default();
+        };
+
+        let event = input.hook_event_name;
+        let outcome = hooks.run(input).await;
+        for message in &outcome.errors {
+            self.emit(AgentEvent::HookFailed {
+                event,
+                message: message.clone(),
+            });
+        }
+        outcome
+    }
+
+    /// Ask the plan reviewer about the plan in an `exit_plan` call
+    async fn review_plan(&self, tool_use: &ToolUse) -> ToolCallOutcome {
+        let Some(plan) = tool_use.input.get("plan").and_then(|p| p.as_str()) else {
+            return ToolCallOutcome::Failed("Invalid parameters: plan is required".to_string());
+        };
+        let Some(reviewer) = &self.plan_reviewer else {
+            return ToolCallOutcome::Refused("No one is available to review the plan; stay in plan mode.".to_string());
+        };
+
+        let _prompt = self.approval_lock.lock().await;
+        match reviewer.review_plan(plan.to_string()).await {
+            PlanDecision::Approve { plan } => ToolCallOutcome::PlanApproved(plan),
+            PlanDecision::Reject { feedback } => ToolCallOutcome::Refused(plan::rejected_message(feedback.as_deref())),
+        }
+    }
+
+    /// Report the tool's progress messages as `AgentEvent::ToolProgress`
+    fn with_progress_events(&self, ctx: ToolContext, id: &str) -> ToolContext {
+        if self.observers.is_empty() {
+            return ctx;
+        }
+
+        let observers = self.observers.clone();
+        let id = id.to_string();
+        ctx.with_progress(ProgressSink::new(move |message| {
+            let event = AgentEvent::ToolProgress {
+                id: id.clone(),
+                message: message.to_string(),
+            };
+            for observer in &observers {
+                observer.on_event(&event);
+            }
+        }))
+    }
+
+    /// Build the context for a tool call, sharing read state across the session's calls
+    fn tool_context(&self, session: &Session) -> ToolContext {
+        let file_state = self
+            .file_states
+            .lock()
+            .unwrap()
+            .entry(session.id())
+            .or_default()
+            .clone();
+        ToolContext::from_session(session)
+            .with_file_state(file_state)
+            .with_todos(self.todo_store(session))
+            .with_checkpoints(self.checkpoint_store(session))
+            .with_path_policy(self.config.path_policy)
+    }
+
+    fn todo_store(&self, session: &Session) -> Arc<TodoStore> {
+        self.todo_lists.lock().unwrap().entry(session.id()).or_default().clone()
+    }
+
+    fn checkpoint_store(&self, session: &Session) -> Arc<CheckpointStore> {
+        if let Some(shared) = &self.shared_checkpoints {
+            return shared.clone();
+        }
+        self.checkpoints.lock().unwrap().entry(session.id()).or_default().clone()
+    }
+
+    /// Files the agent changed since this user message was sent
+    pub fn changed_files(&self, session: &Session, message_id: Uuid) -> Vec<PathBuf> {
+        match user_messages_from(session, message_id) {
+            Some(ids) => self.checkpoint_store(session).changed_files(&ids),
+            None => Vec::new(),
+        }
+    }
+
+    /// Whether file changes since this user message are tracked. Checkpoints
+    /// live in memory, so prompts sent before matw started have none.
+    pub fn has_checkpoint(&self, session: &Session, message_id: Uuid) -> bool {
+        self.checkpoint_store(session).contains(message_id)
+    }
+
+    /// Go back to just before a user message: restore the files the agent
+    /// changed since, deleting those it created, and/or remove the message
+    /// and everything after it from the session
+    pub fn rewind(&self, session: &mut Session, message_id: Uuid, mode: RewindMode) -> Result<Rewound, AgentError> {
+        let ids = user_messages_from(session, message_id)
+            .ok_or_else(|| AgentError::Rewind(format!("no user message with id {}", message_id)))?;
+
+        let mut rewound = Rewound::default();
+        if mode.restores_files() {
+            rewound.files = self
+                .checkpoint_store(session)
+                .rewind(&ids)
+                .map_err(|e| AgentError::Rewind(e.to_string()))?;
+        }
+        if mode.truncates_conversation() {
+            rewound.messages = session.truncate_from(message_id).unwrap_or_default();
+        }
+        Ok(rewound)
+    }
+
+    /// Check a tool call against the permission rules, asking for approval if needed.
+    ///
+    /// Returns the context to run the tool with, or the message to report back
+    /// to the model as the tool result when the call is refused.
+    async fn authorize(&self, tool_use: &ToolUse, ctx: ToolContext) -> Result<ToolContext, String> {
+        let Some(permissions) = &self.permissions else {
+            return Ok(ctx);
+        };
+
+        let check = permissions.check(&tool_use.name, &tool_use.input, &ctx);
+        match check.action {
+            PermissionAction::Allow => {}
+            PermissionAction::Deny => return Err(format!("Permission denied: {}", check.reason)),
+            PermissionAction::Ask => {
+                let Some(approval) = &self.approval else {
+                    return Err(format!("Permission denied: {} and no approver is available", check.reason));
+                };
+
+                let request = ApprovalRequest {
+                    tool_name: tool_use.name.clone(),
+                    input: tool_use.input.clone(),
+                    reason: check.reason,
+                };
+                let _prompt = self.approval_lock.lock().await;
+                self.emit(AgentEvent::PermissionAsked {
+                    id: tool_use.id.clone(),
+                    name: tool_use.name.clone(),
+                    reason: request.reason.clone(),
+                });
+                match approval.request_approval(request).await {
+                    ApprovalDecision::AllowOnce => {}
+                    ApprovalDecision::AllowForSession => {
+                        permissions.allow_for_session(&tool_use.name, &tool_use.input, &ctx);
+                    }
+                    ApprovalDecision::Deny { feedback } => {
+                        let mut refusal = "The user denied this tool call.".to_string();
+                        if let Some(feedback) = feedback.filter(|f| !f.trim().is_empty()) {
+                            refusal.push_str(&format!(" Feedback: {}", feedback.trim()));
+                        }
+                        return Err(refusal);
+                    }
+                }
+            }
+        }
+
+        Ok(ctx.with_permission(PermissionState::Granted))
     }
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum AgentError {
-    #[error("Max iterations reached")]
-    MaxIterationsReached,
+    #[error("Stopped: {0}")]
+    LimitReached(Limit),
 
     #[error("No user message found")]
     NoUserMessage,
@@ -114,9 +883,959 @@ pub enum AgentError {
     #[error("AI provider error: {0}")]
     AIProvider(String),
 
-    #[error("Tool not found: {0}")]
-    ToolNotFound(String),
+    #[error("Stopped after {count} failed tool calls in a row; last error: {last_error}")]
+    TooManyToolFailures { count: usize, last_error: String },
+
+    #[error("Cancelled")]
+    Cancelled,
+
+    #[error("Configuration error: {0}")]
+    Config(String),
+
+    #[error("Rewind failed: {0}")]
+    Rewind(String),
 
-    #[error("Tool execution error: {0}")]
-    ToolExecution(String),
+    #[error("Prompt blocked by hook: {0}")]
+    PromptBlocked(String),
+}
+
+/// Ids of the user messages from `message_id` on, if it is a user message
+fn user_messages_from(session: &Session, message_id: Uuid) -> Option<Vec<Uuid>> {
+    let messages = session.messages();
+    let start = messages
+        .iter()
+        .position(|m| m.id() == message_id && m.role() == Role::User)?;
+    Some(
+        messages[start..]
+            .iter()
+            .filter(|m| m.role() == Role::User)
+            .map(|m| m.id())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permission::PermissionRule;
+    use async_trait::async_trait;
+    use matw_ai::{Chunk, ChunkStream, StopReason, Usage};
+    use matw_core::Content;
+    use matw_tools::tools::{ReadTool, TodoWriteTool, WriteTool};
+    use matw_tools::{PathPolicy, Tool};
+    use std::sync::Mutex;
+
+    /// Provider that replays a fixed sequence of responses
+    struct ScriptedProvider {
+        responses: Mutex<Vec<CompletionResponse>>,
+    }
+
+    impl ScriptedProvider {
+        fn new(mut responses: Vec<CompletionResponse>) -> Self {
+            responses.reverse();
+            Self {
+                responses: Mutex::new(responses),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl AIProvider for ScriptedProvider {
+        fn name(&self) -> &str {
+            "scripted"
+        }
+
+        /// Streams the next response as one delta per word, then its tool uses
+        async fn stream_completion(&self, request: CompletionRequest) -> Result<ChunkStream, AIError> {
+            let response = self.complete(request).await?;
+            let mut chunks: Vec<Chunk> = response
+                .content
+                .split_inclusive(' ')
+                .map(|word| Chunk::Delta(word.to_string()))
+                .collect();
+            chunks.extend(response.tool_uses.into_iter().map(Chunk::ToolUse));
+            chunks.push(Chunk::Stop {
+                reason: response.stop_reason,
+                usage: response.usage,
+            });
+            Ok(ChunkStream::new(Box::pin(futures::stream::iter(chunks.into_iter().map(Ok)))))
+        }
+
+        async fn complete(&self, _request: CompletionRequest) -> Result<CompletionResponse, AIError> {
+            self.responses
+                .lock()
+                .unwrap()
+                .pop()
+                .ok_or_else(|| AIError::InvalidResponse("script exhausted".to_string()))
+        }
+    }
+
+    struct FixedApproval(ApprovalDecision);
+
+    #[async_trait]
+    impl ApprovalHandler for FixedApproval {
+        async fn request_approval(&self, _request: ApprovalRequest) -> ApprovalDecision {
+            self.0.clone()
+        }
+    }
+
+    fn response(content: &str, tool_uses: Vec<ToolUse>) -> CompletionResponse {
+        CompletionResponse {
+            content: content.to_string(),
+            stop_reason: if tool_uses.is_empty() { StopReason::EndTurn } else { StopReason::ToolUse },
+            tool_uses,
+            usage: Usage {
+                input_tokens: 0,
+                output_tokens: 0,
+            },
+        }
+    }
+
+    fn write_call(path: &str) -> ToolUse {
+        ToolUse {
+            id: "call_1".to_string(),
+            name: "write".to_string(),
+            input: serde_json::json!({"path": path, "content": "data"}),
+        }
+    }
+
+    async fn run_write(temp: &tempfile::TempDir, agent: Agent<ScriptedProvider>) -> (Session, bool) {
+        let mut session = Session::new(temp.path().to_path_buf());
+        session.add_message(Message::new_user("write a file".to_string()));
+        agent.process(&mut session, &CancellationToken::new()).await.unwrap();
+        (session, temp.path().join("out.txt").exists())
+    }
+
+    fn registry(tools: Vec<Arc<dyn Tool>>) -> Arc<ToolRegistry> {
+        Arc::new(ToolRegistry::from_tools(tools).unwrap())
+    }
+
+    fn scripted_write() -> ScriptedProvider {
+        ScriptedProvider::new(vec![response("", vec![write_call("out.txt")]), response("done", vec![])])
+    }
+
+    fn tool_result(session: &Session) -> (String, bool) {
+        session
+            .messages()
+            .iter()
+            .find_map(|m| match m.content() {
+                Content::ToolResult { content, is_error, .. } => Some((content.clone(), *is_error)),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_denied_call_reports_feedback() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let agent = Agent::new(scripted_write(), registry(vec![Arc::new(WriteTool::new())]))
+            .with_permissions(PermissionEngine::default())
+            .with_approval_handler(Arc::new(FixedApproval(ApprovalDecision::Deny {
+                feedback: Some("use a different name".to_string()),
+            })));
+
+        let (session, written) = run_write(&temp, agent).await;
+
+        let (content, is_error) = tool_result(&session);
+        assert!(!written);
+        assert!(is_error);
+        assert!(content.contains("use a different name"));
+    }
+
+    #[tokio::test]
+    async fn test_approved_call_runs() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let agent = Agent::new(scripted_write(), registry(vec![Arc::new(WriteTool::new())]))
+            .with_permissions(PermissionEngine::default())
+            .with_approval_handler(Arc::new(FixedApproval(ApprovalDecision::AllowOnce)));
+
+        let (session, written) = run_write(&temp, agent).await;
+
+        assert!(written);
+        assert!(!tool_result(&session).1);
+    }
+
+    #[tokio::test]
+    async fn test_read_state_persists_across_calls() {
+        let temp = tempfile::TempDir::new().unwrap();
+        std::fs::write(temp.path().join("out.txt"), "old").unwrap();
+        let read = ToolUse {
+            id: "call_0".to_string(),
+            name: "read".to_string(),
+            input: serde_json::json!({"path": "out.txt"}),
+        };
+        let provider = ScriptedProvider::new(vec![
+            response("", vec![read]),
+            response("", vec![write_call("out.txt")]),
+            response("done", vec![]),
+        ]);
+        let tools: Vec<Arc<dyn Tool>> = vec![Arc::new(ReadTool::new()), Arc::new(WriteTool::new())];
+
+        run_write(&temp, Agent::new(provider, registry(tools))).await;
+
+        assert_eq!(std::fs::read_to_string(temp.path().join("out.txt")).unwrap(), "data");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_input_reported_to_model() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let call = ToolUse {
+            id: "call_1".to_string(),
+            name: "write".to_string(),
+            input: serde_json::json!({"path": "out.txt"}),
+        };
+        let provider = ScriptedProvider::new(vec![response("", vec![call]), response("done", vec![])]);
+
+        let (session, written) = run_write(&temp, Agent::new(provider, registry(vec![Arc::new(WriteTool::new())]))).await;
+
+        assert!(!written);
+        assert_eq!(
+            tool_result(&session),
+            ("Invalid parameters: `content`: is required".to_string(), true)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deny_rule_skips_approval() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let engine = PermissionEngine::new(vec![PermissionRule::new("write", PermissionAction::Deny)]);
+        let agent = Agent::new(scripted_write(), registry(vec![Arc::new(WriteTool::new())]))
+            .with_permissions(engine)
+            .with_approval_handler(Arc::new(FixedApproval(ApprovalDecision::AllowOnce)));
+
+        let (session, written) = run_write(&temp, agent).await;
+
+        assert!(!written);
+        assert!(tool_result(&session).0.starts_with("Permission denied"));
+    }
+
+    #[tokio::test]
+    async fn test_path_policy_reaches_tools() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let project = tempfile::TempDir::new_in(temp.path()).unwrap();
+        let provider = ScriptedProvider::new(vec![
+            response("", vec![write_call("../out.txt")]),
+            response("done", vec![]),
+        ]);
+        let agent = Agent::new(provider, registry(vec![Arc::new(WriteTool::new())])).with_config(AgentConfig {
+            path_policy: PathPolicy::Deny,
+            ..Default::default()
+        });
+
+        let (session, _) = run_write(&project, agent).await;
+
+        assert!(!temp.path().join("out.txt").exists());
+        assert!(tool_result(&session).0.starts_with("Permission denied"));
+    }
+
+    fn call(id: &str, name: &str, input: serde_json::Value) -> ToolUse {
+        ToolUse {
+            id: id.to_string(),
+            name: name.to_string(),
+            input,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_tool_reported_to_model() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let provider = ScriptedProvider::new(vec![
+            response("", vec![call("call_1", "edit", serde_json::json!({}))]),
+            response("", vec![write_call("out.txt")]),
+            response("done", vec![]),
+        ]);
+
+        let (session, written) = run_write(&temp, Agent::new(provider, registry(vec![Arc::new(WriteTool::new())]))).await;
+
+        assert!(written);
+        assert_eq!(
+            tool_result(&session),
+            ("Tool not found: edit. Available tools: write".to_string(), true)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execution_error_reported_to_model() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let provider = ScriptedProvider::new(vec![
+            response("", vec![call("call_1", "read", serde_json::json!({"path": "missing.txt"}))]),
+            response("done", vec![]),
+        ]);
+        let agent = Agent::new(provider, registry(vec![Arc::new(ReadTool::new())]));
+
+        let (session, _) = run_write(&temp, agent).await;
+
+        let (content, is_error) = tool_result(&session);
+        assert!(is_error);
+        assert_eq!(content, "Not found: missing.txt");
+        assert_eq!(session.messages().last().unwrap().content(), &Content::Text("done".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_consecutive_failures_abort_turn() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let provider = ScriptedProvider::new(vec![
+            response("", vec![call("call_1", "edit", serde_json::json!({}))]),
+            response("", vec![call("call_2", "edit", serde_json::json!({}))]),
+            response("done", vec![]),
+        ]);
+        let agent = Agent::new(provider, registry(vec![]))
+            .with_failure_policy(FailurePolicy { max_consecutive_failures: Some(2) });
+        let mut session = Session::new(temp.path().to_path_buf());
+        session.add_message(Message::new_user("edit a file".to_string()));
+
+        let err = agent.process(&mut session, &CancellationToken::new()).await.unwrap_err();
+
+        assert!(matches!(err, AgentError::TooManyToolFailures { count: 2, .. }));
+        let results = session
+            .messages()
+            .iter()
+            .filter(|m| matches!(m.content(), Content::ToolResult { .. }))
+            .count();
+        assert_eq!(results, 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_batch_is_recorded_before_abort() {
+        let temp = tempfile::TempDir::new().unwrap();
+        std::fs::write(temp.path().join("c.txt"), "c").unwrap();
+        let read = |id: &str, path: &str| call(id, "read", serde_json::json!({"path": path}));
+        let provider = ScriptedProvider::new(vec![response(
+            "",
+            vec![read("call_1", "a.txt"), read("call_2", "b.txt"), read("call_3", "c.txt")],
+        )]);
+        let agent = Agent::new(provider, registry(vec![Arc::new(ReadTool::new())]))
+            .with_failure_policy(FailurePolicy { max_consecutive_failures: Some(2) });
+        let mut session = Session::new(temp.path().to_path_buf());
+        session.add_message(Message::new_user("read the files".to_string()));
+
+        let err = agent.process(&mut session, &CancellationToken::new()).await.unwrap_err();
+
+        assert!(matches!(err, AgentError::TooManyToolFailures { count: 2, .. }));
+        let results: Vec<(&str, bool)> = session
+            .messages()
+            .iter()
+            .filter_map(|m| match m.content() {
+                Content::ToolResult { id, is_error, .. } => Some((id.as_str(), *is_error)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(results, vec![("call_1", true), ("call_2", true), ("call_3", false)]);
+        let uses = session
+            .messages()
+            .iter()
+            .filter(|m| matches!(m.content(), Content::ToolUse { .. }))
+            .count();
+        assert_eq!(uses, 3);
+    }
+
+    /// Observer that keeps every event it sees
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<AgentEvent>>);
+
+    impl AgentObserver for Recorder {
+        fn on_event(&self, event: &AgentEvent) {
+            self.0.lock().unwrap().push(event.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streaming_turn_runs_tools() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let provider = ScriptedProvider::new(vec![
+            response("Writing the file.", vec![write_call("out.txt")]),
+            response("All done.", vec![]),
+        ]);
+        let recorder = Arc::new(Recorder::default());
+        let agent = Agent::new(provider, registry(vec![Arc::new(WriteTool::new())])).with_observer(recorder.clone());
+        let mut session = Session::new(temp.path().to_path_buf());
+        session.add_message(Message::new_user("write a file".to_string()));
+
+        agent.process_streaming(&mut session, &CancellationToken::new()).await.unwrap();
+
+        let deltas: Vec<String> = recorder
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|e| match e {
+                AgentEvent::TextDelta { text } => Some(text.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(deltas, vec!["Writing ", "the ", "file.", "All ", "done."]);
+        assert!(temp.path().join("out.txt").exists());
+        assert!(!tool_result(&session).1);
+        assert_eq!(session.messages().last().unwrap().content(), &Content::Text("All done.".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_turn_events() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let recorder = Arc::new(Recorder::default());
+        let agent = Agent::new(scripted_write(), registry(vec![Arc::new(WriteTool::new())]))
+            .with_permissions(PermissionEngine::default())
+            .with_approval_handler(Arc::new(FixedApproval(ApprovalDecision::AllowOnce)))
+            .with_observer(recorder.clone());
+
+        run_write(&temp, agent).await;
+
+        let events: Vec<String> = recorder
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|e| serde_json::to_value(e).unwrap()["type"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                "turn_started",
+                "usage_updated",
+                "tool_call_requested",
+                "permission_asked",
+                "tool_started",
+                "tool_finished",
+                "usage_updated",
+                "turn_completed",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_turn_reports_error() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let recorder = Arc::new(Recorder::default());
+        let agent = Agent::new(ScriptedProvider::new(vec![]), registry(vec![])).with_observer(recorder.clone());
+        let mut session = Session::new(temp.path().to_path_buf());
+        session.add_message(Message::new_user("hi".to_string()));
+
+        assert!(agent.process(&mut session, &CancellationToken::new()).await.is_err());
+
+        let events = recorder.0.lock().unwrap();
+        assert!(matches!(events[1], AgentEvent::Error { .. }));
+        assert!(matches!(events[2], AgentEvent::TurnCompleted { .. }));
+    }
+
+    /// Tool that sleeps for `delay_ms` and records how many calls overlap
+    struct ProbeTool {
+        name: &'static str,
+        read_only: bool,
+        in_flight: Arc<std::sync::atomic::AtomicUsize>,
+        peak: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Tool for ProbeTool {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn description(&self) -> &str {
+            "probe"
+        }
+
+        fn parameters_schema(&self) -> serde_json::Value {
+            serde_json::json!({"type": "object"})
+        }
+
+        fn is_read_only(&self) -> bool {
+            self.read_only
+        }
+
+        async fn execute(&self, input: serde_json::Value, _ctx: &ToolContext) -> Result<ToolOutput, matw_tools::ToolError> {
+            use std::sync::atomic::Ordering;
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(input["delay_ms"].as_u64().unwrap())).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(ToolOutput {
+                content: input["tag"].as_str().unwrap().to_string(),
+                is_error: false,
+                usage: None,
+            })
+        }
+    }
+
+    /// Run three probe calls whose later calls finish first; returns the
+    /// peak concurrency and the tool results in transcript order
+    async fn run_probes(read_only: bool, limit: usize) -> (usize, Vec<String>) {
+        let peak = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let probe = ProbeTool {
+            name: "probe",
+            read_only,
+            in_flight: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
+            peak: peak.clone(),
+        };
+        let calls = (0..3u64)
+            .map(|i| {
+                let input = serde_json::json!({"tag": format!("call_{}", i), "delay_ms": 60 - i * 20});
+                call(&format!("call_{}", i), "probe", input)
+            })
+            .collect();
+        let provider = ScriptedProvider::new(vec![response("", calls), response("done", vec![])]);
+        let agent = Agent::new(provider, registry(vec![Arc::new(probe)])).with_max_concurrent_tools(limit);
+        let temp = tempfile::TempDir::new().unwrap();
+
+        let (session, _) = run_write(&temp, agent).await;
+
+        let results = session
+            .messages()
+            .iter()
+            .filter_map(|m| match m.content() {
+                Content::ToolResult { content, .. } => Some(content.clone()),
+                _ => None,
+            })
+            .collect();
+        (peak.load(std::sync::atomic::Ordering::SeqCst), results)
+    }
+
+    #[tokio::test]
+    async fn test_read_only_calls_run_concurrently_in_order() {
+        let (peak, results) = run_probes(true, 4).await;
+        assert_eq!(peak, 3);
+        assert_eq!(results, vec!["call_0", "call_1", "call_2"]);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit() {
+        let (peak, results) = run_probes(true, 2).await;
+        assert_eq!(peak, 2);
+        assert_eq!(results, vec!["call_0", "call_1", "call_2"]);
+    }
+
+    #[tokio::test]
+    async fn test_mutating_calls_run_serially() {
+        let (peak, results) = run_probes(false, 4).await;
+        assert_eq!(peak, 1);
+        assert_eq!(results, vec!["call_0", "call_1", "call_2"]);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_tool_calls_get_interrupted_results() {
+        let probe = ProbeTool {
+            name: "probe",
+            read_only: false,
+            in_flight: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
+            peak: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
+        };
+        let slow = serde_json::json!({"tag": "slow", "delay_ms": 10_000});
+        let provider = ScriptedProvider::new(vec![
+            response("", vec![call("call_1", "probe", slow.clone()), call("call_2", "probe", slow)]),
+            response("done", vec![]),
+        ]);
+        let agent = Agent::new(provider, registry(vec![Arc::new(probe)]));
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut session = Session::new(temp.path().to_path_buf());
+        session.add_message(Message::new_user("probe".to_string()));
+        let cancel = CancellationToken::new();
+        let trigger = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            trigger.cancel();
+        });
+
+        let err = agent.process(&mut session, &cancel).await.unwrap_err();
+
+        assert!(matches!(err, AgentError::Cancelled));
+        let results: Vec<(String, bool)> = session
+            .messages()
+            .iter()
+            .filter_map(|m| match m.content() {
+                Content::ToolResult { content, is_error, .. } => Some((content.clone(), *is_error)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(results, vec![(INTERRUPTED.to_string(), true), (INTERRUPTED.to_string(), true)]);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_before_response() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let agent = Agent::new(scripted_write(), registry(vec![Arc::new(WriteTool::new())]));
+        let mut session = Session::new(temp.path().to_path_buf());
+        session.add_message(Message::new_user("write a file".to_string()));
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let err = agent.process(&mut session, &cancel).await.unwrap_err();
+
+        assert!(matches!(err, AgentError::Cancelled));
+        assert_eq!(session.messages().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_reading_nested_file_loads_its_instructions() {
+        let temp = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(temp.path().join("web")).unwrap();
+        std::fs::write(temp.path().join("web/MATW.md"), "Use pnpm.").unwrap();
+        std::fs::write(temp.path().join("web/index.ts"), "export {}").unwrap();
+        let provider = ScriptedProvider::new(vec![
+            response("", vec![call("call_1", "read", serde_json::json!({"path": "web/index.ts"}))]),
+            response("done", vec![]),
+        ]);
+        let recorder = Arc::new(Recorder::default());
+        let agent = Agent::new(provider, registry(vec![Arc::new(ReadTool::new())])).with_observer(recorder.clone());
+
+        let (session, _) = run_write(&temp, agent).await;
+
+        let loaded = session.context().instructions();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].content, "Use pnpm.");
+        assert!(recorder
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|e| matches!(e, AgentEvent::InstructionsLoaded { .. })));
+    }
+
+    fn probe_session(temp: &tempfile::TempDir) -> Session {
+        let mut session = Session::new(temp.path().to_path_buf());
+        session.add_message(Message::new_user("probe".to_string()));
+        session
+    }
+
+    fn quick_probe() -> (Arc<ToolRegistry>, serde_json::Value) {
+        let probe = ProbeTool {
+            name: "probe",
+            read_only: true,
+            in_flight: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
+            peak: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
+        };
+        (registry(vec![Arc::new(probe)]), serde_json::json!({"tag": "ok", "delay_ms": 0}))
+    }
+
+    #[tokio::test]
+    async fn test_turn_limit_stops_after_tool_results() {
+        let (tools, input) = quick_probe();
+        let provider = ScriptedProvider::new(vec![
+            response("", vec![call("call_1", "probe", input.clone())]),
+            response("", vec![call("call_2", "probe", input.clone())]),
+            response("", vec![call("call_3", "probe", input)]),
+        ]);
+        let agent = Agent::new(provider, tools).with_config(AgentConfig {
+            max_turns: 2,
+            ..Default::default()
+        });
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut session = probe_session(&temp);
+
+        let err = agent.process(&mut session, &CancellationToken::new()).await.unwrap_err();
+
+        assert!(matches!(err, AgentError::LimitReached(Limit::Turns(2))));
+        assert_eq!(err.to_string(), "Stopped: reached the limit of 2 model turns");
+        let results = session
+            .messages()
+            .iter()
+            .filter(|m| matches!(m.content(), Content::ToolResult { .. }))
+            .count();
+        assert_eq!(results, 2);
+    }
+
+    #[tokio::test]
+    async fn test_token_and_cost_budgets() {
+        let (tools, input) = quick_probe();
+        let mut first = response("", vec![call("call_1", "probe", input)]);
+        first.usage = Usage {
+            input_tokens: 900,
+            output_tokens: 200,
+        };
+        let config = AgentConfig {
+            token_budget: Some(1000),
+            ..Default::default()
+        };
+        let agent = Agent::new(ScriptedProvider::new(vec![first.clone(), response("done", vec![])]), tools.clone())
+            .with_config(config);
+        let temp = tempfile::TempDir::new().unwrap();
+
+        let err = agent.process(&mut probe_session(&temp), &CancellationToken::new()).await.unwrap_err();
+        assert!(matches!(err, AgentError::LimitReached(Limit::Tokens { used: 1100, budget: 1000 })));
+
+        let config = AgentConfig {
+            cost_budget: Some(0.001),
+            pricing: Some(Pricing {
+                input_per_mtok: 1.0,
+                output_per_mtok: 1.0,
+            }),
+            ..Default::default()
+        };
+        let agent =
+            Agent::new(ScriptedProvider::new(vec![first, response("done", vec![])]), tools).with_config(config);
+
+        let err = agent.process(&mut probe_session(&temp), &CancellationToken::new()).await.unwrap_err();
+        assert!(matches!(err, AgentError::LimitReached(Limit::Cost { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_cost_budget_needs_pricing() {
+        let agent = Agent::new(ScriptedProvider::new(vec![response("done", vec![])]), registry(vec![])).with_config(
+            AgentConfig {
+                cost_budget: Some(1.0),
+                ..Default::default()
+            },
+        );
+        let temp = tempfile::TempDir::new().unwrap();
+
+        let err = agent.process(&mut probe_session(&temp), &CancellationToken::new()).await.unwrap_err();
+
+        assert!(matches!(err, AgentError::Config(_)));
+    }
+
+    #[tokio::test]
+    async fn test_time_limit_interrupts_tool_calls() {
+        let probe = ProbeTool {
+            name: "probe",
+            read_only: false,
+            in_flight: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
+            peak: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
+        };
+        let slow = serde_json::json!({"tag": "slow", "delay_ms": 10_000});
+        let provider = ScriptedProvider::new(vec![response("", vec![call("call_1", "probe", slow)])]);
+        let agent = Agent::new(provider, registry(vec![Arc::new(probe)])).with_config(AgentConfig {
+            max_duration: Some(std::time::Duration::from_millis(50)),
+            ..Default::default()
+        });
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut session = probe_session(&temp);
+
+        let err = agent.process(&mut session, &CancellationToken::new()).await.unwrap_err();
+
+        assert!(matches!(err, AgentError::LimitReached(Limit::Duration(_))));
+        assert_eq!(tool_result(&session), (INTERRUPTED.to_string(), true));
+    }
+
+    struct FixedReview(PlanDecision);
+
+    #[async_trait]
+    impl PlanReviewer for FixedReview {
+        async fn review_plan(&self, _plan: String) -> PlanDecision {
+            self.0.clone()
+        }
+    }
+
+    async fn run_planning(temp: &tempfile::TempDir, decision: PlanDecision) -> Session {
+        let provider = ScriptedProvider::new(vec![
+            response("", vec![call("call_1", "exit_plan", serde_json::json!({"plan": "1. Write out.txt"}))]),
+            response("", vec![write_call("out.txt")]),
+            response("done", vec![]),
+        ]);
+        let agent = Agent::new(provider, registry(vec![Arc::new(WriteTool::new())]))
+            .with_plan_reviewer(Arc::new(FixedReview(decision)));
+        let mut session = Session::new(temp.path().to_path_buf());
+        session.context_mut().set_mode(SessionMode::Plan);
+        session.add_message(Message::new_user("write a file".to_string()));
+        agent.process(&mut session, &CancellationToken::new()).await.unwrap();
+        session
+    }
+
+    #[tokio::test]
+    async fn test_plan_mode_refuses_writes() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let agent = Agent::new(scripted_write(), registry(vec![Arc::new(WriteTool::new())]));
+        let mut session = Session::new(temp.path().to_path_buf());
+        session.context_mut().set_mode(SessionMode::Plan);
+        session.add_message(Message::new_user("write a file".to_string()));
+
+        agent.process(&mut session, &CancellationToken::new()).await.unwrap();
+
+        let (content, is_error) = tool_result(&session);
+        assert!(is_error);
+        assert!(content.contains("not available in plan mode"));
+        assert!(!temp.path().join("out.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_approved_plan_ends_plan_mode() {
+        let temp = tempfile::TempDir::new().unwrap();
+
+        let session = run_planning(
+            &temp,
+            PlanDecision::Approve {
+                plan: "1. Write out.txt with care".to_string(),
+            },
+        )
+        .await;
+
+        assert_eq!(session.context().mode(), SessionMode::Normal);
+        assert_eq!(session.context().plan(), Some("1. Write out.txt with care"));
+        assert!(!tool_result(&session).1);
+        assert!(temp.path().join("out.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_rejected_plan_stays_in_plan_mode() {
+        let temp = tempfile::TempDir::new().unwrap();
+
+        let session = run_planning(
+            &temp,
+            PlanDecision::Reject {
+                feedback: Some("check the tests first".to_string()),
+            },
+        )
+        .await;
+
+        let (content, is_error) = tool_result(&session);
+        assert!(is_error);
+        assert!(content.contains("check the tests first"));
+        assert_eq!(session.context().mode(), SessionMode::Plan);
+        assert_eq!(session.context().plan(), None);
+        assert!(!temp.path().join("out.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_todo_list_saved_in_session() {
+        let todos = serde_json::json!({"todos": [{"content": "Fix the bug", "status": "in_progress"}]});
+        let provider = ScriptedProvider::new(vec![
+            response("", vec![call("call_1", "todo_write", todos)]),
+            response("done", vec![]),
+        ]);
+        let recorder = Arc::new(Recorder::default());
+        let agent = Agent::new(provider, registry(vec![Arc::new(TodoWriteTool::new())])).with_observer(recorder.clone());
+        let temp = tempfile::TempDir::new().unwrap();
+
+        let (session, _) = run_write(&temp, agent).await;
+
+        // The list survives saving and reloading the session
+        let dir = temp.path().join("sessions");
+        session.save(&dir).unwrap();
+        let reloaded = Session::load_from(&dir, session.id()).unwrap();
+        assert_eq!(
+            reloaded.context().todos(),
+            [matw_core::Todo::new("Fix the bug", matw_core::TodoStatus::InProgress)]
+        );
+        assert!(recorder
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|e| matches!(e, AgentEvent::TodosUpdated { todos } if todos.len() == 1)));
+    }
+
+    #[tokio::test]
+    async fn test_rewind_restores_files_and_conversation() {
+        let provider = ScriptedProvider::new(vec![
+            response("", vec![write_call("out.txt")]),
+            response("done", vec![]),
+            response("", vec![write_call("other.txt")]),
+            response("done", vec![]),
+        ]);
+        let agent = Agent::new(provider, registry(vec![Arc::new(WriteTool::new())]));
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut session = Session::new(temp.path().to_path_buf());
+        session.add_message(Message::new_user("write a file".to_string()));
+        let first = session.messages()[0].id();
+        agent.process(&mut session, &CancellationToken::new()).await.unwrap();
+        let second = Message::new_user("write another".to_string());
+        let second_id = second.id();
+        session.add_message(second);
+        let turn_length = session.message_count();
+        agent.process(&mut session, &CancellationToken::new()).await.unwrap();
+
+        assert_eq!(agent.changed_files(&session, first).len(), 2);
+        assert!(agent.has_checkpoint(&session, first));
+        assert!(!agent.has_checkpoint(&session, Uuid::new_v4()));
+        let rewound = agent.rewind(&mut session, second_id, RewindMode::Both).unwrap();
+        assert_eq!(rewound.files, vec![temp.path().join("other.txt")]);
+        assert_eq!(rewound.messages[0].id(), second_id);
+        assert_eq!(session.message_count(), turn_length - 1);
+        assert!(!temp.path().join("other.txt").exists());
+        assert!(temp.path().join("out.txt").exists());
+
+        agent.rewind(&mut session, first, RewindMode::Files).unwrap();
+        assert!(!temp.path().join("out.txt").exists());
+        assert_eq!(session.message_count(), turn_length - 1);
+        assert!(agent.rewind(&mut session, second_id, RewindMode::Both).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pre_tool_use_hook_blocks_call() {
+        use crate::hooks::Hook;
+
+        let hooks = HookRunner::new(vec![
+            Hook::new(HookEvent::PreToolUse, "echo 'out.txt is generated' >&2; exit 2").with_matcher("write"),
+        ]);
+        let agent = Agent::new(scripted_write(), registry(vec![Arc::new(WriteTool::new())])).with_hooks(hooks);
+        let temp = tempfile::TempDir::new().unwrap();
+
+        let (session, written) = run_write(&temp, agent).await;
+
+        assert!(!written);
+        assert_eq!(
+            tool_result(&session),
+            ("Blocked by hook: out.txt is generated".to_string(), true)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pre_tool_use_hook_rewrites_input() {
+        use crate::hooks::Hook;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(temp.path().join("web")).unwrap();
+        std::fs::write(temp.path().join("web/MATW.md"), "Use pnpm.").unwrap();
+        std::fs::write(temp.path().join("web/index.ts"), "export {}").unwrap();
+        let hooks = HookRunner::new(vec![
+            Hook::new(HookEvent::PreToolUse, r#"echo '{"tool_input": {"path": "web/index.ts"}}'"#).with_matcher("read"),
+        ]);
+        let provider = ScriptedProvider::new(vec![
+            response("", vec![call("call_1", "read", serde_json::json!({"path": "index.ts"}))]),
+            response("done", vec![]),
+        ]);
+        let agent = Agent::new(provider, registry(vec![Arc::new(ReadTool::new())])).with_hooks(hooks);
+
+        let (session, _) = run_write(&temp, agent).await;
+
+        // The transcript records the call as it ran, and nested instructions follow the new path
+        let input = session
+            .messages()
+            .iter()
+            .find_map(|m| match m.content() {
+                Content::ToolUse { input, .. } => Some(input.clone()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(input, serde_json::json!({"path": "web/index.ts"}));
+        assert!(tool_result(&session).0.contains("export {}"));
+        assert_eq!(session.context().instructions()[0].content, "Use pnpm.");
+    }
+
+    #[tokio::test]
+    async fn test_prompt_and_stop_hooks() {
+        use crate::hooks::Hook;
+
+        let hooks = HookRunner::new(vec![
+            Hook::new(HookEvent::SessionStart, "echo 'Branch: main'"),
+            Hook::new(HookEvent::UserPromptSubmit, "grep -q secret && exit 2; true"),
+            // Sends the model back once, then lets it stop
+            Hook::new(
+                HookEvent::Stop,
+                r#"grep -q '"stop_hook_active":true' || echo '{"decision": "block", "reason": "run the tests"}'"#,
+            ),
+        ]);
+        let provider = ScriptedProvider::new(vec![response("done", vec![]), response("tests pass", vec![])]);
+        let agent = Agent::new(provider, registry(vec![])).with_hooks(hooks);
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut session = Session::new(temp.path().to_path_buf());
+
+        let blocked = agent.submit_prompt(&mut session, "print the secret".to_string()).await;
+        assert!(matches!(blocked, Err(AgentError::PromptBlocked(_))));
+        assert_eq!(session.message_count(), 0);
+
+        agent.submit_prompt(&mut session, "fix it".to_string()).await.unwrap();
+        agent.process(&mut session, &CancellationToken::new()).await.unwrap();
+
+        let texts: Vec<String> = session
+            .messages()
+            .iter()
+            .map(|m| match m.content() {
+                Content::Text(text) => text.clone(),
+                _ => String::new(),
+            })
+            .collect();
+        assert_eq!(
+            texts,
+            vec!["fix it\n\nBranch: main", "done", "Stop hook feedback: run the tests", "tests pass"]
+        );
+    }
 }
@@ -0,0 +1,126 @@
+//! Agent configuration
+//!
+//! `AgentConfig` holds the model settings sent with every request and the
+//! limits that end a turn early: model turns, wall-clock time, tokens and
+//! cost. Limits apply to a single turn, i.e. one `Agent::process` call.
+
+use matw_ai::Usage;
+use matw_tools::PathPolicy;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+/// Model settings and limits for agent turns
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgentConfig {
+    pub model: String,
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+    /// Model responses per turn
+    pub max_turns: usize,
+    /// Wall-clock time per turn
+    pub max_duration: Option<Duration>,
+    /// Input and output tokens per turn
+    pub token_budget: Option<u64>,
+    /// US dollars per turn; needs `pricing` or a model with known prices
+    pub cost_budget: Option<f64>,
+    /// Prices used for `cost_budget`, overriding the built-in ones
+    pub pricing: Option<Pricing>,
+    /// Whether file tools may touch paths outside the project roots
+    pub path_policy: PathPolicy,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            model: "default".to_string(),
+            max_tokens: Some(4096),
+            temperature: Some(0.7),
+            max_turns: 10,
+            max_duration: None,
+            token_budget: None,
+            cost_budget: None,
+            pricing: None,
+            path_policy: PathPolicy::Flag,
+        }
+    }
+}
+
+impl AgentConfig {
+    /// Prices for the configured model, if known
+    pub fn resolved_pricing(&self) -> Option<Pricing> {
+        self.pricing.or_else(|| Pricing::for_model(&self.model))
+    }
+}
+
+/// Model prices in US dollars per million tokens
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Pricing {
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+}
+
+impl Pricing {
+    /// Built-in prices for well-known models
+    pub fn for_model(model: &str) -> Option<Self> {
+        let (input_per_mtok, output_per_mtok) = if model.starts_with("claude-opus-4") {
+            (15.0, 75.0)
+        } else if model.starts_with("claude-sonnet-4") || model.starts_with("claude-3-7-sonnet") {
+            (3.0, 15.0)
+        } else if model.starts_with("claude-3-5-haiku") {
+            (0.8, 4.0)
+        } else {
+            return None;
+        };
+        Some(Self {
+            input_per_mtok,
+            output_per_mtok,
+        })
+    }
+
+    /// Cost of `usage` in US dollars
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        (usage.input_tokens as f64 * self.input_per_mtok + usage.output_tokens as f64 * self.output_per_mtok)
+            / 1_000_000.0
+    }
+}
+
+/// A limit that ended a turn
+#[derive(Debug, Clone, PartialEq)]
+pub enum Limit {
+    Turns(usize),
+    Duration(Duration),
+    Tokens { used: u64, budget: u64 },
+    Cost { spent: f64, budget: f64 },
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Turns(max) => write!(f, "reached the limit of {} model turns", max),
+            Limit::Duration(max) => write!(f, "ran for longer than {}s", max.as_secs()),
+            Limit::Tokens { used, budget } => write!(f, "used {} tokens of a {} token budget", used, budget),
+            Limit::Cost { spent, budget } => write!(f, "spent ${:.2} of a ${:.2} budget", spent, budget),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pricing() {
+        let config = AgentConfig {
+            model: "claude-sonnet-4-20250514".to_string(),
+            ..Default::default()
+        };
+        let usage = Usage {
+            input_tokens: 1_000_000,
+            output_tokens: 100_000,
+        };
+
+        assert_eq!(config.resolved_pricing().unwrap().cost(&usage), 4.5);
+        assert_eq!(AgentConfig::default().resolved_pricing(), None);
+    }
+}
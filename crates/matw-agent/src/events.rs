@@ -0,0 +1,171 @@
+//! Agent events
+//!
+//! Everything an agent turn does is reported as an `AgentEvent` to the
+//! observers registered with `Agent::with_observer`. The TUI, headless output
+//! and logs all follow a turn through this one API.
+
+use crate::hooks::HookEvent;
+use matw_ai::Usage;
+use matw_core::{SessionMode, Todo};
+use serde::Serialize;
+use serde_json::Value;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// Something that happened during an agent turn
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentEvent {
+    TurnStarted {
+        session_id: Uuid,
+    },
+    /// Streamed text from the model's current response
+    TextDelta {
+        text: String,
+    },
+    /// The model asked for a tool call
+    ToolCallRequested {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    /// The user is being asked to approve a tool call
+    PermissionAsked {
+        id: String,
+        name: String,
+        reason: String,
+    },
+    ToolStarted {
+        id: String,
+        name: String,
+    },
+    ToolProgress {
+        id: String,
+        message: String,
+    },
+    ToolFinished {
+        id: String,
+        name: String,
+        is_error: bool,
+        duration_ms: u64,
+    },
+    /// Token usage of the latest response or tool call, and of the turn so far
+    UsageUpdated {
+        usage: Usage,
+        total: Usage,
+    },
+    /// An instruction file was added to the session context
+    InstructionsLoaded {
+        path: PathBuf,
+    },
+    /// The session's task list was replaced
+    TodosUpdated {
+        todos: Vec<Todo>,
+    },
+    /// The session switched mode, e.g. when a plan was approved
+    ModeChanged {
+        mode: SessionMode,
+    },
+    /// A hook failed without blocking; the action went ahead
+    HookFailed {
+        event: HookEvent,
+        message: String,
+    },
+    /// The turn failed; `TurnCompleted` follows
+    Error {
+        message: String,
+    },
+    TurnCompleted {
+        usage: Usage,
+    },
+}
+
+/// Receives the events of agent turns
+pub trait AgentObserver: Send + Sync {
+    fn on_event(&self, event: &AgentEvent);
+}
+
+impl<F> AgentObserver for F
+where
+    F: Fn(&AgentEvent) + Send + Sync,
+{
+    fn on_event(&self, event: &AgentEvent) {
+        self(event)
+    }
+}
+
+/// Writes each event as a line of JSON, for headless output and logs
+pub struct JsonLinesObserver<W: Write + Send> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write + Send> JsonLinesObserver<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner().unwrap()
+    }
+}
+
+impl<W: Write + Send> AgentObserver for JsonLinesObserver<W> {
+    fn on_event(&self, event: &AgentEvent) {
+        let Ok(line) = serde_json::to_string(event) else {
+            return;
+        };
+        let mut writer = self.writer.lock().unwrap();
+        writeln!(writer, "{}", line).and_then(|_| writer.flush()).ok();
+    }
+}
+
+/// Logs each event through `tracing`: streamed text, progress and usage at
+/// debug level, failures as warnings and everything else as info
+pub struct TracingObserver;
+
+impl AgentObserver for TracingObserver {
+    fn on_event(&self, event: &AgentEvent) {
+        let Ok(event_json) = serde_json::to_string(event) else {
+            return;
+        };
+        match event {
+            AgentEvent::TextDelta { .. } | AgentEvent::ToolProgress { .. } | AgentEvent::UsageUpdated { .. } => {
+                tracing::debug!(target: "matw::agent", "{}", event_json)
+            }
+            AgentEvent::ToolFinished { is_error: true, .. } | AgentEvent::HookFailed { .. } | AgentEvent::Error { .. } => {
+                tracing::warn!(target: "matw::agent", "{}", event_json)
+            }
+            _ => tracing::info!(target: "matw::agent", "{}", event_json),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_lines_output() {
+        let observer = JsonLinesObserver::new(Vec::new());
+        observer.on_event(&AgentEvent::TextDelta {
+            text: "hi".to_string(),
+        });
+        observer.on_event(&AgentEvent::TurnCompleted {
+            usage: Usage {
+                input_tokens: 3,
+                output_tokens: 1,
+            },
+        });
+
+        let output = String::from_utf8(observer.into_inner()).unwrap();
+        assert_eq!(
+            output,
+            "{\"type\":\"text_delta\",\"text\":\"hi\"}\n\
+             {\"type\":\"turn_completed\",\"usage\":{\"input_tokens\":3,\"output_tokens\":1}}\n"
+        );
+    }
+}
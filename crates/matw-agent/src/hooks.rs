@@ -0,0 +1,465 @@
+//! Lifecycle hooks
+//!
+//! Hooks are shell commands run at points of a session: before and after
+//! tool calls, when the user submits a prompt, when a session starts and
+//! when the model is about to end its turn. Each gets the event as JSON on
+//! stdin. Exit code 2 blocks the action with stderr as the reason; exit 0
+//! may print JSON to block, replace the tool input or add context. Any
+//! other exit code is reported and the action goes ahead.
+//!
+//! A project's `.matw/hooks.toml` comes with the repository, so its hooks
+//! only run once the user has approved the file with `matw trust`.
+
+use crate::permission::glob_matches;
+use crate::AgentError;
+use matw_core::{Session, TrustStore};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use uuid::Uuid;
+
+/// Name of the hooks file in `~/.matw/` and `<project>/.matw/`
+pub const HOOKS_FILE: &str = "hooks.toml";
+
+/// Exit code with which a hook blocks the action
+const BLOCK_EXIT_CODE: i32 = 2;
+
+fn default_timeout_secs() -> u64 {
+    60
+}
+
+/// When a hook runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HookEvent {
+    /// Before a tool call; can block it or replace its input
+    PreToolUse,
+    /// After a tool call; can send feedback to the model
+    PostToolUse,
+    /// Before a prompt is sent; can block it or add context
+    UserPromptSubmit,
+    /// Before the first prompt of a session; can add context
+    SessionStart,
+    /// When the model ends its turn; blocking sends it back to work
+    Stop,
+}
+
+impl HookEvent {
+    /// Whether hooks for this event are matched against a tool name
+    fn is_tool_event(self) -> bool {
+        matches!(self, HookEvent::PreToolUse | HookEvent::PostToolUse)
+    }
+
+    /// Whether plain text printed by the hook is added to the model's context
+    fn takes_plain_context(self) -> bool {
+        matches!(self, HookEvent::UserPromptSubmit | HookEvent::SessionStart)
+    }
+}
+
+impl fmt::Display for HookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A configured hook
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hook {
+    pub event: HookEvent,
+    /// Tool name or glob pattern for tool events; all tools if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matcher: Option<String>,
+    /// Run with `sh -c` in the session's working directory
+    pub command: String,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Hook {
+    pub fn new(event: HookEvent, command: impl Into<String>) -> Self {
+        Self {
+            event,
+            matcher: None,
+            command: command.into(),
+            timeout_secs: default_timeout_secs(),
+        }
+    }
+
+    pub fn with_matcher(mut self, matcher: impl Into<String>) -> Self {
+        self.matcher = Some(matcher.into());
+        self
+    }
+
+    pub fn with_timeout_secs(mut self, timeout_secs: u64) -> Self {
+        self.timeout_secs = timeout_secs;
+        self
+    }
+
+    fn matches(&self, input: &HookInput) -> bool {
+        if self.event != input.hook_event_name {
+            return false;
+        }
+        match (&self.matcher, &input.tool_name) {
+            (Some(matcher), Some(tool_name)) if self.event.is_tool_event() => glob_matches(matcher, tool_name),
+            _ => true,
+        }
+    }
+}
+
+/// Hooks as stored in a config file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HookConfig {
+    #[serde(default)]
+    pub hooks: Vec<Hook>,
+}
+
+impl HookConfig {
+    /// Load hooks from a TOML file, or return an empty config if it does not exist
+    pub fn load(path: &Path) -> Result<Self, AgentError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| AgentError::Config(format!("{}: {}", path.display(), e)))?;
+        Self::parse(&content, path)
+    }
+
+    /// Parse hooks read from `path`
+    pub fn parse(content: &str, path: &Path) -> Result<Self, AgentError> {
+        toml::from_str(content).map_err(|e| AgentError::Config(format!("{}: {}", path.display(), e)))
+    }
+}
+
+/// The event a hook gets on stdin
+#[derive(Debug, Clone, Serialize)]
+pub struct HookInput {
+    pub hook_event_name: HookEvent,
+    pub session_id: Uuid,
+    pub cwd: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_input: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_response: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    /// For `Stop`: whether a Stop hook already sent the model back this turn
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_hook_active: Option<bool>,
+}
+
+impl HookInput {
+    pub fn new(event: HookEvent, session: &Session) -> Self {
+        Self {
+            hook_event_name: event,
+            session_id: session.id(),
+            cwd: session.context().working_dir().to_path_buf(),
+            tool_name: None,
+            tool_input: None,
+            tool_response: None,
+            prompt: None,
+            stop_hook_active: None,
+        }
+    }
+
+    pub fn with_tool(mut self, name: &str, input: &Value) -> Self {
+        self.tool_name = Some(name.to_string());
+        self.tool_input = Some(input.clone());
+        self
+    }
+
+    pub fn with_tool_response(mut self, content: &str, is_error: bool) -> Self {
+        self.tool_response = Some(json!({"content": content, "is_error": is_error}));
+        self
+    }
+
+    pub fn with_prompt(mut self, prompt: &str) -> Self {
+        self.prompt = Some(prompt.to_string());
+        self
+    }
+
+    pub fn with_stop_hook_active(mut self, active: bool) -> Self {
+        self.stop_hook_active = Some(active);
+        self
+    }
+}
+
+/// JSON a hook may print on stdout when it exits with 0
+#[derive(Debug, Default, Deserialize)]
+struct HookResponse {
+    /// `"block"` to block the action
+    #[serde(default)]
+    decision: Option<String>,
+    #[serde(default)]
+    reason: Option<String>,
+    /// Replacement input for the tool call, `PreToolUse` only
+    #[serde(default)]
+    tool_input: Option<Value>,
+    #[serde(default)]
+    additional_context: Option<String>,
+}
+
+/// What the hooks for an event decided
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HookOutcome {
+    /// Why a hook blocked the action, if one did
+    pub blocked: Option<String>,
+    /// Tool input as replaced by `PreToolUse` hooks
+    pub tool_input: Option<Value>,
+    /// Text the hooks asked to add to the model's context
+    pub context: Vec<String>,
+    /// Hooks that failed without blocking, for showing to the user
+    pub errors: Vec<String>,
+}
+
+/// Runs the configured hooks for an event
+#[derive(Debug, Clone, Default)]
+pub struct HookRunner {
+    hooks: Vec<Hook>,
+}
+
+impl HookRunner {
+    pub fn new(hooks: Vec<Hook>) -> Self {
+        Self { hooks }
+    }
+
+    /// Load hooks from `~/.matw/hooks.toml`, and from
+    /// `<project>/.matw/hooks.toml` if `trust` approves it as it is
+    pub fn load(project_dir: &Path, trust: &TrustStore) -> Result<Self, AgentError> {
+        let mut runner = Self::default();
+        if let Some(home) = dirs::home_dir() {
+            runner.add_hooks(HookConfig::load(&home.join(".matw").join(HOOKS_FILE))?.hooks);
+        }
+        let project = project_dir.join(".matw").join(HOOKS_FILE);
+        if let Some(content) = trust.read_approved(&project) {
+            runner.add_hooks(HookConfig::parse(&content, &project)?.hooks);
+        }
+        Ok(runner)
+    }
+
+    pub fn add_hooks(&mut self, hooks: impl IntoIterator<Item = Hook>) {
+        self.hooks.extend(hooks);
+    }
+
+    pub fn hooks(&self) -> &[Hook] {
+        &self.hooks
+    }
+
+    /// The tool hooks only, for sub-agents: they have no prompts of their own
+    /// and their turn ending is not the session's
+    pub fn for_subagents(&self) -> Self {
+        Self::new(self.hooks.iter().filter(|h| h.event.is_tool_event()).cloned().collect())
+    }
+
+    /// Run the matching hooks in order until one blocks. A replaced tool
+    /// input is passed on to the hooks after the one that replaced it.
+    pub async fn run(&self, mut input: HookInput) -> HookOutcome {
+        let mut outcome = HookOutcome::default();
+        let event = input.hook_event_name;
+
+        for hook in &self.hooks {
+            if !hook.matches(&input) {
+                continue;
+            }
+            let result = match run_command(hook, &input).await {
+                Ok(result) => result,
+                Err(e) => {
+                    outcome.errors.push(format!("{} hook `{}` {}", event, hook.command, e));
+                    continue;
+                }
+            };
+
+            match result {
+                CommandResult::Blocked(reason) => {
+                    outcome.blocked = Some(reason);
+                    break;
+                }
+                CommandResult::Text(text) => {
+                    if event.takes_plain_context() && !text.is_empty() {
+                        outcome.context.push(text);
+                    }
+                }
+                CommandResult::Json(response) => {
+                    if let Some(context) = response.additional_context.filter(|c| !c.trim().is_empty()) {
+                        outcome.context.push(context);
+                    }
+                    if let Some(tool_input) = response.tool_input.filter(|_| event == HookEvent::PreToolUse) {
+                        input.tool_input = Some(tool_input.clone());
+                        outcome.tool_input = Some(tool_input);
+                    }
+                    if response.decision.as_deref() == Some("block") {
+                        outcome.blocked = Some(
+                            response
+                                .reason
+                                .unwrap_or_else(|| format!("blocked by hook `{}`", hook.command)),
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+
+        outcome
+    }
+}
+
+/// How a hook command finished, if it did not fail
+enum CommandResult {
+    Blocked(String),
+    /// Exit 0 with plain text, possibly empty, on stdout
+    Text(String),
+    Json(HookResponse),
+}
+
+async fn run_command(hook: &Hook, input: &HookInput) -> Result<CommandResult, String> {
+    let payload = serde_json::to_vec(input).map_err(|e| e.to_string())?;
+
+    // The child is killed if the future is dropped on timeout or cancellation
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(&hook.command)
+        .current_dir(&input.cwd)
+        .env("MATW_PROJECT_DIR", &input.cwd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("could not start: {}", e))?;
+
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let run = async move {
+        // A hook may exit without reading its input
+        stdin.write_all(&payload).await.ok();
+        drop(stdin);
+        child.wait_with_output().await
+    };
+    let output = tokio::time::timeout(Duration::from_secs(hook.timeout_secs), run)
+        .await
+        .map_err(|_| format!("timed out after {} s", hook.timeout_secs))?
+        .map_err(|e| e.to_string())?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+
+    match output.status.code() {
+        Some(0) if stdout.starts_with('{') => serde_json::from_str(&stdout)
+            .map(CommandResult::Json)
+            .map_err(|e| format!("printed invalid JSON: {}", e)),
+        Some(0) => Ok(CommandResult::Text(stdout)),
+        Some(BLOCK_EXIT_CODE) if stderr.is_empty() => {
+            Ok(CommandResult::Blocked(format!("blocked by hook `{}`", hook.command)))
+        }
+        Some(BLOCK_EXIT_CODE) => Ok(CommandResult::Blocked(stderr)),
+        code => Err(format!(
+            "failed with exit code {}: {}",
+            code.map_or("none".to_string(), |c| c.to_string()),
+            stderr
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(dir: &Path) -> Session {
+        Session::new(dir.to_path_buf())
+    }
+
+    #[tokio::test]
+    async fn test_exit_code_two_blocks_with_stderr() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let runner = HookRunner::new(vec![
+            Hook::new(HookEvent::PreToolUse, "echo 'no rm please' >&2; exit 2").with_matcher("bash"),
+        ]);
+
+        let input = HookInput::new(HookEvent::PreToolUse, &session(temp.path()))
+            .with_tool("bash", &json!({"command": "rm -rf /"}));
+        assert_eq!(runner.run(input).await.blocked.as_deref(), Some("no rm please"));
+
+        let input = HookInput::new(HookEvent::PreToolUse, &session(temp.path()))
+            .with_tool("read", &json!({"path": "a.txt"}));
+        assert_eq!(runner.run(input).await, HookOutcome::default());
+    }
+
+    #[tokio::test]
+    async fn test_json_output_replaces_input_and_adds_context() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let runner = HookRunner::new(vec![
+            Hook::new(
+                HookEvent::PreToolUse,
+                r#"echo '{"tool_input": {"path": "b.txt"}, "additional_context": "b is newer"}'"#,
+            ),
+            // Sees the replaced input
+            Hook::new(HookEvent::PreToolUse, "grep -q b.txt || exit 2"),
+        ]);
+
+        let input = HookInput::new(HookEvent::PreToolUse, &session(temp.path()))
+            .with_tool("read", &json!({"path": "a.txt"}));
+        let outcome = runner.run(input).await;
+
+        assert_eq!(outcome.blocked, None);
+        assert_eq!(outcome.tool_input, Some(json!({"path": "b.txt"})));
+        assert_eq!(outcome.context, vec!["b is newer".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_failures_and_timeouts_do_not_block() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let runner = HookRunner::new(vec![
+            Hook::new(HookEvent::UserPromptSubmit, "exit 1"),
+            Hook::new(HookEvent::UserPromptSubmit, "sleep 5").with_timeout_secs(0),
+            Hook::new(HookEvent::UserPromptSubmit, "cat > /dev/null; echo 'Branch: main'"),
+        ]);
+
+        let input = HookInput::new(HookEvent::UserPromptSubmit, &session(temp.path())).with_prompt("hi");
+        let outcome = runner.run(input).await;
+
+        assert_eq!(outcome.blocked, None);
+        assert_eq!(outcome.errors.len(), 2);
+        assert!(outcome.errors[1].contains("timed out"));
+        assert_eq!(outcome.context, vec!["Branch: main".to_string()]);
+    }
+
+    #[test]
+    fn test_project_hooks_need_approval() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let file = temp.path().join(".matw").join(HOOKS_FILE);
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(&file, "[[hooks]]\nevent = \"SessionStart\"\ncommand = \"touch pwned\"\n").unwrap();
+        let count = |trust: &TrustStore| {
+            let runner = HookRunner::load(temp.path(), trust).unwrap();
+            runner.hooks.iter().filter(|hook| hook.command == "touch pwned").count()
+        };
+
+        let mut trust = TrustStore::default();
+        assert_eq!(count(&trust), 0);
+        trust.approve(&file, std::fs::read_to_string(&file).unwrap());
+        assert_eq!(count(&trust), 1);
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config: HookConfig = toml::from_str(
+            r#"
+            [[hooks]]
+            event = "PostToolUse"
+            matcher = "write"
+            command = "cargo fmt"
+            timeout_secs = 30
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.hooks,
+            vec![Hook::new(HookEvent::PostToolUse, "cargo fmt").with_matcher("write").with_timeout_secs(30)]
+        );
+    }
+}
@@ -0,0 +1,249 @@
+//! Project instruction files
+//!
+//! Instructions are read from `MATW.md`, `CLAUDE.md` and `AGENTS.md` files: a
+//! user-global one in `~/.matw/`, one per directory from the git root down to
+//! the working directory, and those in deeper directories once the agent
+//! reads a file inside them. A line holding only `@path` is replaced by the
+//! contents of that file.
+
+use matw_core::{Context, InstructionFile};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File names read in each directory, in load order
+pub const INSTRUCTION_FILE_NAMES: &[&str] = &["MATW.md", "CLAUDE.md", "AGENTS.md"];
+
+/// How deeply `@path` imports may nest
+const MAX_IMPORT_DEPTH: usize = 5;
+
+/// Directory holding the user-global instruction files
+pub fn user_instructions_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".matw"))
+}
+
+/// Load the instruction files that apply when a session starts: the user's,
+/// then those from the git root (or working directory) down to the working
+/// directory
+pub fn discover(working_dir: &Path, git_root: Option<&Path>, user_dir: Option<&Path>) -> Vec<InstructionFile> {
+    let mut files = Vec::new();
+    let mut seen = HashSet::new();
+
+    if let Some(dir) = user_dir {
+        load_dir(dir, &mut files, &mut seen);
+    }
+    for dir in dirs_between(git_root.unwrap_or(working_dir), working_dir) {
+        load_dir(&dir, &mut files, &mut seen);
+    }
+
+    files
+}
+
+/// Instruction files below the working directory that apply to `path` and
+/// are not loaded yet. `path` may be a file or a directory.
+pub fn nested(context: &Context, path: &Path) -> Vec<InstructionFile> {
+    let Ok(working_dir) = context.working_dir().canonicalize() else {
+        return Vec::new();
+    };
+    let Ok(path) = working_dir.join(path).canonicalize() else {
+        return Vec::new();
+    };
+    let dir = if path.is_dir() { path.as_path() } else { path.parent().unwrap_or(&path) };
+    if dir == working_dir || !dir.starts_with(&working_dir) {
+        return Vec::new();
+    }
+
+    let mut files = Vec::new();
+    let mut seen: HashSet<PathBuf> = context.instructions().iter().map(|f| f.path.clone()).collect();
+    // The working directory itself was covered by `discover`
+    for dir in dirs_between(&working_dir, dir).into_iter().skip(1) {
+        load_dir(&dir, &mut files, &mut seen);
+    }
+    files
+}
+
+/// Load one instruction file, expanding its imports
+pub fn load_file(path: &Path) -> Option<InstructionFile> {
+    let path = path.canonicalize().ok()?;
+    let mut imports = Vec::new();
+    let mut stack = vec![path.clone()];
+    let content = expand(&path, &mut stack, &mut imports)?;
+    Some(InstructionFile { path, content, imports })
+}
+
+fn load_dir(dir: &Path, files: &mut Vec<InstructionFile>, seen: &mut HashSet<PathBuf>) {
+    for name in INSTRUCTION_FILE_NAMES {
+        let path = dir.join(name);
+        if !path.is_file() {
+            continue;
+        }
+        // A symlinked CLAUDE.md -> AGENTS.md is only loaded once
+        let Ok(canonical) = path.canonicalize() else {
+            continue;
+        };
+        if seen.insert(canonical.clone()) {
+            files.extend(load_file(&canonical));
+        }
+    }
+}
+
+/// `top` and each directory below it down to `bottom`, outermost first.
+/// Just `bottom` if it is not inside `top`.
+fn dirs_between(top: &Path, bottom: &Path) -> Vec<PathBuf> {
+    if !bottom.starts_with(top) {
+        return vec![bottom.to_path_buf()];
+    }
+    let mut dirs: Vec<PathBuf> = bottom
+        .ancestors()
+        .take_while(|dir| dir.starts_with(top))
+        .map(Path::to_path_buf)
+        .collect();
+    dirs.reverse();
+    dirs
+}
+
+/// Read `path`, replacing import lines with the imported contents. Imports
+/// that are missing, circular or too deep are left as written.
+fn expand(path: &Path, stack: &mut Vec<PathBuf>, imports: &mut Vec<PathBuf>) -> Option<String> {
+    let text = fs::read_to_string(path).ok()?;
+    let base = path.parent().unwrap_or(Path::new("."));
+    let mut lines = Vec::new();
+    let mut in_code_block = false;
+
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+
+        let target = import_target(line)
+            .filter(|_| !in_code_block && stack.len() <= MAX_IMPORT_DEPTH)
+            .and_then(|target| resolve_import(base, target).canonicalize().ok())
+            .filter(|target| !stack.contains(target));
+
+        let Some(target) = target else {
+            lines.push(line.to_string());
+            continue;
+        };
+
+        stack.push(target.clone());
+        match expand(&target, stack, imports) {
+            Some(content) => {
+                if !imports.contains(&target) {
+                    imports.push(target);
+                }
+                lines.push(content);
+            }
+            None => lines.push(line.to_string()),
+        }
+        stack.pop();
+    }
+
+    Some(lines.join("\n"))
+}
+
+fn import_target(line: &str) -> Option<&str> {
+    line.trim()
+        .strip_prefix('@')
+        .filter(|target| !target.is_empty() && !target.contains(char::is_whitespace))
+}
+
+fn resolve_import(base: &Path, target: &str) -> PathBuf {
+    if let Some(rest) = target.strip_prefix("~/") {
+        if let Some(home) = dirs::home_dir() {
+            return home.join(rest);
+        }
+    }
+    base.join(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn paths(files: &[InstructionFile], root: &Path) -> Vec<String> {
+        let root = root.canonicalize().unwrap();
+        files
+            .iter()
+            .map(|f| f.path.strip_prefix(&root).unwrap().display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_discover_user_then_root_down() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("repo");
+        let cwd = root.join("crates/app");
+        let user = temp.path().join("home/.matw");
+        write(&user.join("MATW.md"), "user");
+        write(&root.join("AGENTS.md"), "agents");
+        write(&root.join("CLAUDE.md"), "claude");
+        write(&root.join("crates/MATW.md"), "crates");
+        write(&cwd.join("MATW.md"), "app");
+
+        let files = discover(&cwd, Some(&root), Some(&user));
+
+        assert_eq!(
+            paths(&files, temp.path()),
+            vec![
+                "home/.matw/MATW.md",
+                "repo/CLAUDE.md",
+                "repo/AGENTS.md",
+                "repo/crates/MATW.md",
+                "repo/crates/app/MATW.md"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_imports_with_cycle() {
+        let temp = TempDir::new().unwrap();
+        write(&temp.path().join("MATW.md"), "Top\n@docs/style.md\n@missing.md");
+        write(&temp.path().join("docs/style.md"), "Style\n@../MATW.md\n@rules.md");
+        write(&temp.path().join("docs/rules.md"), "Rules");
+
+        let file = load_file(&temp.path().join("MATW.md")).unwrap();
+        let root = temp.path().canonicalize().unwrap();
+
+        assert_eq!(file.content, "Top\nStyle\n@../MATW.md\nRules\n@missing.md");
+        let imports: Vec<PathBuf> = file.imports.iter().map(|p| p.strip_prefix(&root).unwrap().to_path_buf()).collect();
+        assert_eq!(imports, vec![PathBuf::from("docs/rules.md"), PathBuf::from("docs/style.md")]);
+    }
+
+    #[test]
+    fn test_imports_ignored_in_code_blocks() {
+        let temp = TempDir::new().unwrap();
+        write(&temp.path().join("MATW.md"), "```\n@other.md\n```");
+        write(&temp.path().join("other.md"), "Other");
+
+        let file = load_file(&temp.path().join("MATW.md")).unwrap();
+
+        assert_eq!(file.content, "```\n@other.md\n```");
+    }
+
+    #[test]
+    fn test_nested_loads_once() {
+        let temp = TempDir::new().unwrap();
+        write(&temp.path().join("MATW.md"), "root");
+        write(&temp.path().join("web/MATW.md"), "web");
+        write(&temp.path().join("web/src/AGENTS.md"), "src");
+        write(&temp.path().join("web/src/main.ts"), "");
+        let mut context = Context::new(temp.path().to_path_buf());
+        for file in discover(temp.path(), None, None) {
+            context.add_instructions(file);
+        }
+
+        let files = nested(&context, Path::new("web/src/main.ts"));
+        assert_eq!(paths(&files, temp.path()), vec!["web/MATW.md", "web/src/AGENTS.md"]);
+
+        for file in files {
+            context.add_instructions(file);
+        }
+        assert!(nested(&context, Path::new("web/src")).is_empty());
+    }
+}
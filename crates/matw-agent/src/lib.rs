@@ -3,7 +3,25 @@
 //! Provides agent loop and orchestration for AI interactions.
 
 pub mod agent;
+pub mod config;
+pub mod events;
+pub mod hooks;
+pub mod instructions;
+pub mod permission;
+pub mod plan;
+pub mod prompt;
 pub mod streaming;
+pub mod task;
 
-pub use agent::{Agent, AgentError};
-pub use streaming::process_streaming;
+pub use agent::{Agent, AgentError, FailurePolicy, RewindMode, Rewound};
+pub use config::{AgentConfig, Limit, Pricing};
+pub use events::{AgentEvent, AgentObserver, JsonLinesObserver, TracingObserver};
+pub use hooks::{Hook, HookConfig, HookEvent, HookInput, HookOutcome, HookRunner};
+pub use permission::{
+    ApprovalDecision, ApprovalHandler, ApprovalRequest, PermissionAction, PermissionConfig,
+    PermissionEngine, PermissionRule,
+};
+pub use plan::{PlanDecision, PlanReviewer};
+pub use prompt::SystemPromptBuilder;
+pub use streaming::collect_stream;
+pub use task::{load_agent_types, AgentType, TaskTool};
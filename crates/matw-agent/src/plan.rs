@@ -0,0 +1,67 @@
+//! Plan mode
+//!
+//! While a session is in `SessionMode::Plan` the agent only offers read-only
+//! tools and `exit_plan`. The model explores, then calls `exit_plan` with a
+//! plan; once a `PlanReviewer` approves it, possibly edited, the session goes
+//! back to normal mode with the plan in its context.
+
+use async_trait::async_trait;
+use matw_ai::ToolDefinition;
+use serde_json::json;
+
+/// Name of the tool the model calls to propose its plan
+pub const EXIT_PLAN_TOOL_NAME: &str = "exit_plan";
+
+/// The user's answer to a proposed plan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanDecision {
+    /// Carry out this plan, which the user may have edited
+    Approve { plan: String },
+    /// Keep planning
+    Reject { feedback: Option<String> },
+}
+
+/// Asks the user to review a plan
+#[async_trait]
+pub trait PlanReviewer: Send + Sync {
+    async fn review_plan(&self, plan: String) -> PlanDecision;
+}
+
+/// Definition of the `exit_plan` tool offered in plan mode
+pub fn exit_plan_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: EXIT_PLAN_TOOL_NAME.to_string(),
+        description: "Present your plan to the user for approval and leave plan mode\n\n\
+            Call this once you understand the task. When the user approves, all tools become available."
+            .to_string(),
+        parameters: json!({
+            "type": "object",
+            "properties": {
+                "plan": {
+                    "type": "string",
+                    "description": "The steps you will take, in Markdown"
+                }
+            },
+            "required": ["plan"]
+        }),
+    }
+}
+
+/// Tool result when the user approves the plan
+pub(crate) fn approved_message(plan: &str) -> String {
+    format!(
+        "The user approved the plan. Plan mode is over and all tools are available; carry out this plan:\n\n{}",
+        plan
+    )
+}
+
+/// Tool result when the user rejects the plan
+pub(crate) fn rejected_message(feedback: Option<&str>) -> String {
+    match feedback {
+        Some(feedback) => format!(
+            "The user rejected the plan: {}\nRevise it and call {} again.",
+            feedback, EXIT_PLAN_TOOL_NAME
+        ),
+        None => format!("The user rejected the plan. Revise it and call {} again.", EXIT_PLAN_TOOL_NAME),
+    }
+}
@@ -0,0 +1,253 @@
+//! System prompt assembly
+//!
+//! The system prompt is built from sections: base instructions, tool usage
+//! guidance, facts about the environment, the project's instruction files and
+//! plan mode or the approved plan.
+//! Users can replace the base instructions and append their own text from
+//! config or the command line.
+
+use crate::plan::EXIT_PLAN_TOOL_NAME;
+use matw_core::{Context, SessionMode};
+use matw_tools::Tool;
+use std::sync::Arc;
+
+/// Base instructions used unless overridden
+pub const DEFAULT_BASE_PROMPT: &str = "\
+You are MATW, an AI coding assistant working in the user's project from the terminal.
+Help with software engineering tasks: reading and changing code, running commands and explaining results.
+Be concise. Prefer making a change over describing it, and check your work when you can.";
+
+const TOOL_GUIDANCE: &str = "\
+Use tools to inspect the project instead of guessing. Read a file before changing it.
+Independent read-only calls may be made together in one response.
+If a tool call fails or is denied, read the error and adjust instead of repeating the same call.";
+
+/// Builds the system prompt for a session
+#[derive(Debug, Clone, Default)]
+pub struct SystemPromptBuilder {
+    base: Option<String>,
+    appends: Vec<String>,
+    os: Option<String>,
+    date: Option<String>,
+}
+
+impl SystemPromptBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the base instructions
+    pub fn with_override(mut self, base: impl Into<String>) -> Self {
+        self.base = Some(base.into());
+        self
+    }
+
+    /// Add text after all other sections
+    pub fn with_append(mut self, text: impl Into<String>) -> Self {
+        self.appends.push(text.into());
+        self
+    }
+
+    /// Report this OS instead of the current one
+    pub fn with_os(mut self, os: impl Into<String>) -> Self {
+        self.os = Some(os.into());
+        self
+    }
+
+    /// Report this date instead of today's
+    pub fn with_date(mut self, date: impl Into<String>) -> Self {
+        self.date = Some(date.into());
+        self
+    }
+
+    /// Render the prompt for a session context and the tools offered with it
+    pub fn build(&self, context: &Context, tools: &[Arc<dyn Tool>]) -> String {
+        let mut sections = vec![self.base.as_deref().unwrap_or(DEFAULT_BASE_PROMPT).trim().to_string()];
+
+        if !tools.is_empty() {
+            sections.push(tools_section(tools));
+        }
+
+        sections.push(self.environment_section(context));
+
+        if let Some(instructions) = instructions_section(context) {
+            sections.push(instructions);
+        }
+
+        if let Some(plan) = plan_section(context) {
+            sections.push(plan);
+        }
+
+        sections.extend(
+            self.appends
+                .iter()
+                .map(|text| text.trim())
+                .filter(|text| !text.is_empty())
+                .map(str::to_string),
+        );
+
+        sections.join("\n\n")
+    }
+
+    fn environment_section(&self, context: &Context) -> String {
+        let os = self.os.clone().unwrap_or_else(|| std::env::consts::OS.to_string());
+        let date = self
+            .date
+            .clone()
+            .unwrap_or_else(|| chrono::Local::now().format("%Y-%m-%d").to_string());
+
+        let mut lines = vec![
+            "# Environment".to_string(),
+            String::new(),
+            format!("- Working directory: {}", context.working_dir().display()),
+            format!("- Platform: {}", os),
+            format!("- Date: {}", date),
+        ];
+
+        match context.git_info() {
+            Some(git) => {
+                let commit: String = git.commit.chars().take(7).collect();
+                let status = if git.dirty { "uncommitted changes" } else { "clean" };
+                lines.push(format!("- Git repository: {}", git.root.display()));
+                lines.push(format!("- Git branch: {} at {} ({})", git.branch, commit, status));
+            }
+            None => lines.push("- Git repository: none".to_string()),
+        }
+
+        lines.join("\n")
+    }
+}
+
+fn instructions_section(context: &Context) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(claude_md) = context.claude_md().filter(|c| !c.trim().is_empty()) {
+        parts.push(claude_md.trim().to_string());
+    }
+    for file in context.instructions().iter().filter(|f| !f.content.trim().is_empty()) {
+        parts.push(format!("Contents of {}:\n\n{}", file.path.display(), file.content.trim()));
+    }
+
+    if parts.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "# Project instructions\n\nThe project provides these instructions. Follow them; later ones are more specific.\n\n{}",
+        parts.join("\n\n")
+    ))
+}
+
+fn plan_section(context: &Context) -> Option<String> {
+    match context.mode() {
+        SessionMode::Plan => Some(format!(
+            "# Plan mode\n\n\
+             The session is in plan mode. Only read-only tools are available: explore the project and work out \
+             what to change, but do not try to change anything. When you are ready, call {} with a step-by-step \
+             plan. The user will approve, edit or reject it.",
+            EXIT_PLAN_TOOL_NAME
+        )),
+        SessionMode::Normal => context
+            .plan()
+            .map(|plan| format!("# Approved plan\n\nThe user approved this plan. Follow it:\n\n{}", plan.trim())),
+    }
+}
+
+fn tools_section(tools: &[Arc<dyn Tool>]) -> String {
+    let mut lines = vec!["# Using tools".to_string(), String::new(), TOOL_GUIDANCE.to_string(), String::new()];
+    for tool in tools {
+        let summary = tool.description().lines().next().unwrap_or_default();
+        lines.push(format!("- {}: {}", tool.name(), summary));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matw_core::{GitInfo, InstructionFile};
+    use matw_tools::tools::{GlobTool, ReadTool};
+    use std::path::PathBuf;
+
+    fn builder() -> SystemPromptBuilder {
+        SystemPromptBuilder::new().with_os("linux").with_date("2026-01-15")
+    }
+
+    fn context() -> Context {
+        let git_info = GitInfo {
+            branch: "main".to_string(),
+            commit: "0123456789abcdef".to_string(),
+            root: PathBuf::from("/work/app"),
+            dirty: true,
+        };
+        let mut context = Context::with_details(PathBuf::from("/work/app"), Some(git_info), None);
+        context.add_instructions(InstructionFile {
+            path: PathBuf::from("/work/app/MATW.md"),
+            content: "Run `cargo fmt` before committing.\n".to_string(),
+            imports: vec![],
+        });
+        context
+    }
+
+    #[test]
+    fn test_full_prompt() {
+        let tools: Vec<Arc<dyn Tool>> = vec![Arc::new(ReadTool::new()), Arc::new(GlobTool::new())];
+
+        let prompt = builder().build(&context(), &tools);
+
+        let expected = format!(
+            "{}\n\n\
+             # Using tools\n\n\
+             {}\n\n\
+             - read: Read the contents of a file\n\
+             - glob: Find files matching a glob pattern\n\n\
+             # Environment\n\n\
+             - Working directory: /work/app\n\
+             - Platform: linux\n\
+             - Date: 2026-01-15\n\
+             - Git repository: /work/app\n\
+             - Git branch: main at 0123456 (uncommitted changes)\n\n\
+             # Project instructions\n\n\
+             The project provides these instructions. Follow them; later ones are more specific.\n\n\
+             Contents of /work/app/MATW.md:\n\n\
+             Run `cargo fmt` before committing.",
+            DEFAULT_BASE_PROMPT,
+            TOOL_GUIDANCE,
+        );
+        assert_eq!(prompt, expected);
+    }
+
+    #[test]
+    fn test_override_and_append_without_git() {
+        let context = Context::new(PathBuf::from("/tmp/scratch"));
+
+        let prompt = builder()
+            .with_override("You are a terse reviewer.")
+            .with_append("Answer in French.")
+            .with_append("  ")
+            .build(&context, &[]);
+
+        assert_eq!(
+            prompt,
+            "You are a terse reviewer.\n\n\
+             # Environment\n\n\
+             - Working directory: /tmp/scratch\n\
+             - Platform: linux\n\
+             - Date: 2026-01-15\n\
+             - Git repository: none\n\n\
+             Answer in French."
+        );
+    }
+
+    #[test]
+    fn test_plan_sections() {
+        let mut context = Context::new(PathBuf::from("/tmp/scratch"));
+        context.set_plan(Some("1. Add the flag\n".to_string()));
+
+        let prompt = builder().build(&context, &[]);
+        assert!(prompt.ends_with("# Approved plan\n\nThe user approved this plan. Follow it:\n\n1. Add the flag"));
+
+        context.set_mode(SessionMode::Plan);
+        let prompt = builder().build(&context, &[]);
+        assert!(prompt.contains("# Plan mode"));
+        assert!(!prompt.contains("# Approved plan"));
+    }
+}
@@ -1,48 +1,100 @@
 //! Streaming response support
 
 use futures::StreamExt;
-use matw_ai::{AIProvider, Chunk, CompletionRequest};
-use matw_core::{Message, Session};
-
-pub async fn process_streaming<P: AIProvider>(
-    provider: &P,
-    session: &mut Session,
-    on_delta: impl Fn(String),
-) -> Result<(), super::agent::AgentError> {
-    let request = CompletionRequest {
-        messages: session.messages().to_vec(),
-        tools: vec![],
-        model: "default".to_string(),
-        max_tokens: Some(4096),
-        temperature: Some(0.7),
-        system_prompt: None,
-    };
-
-    let stream = provider
-        .stream_completion(request)
-        .await
-        .map_err(|e| super::agent::AgentError::AIProvider(e.to_string()))?;
-
-    let mut response_text = String::new();
+use matw_ai::{AIError, Chunk, ChunkStream, CompletionResponse, StopReason, Usage};
 
-    futures::pin_mut!(stream);
+/// Collect a streamed completion into a response, passing each text delta to
+/// `on_delta` as it arrives.
+///
+/// Providers that end the stream without a `Chunk::Stop` are treated as having
+/// stopped for tool use if they sent any tool calls, and at the end of the
+/// turn otherwise.
+pub async fn collect_stream(
+    stream: ChunkStream,
+    on_delta: &(dyn Fn(&str) + Send + Sync),
+) -> Result<CompletionResponse, AIError> {
+    let mut content = String::new();
+    let mut tool_uses = Vec::new();
+    let mut stop = None;
 
-    while let Some(chunk_result) = stream.next().await {
-        let chunk = chunk_result
-            .map_err(|e| super::agent::AgentError::AIProvider(e.to_string()))?;
+    futures::pin_mut!(stream);
 
-        match chunk {
+    while let Some(chunk) = stream.next().await {
+        match chunk? {
             Chunk::Delta(text) => {
-                on_delta(text.clone());
-                response_text.push_str(&text);
+                on_delta(&text);
+                content.push_str(&text);
             }
+            Chunk::ToolUse(tool_use) => tool_uses.push(tool_use),
+            Chunk::Stop { reason, usage } => stop = Some((reason, usage)),
             Chunk::Done => break,
-            _ => {}
         }
     }
 
-    // Add final message
-    session.add_message(Message::new_assistant(response_text));
+    let (stop_reason, usage) = stop.unwrap_or_else(|| {
+        let reason = if tool_uses.is_empty() { StopReason::EndTurn } else { StopReason::ToolUse };
+        (reason, Usage::default())
+    });
+
+    Ok(CompletionResponse {
+        content,
+        tool_uses,
+        stop_reason,
+        usage,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+    use matw_ai::ToolUse;
+    use std::sync::Mutex;
+
+    fn chunks(chunks: Vec<Chunk>) -> ChunkStream {
+        ChunkStream::new(Box::pin(stream::iter(chunks.into_iter().map(Ok))))
+    }
+
+    #[tokio::test]
+    async fn test_collects_deltas_and_tool_uses() {
+        let deltas = Mutex::new(Vec::new());
+        let stream = chunks(vec![
+            Chunk::Delta("Let me ".to_string()),
+            Chunk::Delta("look.".to_string()),
+            Chunk::ToolUse(ToolUse {
+                id: "call_1".to_string(),
+                name: "read".to_string(),
+                input: serde_json::json!({"path": "a.txt"}),
+            }),
+            Chunk::Done,
+        ]);
+
+        let response = collect_stream(stream, &|d| deltas.lock().unwrap().push(d.to_string()))
+            .await
+            .unwrap();
+
+        assert_eq!(*deltas.lock().unwrap(), vec!["Let me ", "look."]);
+        assert_eq!(response.content, "Let me look.");
+        assert_eq!(response.tool_uses.len(), 1);
+        assert_eq!(response.stop_reason, StopReason::ToolUse);
+    }
 
-    Ok(())
+    #[tokio::test]
+    async fn test_stop_chunk_sets_reason_and_usage() {
+        let stream = chunks(vec![
+            Chunk::Delta("Done".to_string()),
+            Chunk::Stop {
+                reason: StopReason::MaxTokens,
+                usage: Usage {
+                    input_tokens: 10,
+                    output_tokens: 2,
+                },
+            },
+        ]);
+
+        let response = collect_stream(stream, &|_| {}).await.unwrap();
+
+        assert_eq!(response.stop_reason, StopReason::MaxTokens);
+        assert_eq!(response.usage.output_tokens, 2);
+    }
 }
@@ -0,0 +1,573 @@
+//! Sub-agent delegation
+//!
+//! The `task` tool hands a self-contained piece of work to a sub-agent: a
+//! nested `Agent` with a fresh session, its own system prompt and a subset of
+//! the tools. Only the sub-agent's final message comes back as the tool
+//! result, and its token usage is added to the calling turn.
+//!
+//! Agent types are read from `~/.matw/agents/` and `<project>/.matw/agents/`.
+//! A Markdown file holds TOML front matter between `+++` lines and the
+//! system prompt as its body; a TOML file holds the same fields with the
+//! prompt in `system_prompt`:
+//!
+//! ```text
+//! +++
+//! description = "Searches the codebase and reports where things are"
+//! tools = ["read", "glob"]
+//! max_turns = 20
+//! +++
+//! You find code. Report file paths and line numbers, not whole files.
+//! ```
+
+use crate::agent::{Agent, AgentError};
+use crate::config::AgentConfig;
+use crate::events::{AgentEvent, AgentObserver};
+use crate::hooks::HookRunner;
+use crate::permission::{ApprovalDecision, ApprovalHandler, ApprovalRequest, PermissionEngine};
+use crate::prompt::SystemPromptBuilder;
+use async_trait::async_trait;
+use matw_ai::{AIProvider, Usage};
+use matw_core::{Content, Context, Message, Role, Session};
+use matw_tools::{ProgressSink, Tool, ToolContext, ToolError, ToolFilter, ToolOutput, ToolRegistry, ToolSource};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::{Arc, Mutex, Weak};
+use tokio::sync::Semaphore;
+
+/// Name of the delegation tool
+pub const TASK_TOOL_NAME: &str = "task";
+
+/// Agent type used when a call does not name one
+pub const DEFAULT_AGENT_TYPE: &str = "general";
+
+/// Appended to every sub-agent's system prompt
+const SUBAGENT_GUIDANCE: &str = "\
+You are working on a task delegated by another agent. Work on your own; you cannot ask questions.
+When you are done, reply with a concise summary of what you found or changed. Only that reply is passed back.";
+
+/// A kind of sub-agent the model can delegate to
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AgentType {
+    /// Defaults to the file name without its extension
+    #[serde(default)]
+    pub name: String,
+    pub description: String,
+    /// Replaces the base instructions of the system prompt when not empty
+    #[serde(default)]
+    pub system_prompt: String,
+    /// Tool names or glob patterns the sub-agent may use; all when unset.
+    /// Sub-agents never get the `task` tool.
+    #[serde(default)]
+    pub tools: Option<Vec<String>>,
+    /// Overrides the model of the calling agent
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Overrides the calling agent's turn limit
+    #[serde(default)]
+    pub max_turns: Option<usize>,
+}
+
+impl AgentType {
+    /// The built-in agent type with all tools and the default prompt
+    pub fn general() -> Self {
+        Self {
+            name: DEFAULT_AGENT_TYPE.to_string(),
+            description: "General-purpose agent for research and multi-step changes".to_string(),
+            system_prompt: String::new(),
+            tools: None,
+            model: None,
+            max_turns: None,
+        }
+    }
+
+    /// Parse an agent type from a Markdown or TOML file's contents
+    pub fn parse(name: &str, content: &str, markdown: bool) -> Result<Self, String> {
+        let mut agent_type: AgentType = if markdown {
+            let (front_matter, body) = split_front_matter(content).ok_or("missing +++ front matter")?;
+            let mut agent_type: AgentType = toml::from_str(front_matter).map_err(|e| e.to_string())?;
+            agent_type.system_prompt = body.trim().to_string();
+            agent_type
+        } else {
+            toml::from_str(content).map_err(|e| e.to_string())?
+        };
+        if agent_type.name.is_empty() {
+            agent_type.name = name.to_string();
+        }
+        Ok(agent_type)
+    }
+
+    fn tool_filter(&self) -> ToolFilter {
+        ToolFilter {
+            enabled: self.tools.clone(),
+            disabled: vec![TASK_TOOL_NAME.to_string()],
+        }
+    }
+}
+
+/// Load the built-in agent type, then those from `~/.matw/agents/` and
+/// `<project>/.matw/agents/`. Later definitions replace earlier ones with the
+/// same name.
+pub fn load_agent_types(project_dir: &Path) -> Result<Vec<AgentType>, AgentError> {
+    let mut types = vec![AgentType::general()];
+    let dirs = dirs::home_dir()
+        .map(|home| home.join(".matw/agents"))
+        .into_iter()
+        .chain([project_dir.join(".matw/agents")]);
+    for dir in dirs {
+        for agent_type in load_dir(&dir)? {
+            match types.iter_mut().find(|t| t.name == agent_type.name) {
+                Some(existing) => *existing = agent_type,
+                None => types.push(agent_type),
+            }
+        }
+    }
+    Ok(types)
+}
+
+fn load_dir(dir: &Path) -> Result<Vec<AgentType>, AgentError> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+    let mut paths: Vec<_> = entries.filter_map(|entry| entry.ok().map(|e| e.path())).collect();
+    paths.sort();
+
+    let mut types = Vec::new();
+    for path in paths {
+        let markdown = match path.extension().and_then(|e| e.to_str()) {
+            Some("md") => true,
+            Some("toml") => false,
+            _ => continue,
+        };
+        let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| AgentError::Config(format!("{}: {}", path.display(), e)))?;
+        let agent_type = AgentType::parse(name, &content, markdown)
+            .map_err(|e| AgentError::Config(format!("{}: {}", path.display(), e)))?;
+        types.push(agent_type);
+    }
+    Ok(types)
+}
+
+fn split_front_matter(content: &str) -> Option<(&str, &str)> {
+    let rest = content.strip_prefix("+++")?;
+    let rest = rest.strip_prefix("\r\n").or_else(|| rest.strip_prefix('\n'))?;
+    let end = rest.find("\n+++")?;
+    let body = rest[end + 4..].split_once('\n').map_or("", |(_, body)| body);
+    Some((&rest[..end], body))
+}
+
+/// Tool that runs a task in a sub-agent
+pub struct TaskTool<P: AIProvider> {
+    provider: Arc<P>,
+    /// The registry this tool is registered in; weak to avoid a cycle
+    tools: Weak<ToolRegistry>,
+    agent_types: Vec<AgentType>,
+    config: AgentConfig,
+    context: Option<Context>,
+    permissions: Option<Arc<PermissionEngine>>,
+    approval: Option<Arc<dyn ApprovalHandler>>,
+    hooks: Option<Arc<HookRunner>>,
+    slots: Arc<Semaphore>,
+    description: String,
+}
+
+impl<P: AIProvider + 'static> TaskTool<P> {
+    /// Create the tool for agents using `provider` and tools from `tools`
+    pub fn new(provider: Arc<P>, tools: &Arc<ToolRegistry>, agent_types: Vec<AgentType>) -> Self {
+        let mut description = "Delegate a self-contained task to a sub-agent, which returns a summary of its work\n\n\
+            Use it for searches or changes that need many steps, so their details stay out of this conversation. \
+            Several tasks in one response run in parallel.\n\nAgent types:"
+            .to_string();
+        for agent_type in &agent_types {
+            description.push_str(&format!("\n- {}: {}", agent_type.name, agent_type.description));
+        }
+
+        Self {
+            provider,
+            tools: Arc::downgrade(tools),
+            agent_types,
+            config: AgentConfig::default(),
+            context: None,
+            permissions: None,
+            approval: None,
+            hooks: None,
+            slots: Arc::new(Semaphore::new(3)),
+            description,
+        }
+    }
+
+    /// Model settings and limits for sub-agents; agent types may override some
+    pub fn with_config(mut self, config: AgentConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Start sub-agent sessions from this context instead of an empty one
+    pub fn with_context(mut self, context: Context) -> Self {
+        self.context = Some(context);
+        self
+    }
+
+    /// Check sub-agents' tool calls against these rules
+    pub fn with_permissions(mut self, permissions: Arc<PermissionEngine>) -> Self {
+        self.permissions = Some(permissions);
+        self
+    }
+
+    /// Ask this handler about sub-agents' tool calls, one prompt at a time
+    pub fn with_approval_handler(mut self, approval: Arc<dyn ApprovalHandler>) -> Self {
+        self.approval = Some(Arc::new(OneAtATime {
+            inner: approval,
+            lock: tokio::sync::Mutex::new(()),
+        }));
+        self
+    }
+
+    /// Run the tool hooks of `hooks` around sub-agents' tool calls
+    pub fn with_hooks(mut self, hooks: &HookRunner) -> Self {
+        self.hooks = Some(Arc::new(hooks.for_subagents()));
+        self
+    }
+
+    /// Limit how many sub-agents run at once
+    pub fn with_max_parallel(mut self, limit: usize) -> Self {
+        self.slots = Arc::new(Semaphore::new(limit.max(1)));
+        self
+    }
+
+    fn build_agent(&self, agent_type: &AgentType, parent: &ToolRegistry, observer: Arc<dyn AgentObserver>) -> Agent<Arc<P>> {
+        let filter = agent_type.tool_filter();
+        let tools = ToolRegistry::new();
+        for tool in parent.tools().into_iter().filter(|tool| filter.allows(tool.name())) {
+            let source = parent.source(tool.name()).unwrap_or(ToolSource::BuiltIn);
+            // Names are unique in the parent registry
+            tools.register(tool, source).ok();
+        }
+
+        let mut config = self.config.clone();
+        if let Some(model) = &agent_type.model {
+            config.model = model.clone();
+        }
+        if let Some(max_turns) = agent_type.max_turns {
+            config.max_turns = max_turns;
+        }
+
+        let mut prompt = SystemPromptBuilder::new().with_append(SUBAGENT_GUIDANCE);
+        if !agent_type.system_prompt.trim().is_empty() {
+            prompt = prompt.with_override(agent_type.system_prompt.clone());
+        }
+
+        let mut agent = Agent::new(self.provider.clone(), Arc::new(tools))
+            .with_config(config)
+            .with_system_prompt(prompt)
+            .with_observer(observer);
+        if let Some(permissions) = &self.permissions {
+            agent = agent.with_permissions(permissions.clone());
+        }
+        if let Some(approval) = &self.approval {
+            agent = agent.with_approval_handler(approval.clone());
+        }
+        if let Some(hooks) = &self.hooks {
+            agent = agent.with_hooks(hooks.clone());
+        }
+        agent
+    }
+}
+
+#[async_trait]
+impl<P: AIProvider + 'static> Tool for TaskTool<P> {
+    fn name(&self) -> &str {
+        TASK_TOOL_NAME
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn parameters_schema(&self) -> Value {
+        let names: Vec<&str> = self.agent_types.iter().map(|t| t.name.as_str()).collect();
+        json!({
+            "type": "object",
+            "properties": {
+                "description": {
+                    "type": "string",
+                    "description": "A few words describing the task"
+                },
+                "prompt": {
+                    "type": "string",
+                    "description": "The task, with all the context the sub-agent needs"
+                },
+                "agent_type": {
+                    "type": "string",
+                    "enum": names,
+                    "description": "Kind of agent to run (default: general)"
+                }
+            },
+            "required": ["description", "prompt"]
+        })
+    }
+
+    /// Sub-agents check their own calls; the semaphore bounds how many run
+    fn is_concurrency_safe(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, input: Value, ctx: &ToolContext) -> Result<ToolOutput, ToolError> {
+        let label = input["description"].as_str().unwrap_or(TASK_TOOL_NAME).to_string();
+        let prompt = input["prompt"]
+            .as_str()
+            .ok_or_else(|| ToolError::InvalidParameters("prompt is required".to_string()))?;
+        let type_name = input["agent_type"].as_str().unwrap_or(DEFAULT_AGENT_TYPE);
+        let agent_type = self.agent_types.iter().find(|t| t.name == type_name).ok_or_else(|| {
+            let names: Vec<&str> = self.agent_types.iter().map(|t| t.name.as_str()).collect();
+            ToolError::InvalidParameters(format!("Unknown agent type: {}. Available: {}", type_name, names.join(", ")))
+        })?;
+        let parent = self
+            .tools
+            .upgrade()
+            .ok_or_else(|| ToolError::ExecutionFailed("tool registry is gone".to_string()))?;
+
+        let _slot = tokio::select! {
+            biased;
+            _ = ctx.cancellation().cancelled() => return Err(ToolError::Cancelled),
+            slot = self.slots.acquire() => slot.map_err(|e| ToolError::ExecutionFailed(e.to_string()))?,
+        };
+
+        let observer = Arc::new(SubAgentObserver {
+            label,
+            progress: ctx.progress().cloned(),
+            usage: Mutex::new(Usage::default()),
+        });
+        // Files the sub-agent changes are rewound with the parent's turn
+        let agent = self
+            .build_agent(agent_type, &parent, observer.clone())
+            .with_checkpoints(ctx.checkpoints().clone());
+
+        let context = match &self.context {
+            Some(context) => context.clone(),
+            None => Context::new(ctx.working_dir().to_path_buf()),
+        };
+        let mut session = Session::with_context(context);
+        session.add_message(Message::new_user(prompt.to_string()));
+
+        let result = agent.process(&mut session, ctx.cancellation()).await;
+        let usage = Some(*observer.usage.lock().unwrap());
+        let summary = final_reply(&session);
+
+        match result {
+            Ok(()) => Ok(ToolOutput {
+                content: summary.unwrap_or_else(|| "The sub-agent finished without a reply.".to_string()),
+                is_error: false,
+                usage,
+            }),
+            Err(AgentError::Cancelled) => Err(ToolError::Cancelled),
+            Err(e) => {
+                let content = match summary {
+                    Some(summary) => format!("Sub-agent failed: {}\n\nIts last reply:\n{}", e, summary),
+                    None => format!("Sub-agent failed: {}", e),
+                };
+                Ok(ToolOutput {
+                    content,
+                    is_error: true,
+                    usage,
+                })
+            }
+        }
+    }
+}
+
+/// The last non-empty text the sub-agent wrote
+fn final_reply(session: &Session) -> Option<String> {
+    session.messages().iter().rev().find_map(|m| match m.content() {
+        Content::Text(text) if m.role() == Role::Assistant && !text.trim().is_empty() => Some(text.trim().to_string()),
+        _ => None,
+    })
+}
+
+/// Reports a sub-agent's tool calls as progress of the `task` call and
+/// records its usage
+struct SubAgentObserver {
+    label: String,
+    progress: Option<ProgressSink>,
+    usage: Mutex<Usage>,
+}
+
+impl AgentObserver for SubAgentObserver {
+    fn on_event(&self, event: &AgentEvent) {
+        match event {
+            AgentEvent::ToolStarted { name, .. } => {
+                if let Some(progress) = &self.progress {
+                    progress.report(&format!("{}: running {}", self.label, name));
+                }
+            }
+            AgentEvent::TurnCompleted { usage } => *self.usage.lock().unwrap() = *usage,
+            _ => {}
+        }
+    }
+}
+
+/// Parallel sub-agents share one approval handler; prompts wait their turn
+struct OneAtATime {
+    inner: Arc<dyn ApprovalHandler>,
+    lock: tokio::sync::Mutex<()>,
+}
+
+#[async_trait]
+impl ApprovalHandler for OneAtATime {
+    async fn request_approval(&self, request: ApprovalRequest) -> ApprovalDecision {
+        let _prompt = self.lock.lock().await;
+        self.inner.request_approval(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use matw_ai::{AIError, ChunkStream, CompletionRequest, CompletionResponse, StopReason, ToolUse};
+    use matw_tools::tools::{GlobTool, ReadTool, WriteTool};
+    use tokio_util::sync::CancellationToken;
+
+    /// Provider that answers by the system prompt: the sub-agent reads a
+    /// file and summarises it, the main agent delegates once
+    struct DelegatingProvider {
+        requests: Mutex<Vec<CompletionRequest>>,
+    }
+
+    fn reply(content: &str, tool_uses: Vec<ToolUse>) -> CompletionResponse {
+        CompletionResponse {
+            content: content.to_string(),
+            stop_reason: if tool_uses.is_empty() { StopReason::EndTurn } else { StopReason::ToolUse },
+            tool_uses,
+            usage: Usage {
+                input_tokens: 100,
+                output_tokens: 10,
+            },
+        }
+    }
+
+    #[async_trait]
+    impl AIProvider for DelegatingProvider {
+        fn name(&self) -> &str {
+            "delegating"
+        }
+
+        async fn stream_completion(&self, _request: CompletionRequest) -> Result<ChunkStream, AIError> {
+            Err(AIError::NotConfigured("delegating provider does not stream".to_string()))
+        }
+
+        async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, AIError> {
+            let sub_agent = request.system_prompt.as_deref().unwrap_or_default().contains("delegated");
+            let has_result = request.messages.iter().any(|m| m.is_tool_result());
+            self.requests.lock().unwrap().push(request);
+            Ok(match (sub_agent, has_result) {
+                (true, false) => reply(
+                    "",
+                    vec![ToolUse {
+                        id: "sub_1".to_string(),
+                        name: "read".to_string(),
+                        input: json!({"path": "notes.txt"}),
+                    }],
+                ),
+                (true, true) => reply("The notes say hello.", vec![]),
+                (false, false) => reply(
+                    "",
+                    vec![ToolUse {
+                        id: "task_1".to_string(),
+                        name: TASK_TOOL_NAME.to_string(),
+                        input: json!({"description": "Read notes", "prompt": "Summarise notes.txt", "agent_type": "explore"}),
+                    }],
+                ),
+                (false, true) => reply("Done.", vec![]),
+            })
+        }
+    }
+
+    fn explore() -> AgentType {
+        AgentType::parse(
+            "explore",
+            "+++\ndescription = \"Finds code\"\ntools = [\"read\", \"glob\"]\n+++\nYou find code.\n",
+            true,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_agent_types() {
+        let agent_type = explore();
+        assert_eq!(agent_type.name, "explore");
+        assert_eq!(agent_type.description, "Finds code");
+        assert_eq!(agent_type.system_prompt, "You find code.");
+        assert_eq!(agent_type.tools, Some(vec!["read".to_string(), "glob".to_string()]));
+
+        let agent_type =
+            AgentType::parse("review", "name = \"reviewer\"\ndescription = \"Reviews\"\nmax_turns = 5", false).unwrap();
+        assert_eq!(agent_type.name, "reviewer");
+        assert_eq!(agent_type.max_turns, Some(5));
+
+        assert!(AgentType::parse("bad", "no front matter", true).is_err());
+    }
+
+    #[test]
+    fn test_project_types_replace_builtin() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let dir = temp.path().join(".matw/agents");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("general.toml"), "description = \"Custom\"").unwrap();
+        std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let types = load_agent_types(temp.path()).unwrap();
+
+        let general = types.iter().find(|t| t.name == "general").unwrap();
+        assert_eq!(general.description, "Custom");
+        assert!(!types.iter().any(|t| t.name == "notes"));
+    }
+
+    #[tokio::test]
+    async fn test_task_runs_sub_agent() {
+        let temp = tempfile::TempDir::new().unwrap();
+        std::fs::write(temp.path().join("notes.txt"), "hello").unwrap();
+        let provider = Arc::new(DelegatingProvider {
+            requests: Mutex::new(Vec::new()),
+        });
+        let tools = Arc::new(ToolRegistry::new());
+        for tool in [Arc::new(ReadTool::new()) as Arc<dyn Tool>, Arc::new(GlobTool::new()), Arc::new(WriteTool::new())] {
+            tools.register(tool, ToolSource::BuiltIn).unwrap();
+        }
+        let task = TaskTool::new(provider.clone(), &tools, vec![AgentType::general(), explore()]);
+        tools.register(Arc::new(task), ToolSource::BuiltIn).unwrap();
+        let agent = Agent::new(provider.clone(), tools);
+        let mut session = Session::new(temp.path().to_path_buf());
+        session.add_message(Message::new_user("What do the notes say?".to_string()));
+        let usage = Arc::new(Mutex::new(Usage::default()));
+        let recorded = usage.clone();
+        let agent = agent.with_observer(Arc::new(move |event: &AgentEvent| {
+            if let AgentEvent::TurnCompleted { usage } = event {
+                *recorded.lock().unwrap() = *usage;
+            }
+        }));
+
+        agent.process(&mut session, &CancellationToken::new()).await.unwrap();
+
+        let result = session
+            .messages()
+            .iter()
+            .find_map(|m| match m.content() {
+                Content::ToolResult { content, is_error, .. } => Some((content.clone(), *is_error)),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(result, ("The notes say hello.".to_string(), false));
+
+        // The sub-agent sees its own prompt and only the explore tools
+        let requests = provider.requests.lock().unwrap();
+        let sub_request = &requests[1];
+        assert!(sub_request.system_prompt.as_deref().unwrap().starts_with("You find code."));
+        let names: Vec<&str> = sub_request.tools.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["read", "glob"]);
+
+        // Two main and two sub-agent responses
+        assert_eq!(usage.lock().unwrap().input_tokens, 400);
+    }
+}
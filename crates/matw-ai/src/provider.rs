@@ -46,7 +46,7 @@ pub enum StopReason {
     ToolUse,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub struct Usage {
     pub input_tokens: u32,
     pub output_tokens: u32,
@@ -79,9 +79,16 @@ impl Stream for ChunkStream {
 pub enum Chunk {
     Delta(String),
     ToolUse(ToolUse),
+    /// Why the model stopped, with the token usage of the response
+    Stop { reason: StopReason, usage: Usage },
     Done,
 }
 
+/// A model provider.
+///
+/// Requests are cancelled by dropping them: dropping the future returned by
+/// `complete` or `stream_completion`, or the `ChunkStream` itself, must abort
+/// the underlying HTTP request. The agent relies on this to cancel turns.
 #[async_trait]
 pub trait AIProvider: Send + Sync {
     fn name(&self) -> &str;
@@ -97,6 +104,28 @@ pub trait AIProvider: Send + Sync {
     ) -> Result<CompletionResponse, AIError>;
 }
 
+/// Lets agents and sub-agents share one provider
+#[async_trait]
+impl<P: AIProvider + ?Sized> AIProvider for std::sync::Arc<P> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    async fn stream_completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<ChunkStream, AIError> {
+        (**self).stream_completion(request).await
+    }
+
+    async fn complete(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse, AIError> {
+        (**self).complete(request).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,6 +1,9 @@
 use anyhow::Result;
+use matw_agent::{AgentConfig, Pricing};
+use matw_tools::{PathPolicy, ToolFilter};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::time::Duration;
 
 /// Default configuration file location
 fn default_config_path() -> PathBuf {
@@ -10,6 +13,15 @@ fn default_config_path() -> PathBuf {
         .join("config.toml")
 }
 
+/// Where sessions are saved after each turn, resumed from, and served from
+/// by `matw mcp serve`
+pub fn sessions_dir() -> PathBuf {
+    dirs::home_dir()
+        .expect("Unable to determine home directory")
+        .join(".matw")
+        .join("sessions")
+}
+
 /// CLI configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -36,6 +48,42 @@ pub struct Config {
     /// Temperature for generation
     #[serde(default = "default_temperature")]
     pub temperature: f32,
+
+    /// Model responses per turn before the agent stops
+    #[serde(default = "default_max_turns")]
+    pub max_turns: usize,
+
+    /// Wall-clock seconds per turn
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_duration_secs: Option<u64>,
+
+    /// Input and output tokens per turn
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_budget: Option<u64>,
+
+    /// US dollars per turn
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_budget: Option<f64>,
+
+    /// Model prices for the cost budget (`[pricing]` with `input_per_mtok` / `output_per_mtok`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pricing: Option<Pricing>,
+
+    /// Replaces the base instructions of the system prompt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+
+    /// Appended to the end of the system prompt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub append_system_prompt: Option<String>,
+
+    /// Tools offered to the model (`[tools]` with `enabled` / `disabled` lists)
+    #[serde(default)]
+    pub tools: ToolFilter,
+
+    /// Paths outside the working directory: `"flag"` (the default) or `"deny"`
+    #[serde(default)]
+    pub path_policy: PathPolicy,
 }
 
 fn default_provider() -> String {
@@ -54,6 +102,10 @@ fn default_temperature() -> f32 {
     0.7
 }
 
+fn default_max_turns() -> usize {
+    10
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -63,6 +115,15 @@ impl Default for Config {
             model: default_model(),
             max_tokens: default_max_tokens(),
             temperature: default_temperature(),
+            max_turns: default_max_turns(),
+            max_duration_secs: None,
+            token_budget: None,
+            cost_budget: None,
+            pricing: None,
+            system_prompt: None,
+            append_system_prompt: None,
+            tools: ToolFilter::default(),
+            path_policy: PathPolicy::default(),
         }
     }
 }
@@ -82,6 +143,21 @@ impl Config {
         Ok(config)
     }
 
+    /// Model settings and limits for the agent
+    pub fn agent_config(&self) -> AgentConfig {
+        AgentConfig {
+            model: self.model.clone(),
+            max_tokens: Some(u32::try_from(self.max_tokens).unwrap_or(u32::MAX)),
+            temperature: Some(self.temperature),
+            max_turns: self.max_turns,
+            max_duration: self.max_duration_secs.map(Duration::from_secs),
+            token_budget: self.token_budget,
+            cost_budget: self.cost_budget,
+            pricing: self.pricing,
+            path_policy: self.path_policy,
+        }
+    }
+
     /// Save configuration to file
     pub fn save(&self) -> Result<()> {
         let path = default_config_path();
@@ -120,6 +196,15 @@ mod tests {
             model: "glm-4".to_string(),
             max_tokens: 4096,
             temperature: 0.5,
+            max_turns: 10,
+            max_duration_secs: None,
+            token_budget: None,
+            cost_budget: None,
+            pricing: None,
+            system_prompt: None,
+            append_system_prompt: None,
+            tools: ToolFilter::default(),
+            path_policy: PathPolicy::default(),
         };
 
         let toml_str = toml::to_string_pretty(&config).unwrap();
@@ -141,5 +226,51 @@ mod tests {
         assert_eq!(config.model, "moonshot-v1-8k");
         assert_eq!(config.max_tokens, 2048);
         assert_eq!(config.temperature, 0.8);
+        assert_eq!(config.tools, ToolFilter::default());
+    }
+
+    #[test]
+    fn test_config_tool_filter() {
+        let toml_str = r#"
+            [tools]
+            disabled = ["bash", "mcp__github__*"]
+        "#;
+
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.tools.enabled, None);
+        assert!(!config.tools.allows("bash"));
+        assert!(!config.tools.allows("mcp__github__create_issue"));
+        assert!(config.tools.allows("read"));
+    }
+
+    #[test]
+    fn test_config_limits() {
+        let toml_str = r#"
+            model = "custom-model"
+            max_turns = 25
+            max_duration_secs = 600
+            cost_budget = 2.5
+
+            [pricing]
+            input_per_mtok = 1.0
+            output_per_mtok = 4.0
+        "#;
+
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let agent = config.agent_config();
+        assert_eq!(agent.model, "custom-model");
+        assert_eq!(agent.max_tokens, Some(8192));
+        assert_eq!(agent.max_turns, 25);
+        assert_eq!(agent.max_duration, Some(Duration::from_secs(600)));
+        assert_eq!(agent.token_budget, None);
+        assert_eq!(agent.cost_budget, Some(2.5));
+        assert_eq!(agent.resolved_pricing().unwrap().output_per_mtok, 4.0);
+        assert_eq!(agent.path_policy, PathPolicy::Flag);
+    }
+
+    #[test]
+    fn test_config_path_policy() {
+        let config: Config = toml::from_str(r#"path_policy = "deny""#).unwrap();
+        assert_eq!(config.agent_config().path_policy, PathPolicy::Deny);
     }
 }
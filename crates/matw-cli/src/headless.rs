@@ -0,0 +1,206 @@
+//! Headless runs
+//!
+//! `matw --prompt <text>` runs one agent turn without the TUI. The answer is
+//! printed when the turn ends, or with `--json` every event of the turn is
+//! printed as a line of JSON while it runs. No one is there to approve tool
+//! calls, so calls that the permission rules ask about are denied.
+
+use anyhow::Result;
+use matw_agent::{
+    load_agent_types, Agent, AgentError, HookRunner, JsonLinesObserver, PermissionEngine, TaskTool, TracingObserver,
+};
+use matw_ai::providers::GLMProvider;
+use matw_ai::AIProvider;
+use matw_core::{Content, Role, Session, TrustStore};
+use matw_mcp::{McpConfig, McpManager};
+use matw_tools::{ToolRegistry, ToolSource};
+use matw_tui::RunOptions;
+use std::io::Write;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+/// How a headless run reports its turn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    /// The model's final answer
+    Text,
+    /// Every `AgentEvent` as a line of JSON
+    Json,
+}
+
+/// Run one turn on `prompt` with the same tools, MCP servers, permissions
+/// and hooks as the TUI, printing it to stdout. Ctrl-C interrupts the turn.
+pub async fn run(mut session: Session, options: RunOptions, api_key: String, prompt: String, output: Output) -> Result<()> {
+    let working_dir = session.context().working_dir().to_path_buf();
+    let tools = Arc::new(ToolRegistry::with_builtins());
+    tools.set_filter(options.tools);
+
+    // Project files that run commands are ignored until the user approves them
+    let trust = TrustStore::load_user()?;
+    for file in trust.unapproved(&working_dir) {
+        eprintln!("Not approved, so not used: {} (run `matw trust`)", file.display());
+    }
+
+    // MCP servers run until the manager is dropped at the end of the run
+    let mut mcp = McpManager::new(tools.clone(), &working_dir);
+    for (server, e) in mcp.start_all(&McpConfig::load_all(&working_dir, &trust)?).await {
+        eprintln!("MCP server {} failed to start: {}", server, e);
+    }
+
+    let provider = Arc::new(GLMProvider::new(api_key, None));
+    let permissions = Arc::new(PermissionEngine::load(&working_dir)?);
+    let hooks = HookRunner::load(&working_dir, &trust)?;
+    let task = TaskTool::new(provider.clone(), &tools, load_agent_types(&working_dir)?)
+        .with_config(options.agent.clone())
+        .with_context(session.context().clone())
+        .with_permissions(permissions.clone())
+        .with_hooks(&hooks);
+    tools.register(Arc::new(task), ToolSource::BuiltIn)?;
+    let agent = Agent::new(provider, tools)
+        .with_config(options.agent)
+        .with_permissions(permissions)
+        .with_hooks(hooks)
+        .with_system_prompt(options.system_prompt)
+        .with_observer(Arc::new(TracingObserver));
+
+    let cancel = CancellationToken::new();
+    let interrupt = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            interrupt.cancel();
+        }
+    });
+
+    let result = run_turn(agent, &mut session, prompt, output, std::io::stdout(), &cancel).await;
+    if let Some(dir) = &options.sessions_dir {
+        session.save(dir)?;
+    }
+    drop(mcp);
+    Ok(result?)
+}
+
+/// Run one turn of `agent` on `prompt`, reporting it to `out`
+pub async fn run_turn<P, W>(
+    agent: Agent<P>,
+    session: &mut Session,
+    prompt: String,
+    output: Output,
+    mut out: W,
+    cancel: &CancellationToken,
+) -> Result<(), AgentError>
+where
+    P: AIProvider,
+    W: Write + Send + 'static,
+{
+    if output == Output::Json {
+        let agent = agent.with_observer(Arc::new(JsonLinesObserver::new(out)));
+        agent.submit_prompt(session, prompt).await?;
+        return agent.process_streaming(session, cancel).await;
+    }
+
+    agent.submit_prompt(session, prompt).await?;
+    agent.process(session, cancel).await?;
+    if let Some(answer) = final_answer(session) {
+        writeln!(out, "{}", answer).ok();
+    }
+    Ok(())
+}
+
+/// The text of the model's last response
+fn final_answer(session: &Session) -> Option<&str> {
+    session.messages().iter().rev().find_map(|message| match message.content() {
+        Content::Text(text) if message.role() == Role::Assistant => Some(text.as_str()),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use matw_ai::{AIError, Chunk, ChunkStream, CompletionRequest, CompletionResponse, StopReason, Usage};
+    use std::sync::Mutex;
+
+    /// Provider that always gives the same answer
+    struct FixedProvider;
+
+    #[async_trait]
+    impl AIProvider for FixedProvider {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        async fn stream_completion(&self, request: CompletionRequest) -> Result<ChunkStream, AIError> {
+            let response = self.complete(request).await?;
+            let chunks = vec![
+                Chunk::Delta(response.content),
+                Chunk::Stop {
+                    reason: response.stop_reason,
+                    usage: response.usage,
+                },
+            ];
+            Ok(ChunkStream::new(Box::pin(futures::stream::iter(chunks.into_iter().map(Ok)))))
+        }
+
+        async fn complete(&self, _request: CompletionRequest) -> Result<CompletionResponse, AIError> {
+            Ok(CompletionResponse {
+                content: "All done.".to_string(),
+                tool_uses: vec![],
+                stop_reason: StopReason::EndTurn,
+                usage: Usage {
+                    input_tokens: 5,
+                    output_tokens: 2,
+                },
+            })
+        }
+    }
+
+    /// Output that the test can read after the observer has taken it
+    #[derive(Clone, Default)]
+    struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedOutput {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    async fn run_fixed(output: Output) -> String {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut session = Session::new(temp.path().to_path_buf());
+        let agent = Agent::new(FixedProvider, Arc::new(ToolRegistry::new()));
+        let out = SharedOutput::default();
+
+        run_turn(agent, &mut session, "hi".to_string(), output, out.clone(), &CancellationToken::new())
+            .await
+            .unwrap();
+        out.text()
+    }
+
+    #[tokio::test]
+    async fn test_text_output_prints_answer() {
+        assert_eq!(run_fixed(Output::Text).await, "All done.\n");
+    }
+
+    #[tokio::test]
+    async fn test_json_output_prints_events() {
+        let output = run_fixed(Output::Json).await;
+
+        let types: Vec<String> = output
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["type"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(types.first().map(String::as_str), Some("turn_started"));
+        assert_eq!(types.last().map(String::as_str), Some("turn_completed"));
+    }
+}
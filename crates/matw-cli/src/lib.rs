@@ -3,8 +3,11 @@
 //! Provides command-line interface and session management for MATW.
 
 pub mod config;
+pub mod headless;
 pub mod logo;
+pub mod mcp;
 pub mod session;
+pub mod trust;
 
 pub use config::Config;
-pub use session::{detect_git_info, initialize_session, load_claude_md};
+pub use session::{detect_git_info, initialize_session, resume_session};
@@ -1,13 +1,14 @@
-mod config;
-mod logo;
-mod session;
-
 use anyhow::Result;
-use clap::Parser;
-use config::Config;
-use logo::{print_logo, print_logo_with_version};
-use session::initialize_session;
-use std::path::PathBuf;
+use clap::{Parser, Subcommand};
+use matw_cli::logo::{print_logo, print_logo_with_version};
+use matw_agent::SystemPromptBuilder;
+use matw_cli::headless::Output;
+use matw_cli::{initialize_session, resume_session, Config};
+use matw_core::SessionMode;
+use matw_tools::PathPolicy;
+use matw_tui::RunOptions;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 /// MATW - AI-powered coding assistant in Rust
 #[derive(Parser, Debug)]
@@ -33,9 +34,94 @@ struct Args {
     #[arg(short, long)]
     config: Option<PathBuf>,
 
+    /// Maximum tokens in each response
+    #[arg(long)]
+    max_tokens: Option<usize>,
+
+    /// Sampling temperature
+    #[arg(long)]
+    temperature: Option<f32>,
+
+    /// Model responses per turn before stopping
+    #[arg(long)]
+    max_turns: Option<usize>,
+
+    /// Wall-clock seconds per turn before stopping
+    #[arg(long)]
+    max_duration: Option<u64>,
+
+    /// Tokens per turn before stopping
+    #[arg(long)]
+    token_budget: Option<u64>,
+
+    /// US dollars per turn before stopping
+    #[arg(long)]
+    cost_budget: Option<f64>,
+
+    /// Replace the base instructions of the system prompt
+    #[arg(long)]
+    system_prompt: Option<String>,
+
+    /// Append text to the system prompt (repeatable)
+    #[arg(long)]
+    append_system_prompt: Vec<String>,
+
+    /// Refuse file paths outside the working directory instead of only flagging them
+    #[arg(long)]
+    deny_outside_roots: bool,
+
+    /// Start in plan mode: read-only tools until a plan is approved
+    #[arg(long)]
+    plan: bool,
+
+    /// Resume a saved session by its id
+    #[arg(long, value_name = "SESSION_ID")]
+    resume: Option<uuid::Uuid>,
+
+    /// Run one turn with this prompt without the TUI and print the answer
+    #[arg(short, long)]
+    prompt: Option<String>,
+
+    /// With --prompt, print every event of the turn as a line of JSON
+    #[arg(long, requires = "prompt")]
+    json: bool,
+
+    /// Log every event of agent turns to this file
+    #[arg(long, value_name = "FILE")]
+    log_file: Option<PathBuf>,
+
     /// Run in simple mode (without TUI)
     #[arg(long)]
     simple: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Model Context Protocol
+    Mcp {
+        #[command(subcommand)]
+        command: McpCommand,
+    },
+    /// Approve the project's files that run commands, such as .matw/hooks.toml
+    Trust,
+}
+
+#[derive(Subcommand, Debug)]
+enum McpCommand {
+    /// Serve the built-in tools to MCP clients over stdio, or over HTTP with --http
+    Serve {
+        /// Serve Streamable HTTP (and legacy HTTP+SSE) on this address, e.g. 127.0.0.1:8080.
+        /// Clients need the bearer token in MATW_MCP_TOKEN, or one generated and printed.
+        #[arg(long, value_name = "ADDR")]
+        http: Option<std::net::SocketAddr>,
+
+        /// Also offer tools that change things, such as bash and write, over HTTP
+        #[arg(long, requires = "http")]
+        allow_write: bool,
+    },
 }
 
 #[tokio::main]
@@ -55,11 +141,65 @@ async fn main() -> Result<()> {
     if let Some(api_key) = args.api_key {
         config.api_key = Some(api_key);
     }
+    if let Some(max_tokens) = args.max_tokens {
+        config.max_tokens = max_tokens;
+    }
+    if let Some(temperature) = args.temperature {
+        config.temperature = temperature;
+    }
+    if let Some(max_turns) = args.max_turns {
+        config.max_turns = max_turns;
+    }
+    if let Some(secs) = args.max_duration {
+        config.max_duration_secs = Some(secs);
+    }
+    if let Some(budget) = args.token_budget {
+        config.token_budget = Some(budget);
+    }
+    if let Some(budget) = args.cost_budget {
+        config.cost_budget = Some(budget);
+    }
+    if args.deny_outside_roots {
+        config.path_policy = PathPolicy::Deny;
+    }
+
+    if let Some(path) = &args.log_file {
+        init_logging(path)?;
+    }
 
     // Determine working directory
     let working_dir = args.dir.unwrap_or_else(|| std::env::current_dir().unwrap());
 
-    if args.simple {
+    if let Some(Command::Mcp {
+        command: McpCommand::Serve { http, allow_write },
+    }) = args.command
+    {
+        match http {
+            Some(addr) => {
+                let token = std::env::var("MATW_MCP_TOKEN").ok().filter(|t| !t.is_empty());
+                matw_cli::mcp::serve_http(working_dir, config.tools, addr, token, allow_write).await?;
+            }
+            // Stdout carries the protocol, so nothing else may be printed
+            None => matw_cli::mcp::serve(working_dir, config.tools, config.path_policy).await?,
+        }
+    } else if let Some(Command::Trust) = args.command {
+        let Some(trust_file) = matw_core::trust::user_trust_file() else {
+            anyhow::bail!("Unable to determine home directory");
+        };
+        matw_cli::trust::run(&working_dir, &trust_file, std::io::stdin().lock(), std::io::stdout())?;
+    } else if let Some(prompt) = args.prompt {
+        let mut session = match args.resume {
+            Some(id) => resume_session(&matw_cli::config::sessions_dir(), id)?,
+            None => initialize_session(working_dir)?,
+        };
+        if args.plan {
+            session.context_mut().set_mode(SessionMode::Plan);
+        }
+        let output = if args.json { Output::Json } else { Output::Text };
+        let api_key = config.api_key.clone().unwrap_or_default();
+        let options = run_options(&config, &args.system_prompt, &args.append_system_prompt);
+        matw_cli::headless::run(session, options, api_key, prompt, output).await?;
+    } else if args.simple {
         // Simple mode: just print session info
         run_simple_mode(working_dir)?;
     } else {
@@ -67,12 +207,53 @@ async fn main() -> Result<()> {
         print_logo();
         println!("  Starting TUI... Press 'q' to quit, 'Esc' to exit\n");
         std::thread::sleep(std::time::Duration::from_millis(800));
-        matw_tui::run().await?;
+        let options = run_options(&config, &args.system_prompt, &args.append_system_prompt);
+        let mut session = match args.resume {
+            Some(id) => resume_session(&matw_cli::config::sessions_dir(), id)?,
+            None => initialize_session(working_dir)?,
+        };
+        if args.plan {
+            session.context_mut().set_mode(SessionMode::Plan);
+        }
+        matw_tui::run(session, options).await?;
     }
 
     Ok(())
 }
 
+/// Settings for a TUI or headless run
+fn run_options(config: &Config, system_prompt_arg: &Option<String>, appends: &[String]) -> RunOptions {
+    RunOptions {
+        agent: config.agent_config(),
+        system_prompt: system_prompt(config, system_prompt_arg, appends),
+        tools: config.tools.clone(),
+        sessions_dir: Some(matw_cli::config::sessions_dir()),
+    }
+}
+
+/// Send `tracing` output, including every agent event, to `path`
+fn init_logging(path: &Path) -> Result<()> {
+    let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    tracing_subscriber::fmt()
+        .with_writer(Mutex::new(file))
+        .with_ansi(false)
+        .with_max_level(tracing_subscriber::filter::LevelFilter::DEBUG)
+        .init();
+    Ok(())
+}
+
+/// Apply prompt overrides from config, then from the command line
+fn system_prompt(config: &Config, system_prompt: &Option<String>, appends: &[String]) -> SystemPromptBuilder {
+    let mut builder = SystemPromptBuilder::new();
+    if let Some(base) = system_prompt.as_ref().or(config.system_prompt.as_ref()) {
+        builder = builder.with_override(base.clone());
+    }
+    for text in config.append_system_prompt.iter().chain(appends) {
+        builder = builder.with_append(text.clone());
+    }
+    builder
+}
+
 fn run_simple_mode(working_dir: PathBuf) -> Result<()> {
     // Print large CLI-style logo with version
     print_logo_with_version(env!("CARGO_PKG_VERSION"));
@@ -89,8 +270,12 @@ fn run_simple_mode(working_dir: PathBuf) -> Result<()> {
         println!();
     }
 
-    if session.context().claude_md().is_some() {
-        println!("CLAUDE.md loaded");
+    if !session.context().instructions().is_empty() {
+        println!("Instructions loaded from:");
+        for file in session.context().instructions() {
+            println!("  {}", file.path.display());
+        }
+        println!();
     }
 
     println!("Session ID: {}", session.id());
@@ -127,4 +312,66 @@ mod tests {
         let args = args.unwrap();
         assert!(args.simple);
     }
+
+    #[test]
+    fn test_args_headless() {
+        let args = Args::try_parse_from(["matw", "-p", "fix the build", "--json"]).unwrap();
+        assert_eq!(args.prompt.as_deref(), Some("fix the build"));
+        assert!(args.json);
+
+        assert!(Args::try_parse_from(["matw", "--json"]).is_err());
+    }
+
+    #[test]
+    fn test_args_mcp_serve() {
+        let args = Args::try_parse_from(["matw", "--dir", "/tmp", "mcp", "serve"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Command::Mcp {
+                command: McpCommand::Serve { http: None, allow_write: false }
+            })
+        ));
+
+        let args = Args::try_parse_from(["matw", "mcp", "serve", "--http", "127.0.0.1:8080"]).unwrap();
+        let Some(Command::Mcp {
+            command: McpCommand::Serve { http: Some(addr), allow_write },
+        }) = args.command
+        else {
+            panic!("expected an HTTP address");
+        };
+        assert_eq!(addr.port(), 8080);
+        assert!(!allow_write);
+
+        let args = Args::try_parse_from(["matw", "mcp", "serve", "--http", "[::1]:80", "--allow-write"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Command::Mcp {
+                command: McpCommand::Serve { allow_write: true, .. }
+            })
+        ));
+        assert!(Args::try_parse_from(["matw", "mcp", "serve", "--allow-write"]).is_err());
+    }
+
+    #[test]
+    fn test_system_prompt_flags_override_config() {
+        let config = Config {
+            system_prompt: Some("From config.".to_string()),
+            append_system_prompt: Some("Config append.".to_string()),
+            ..Config::default()
+        };
+        let args = Args::try_parse_from([
+            "matw",
+            "--system-prompt",
+            "From flag.",
+            "--append-system-prompt",
+            "Flag append.",
+        ])
+        .unwrap();
+
+        let prompt = system_prompt(&config, &args.system_prompt, &args.append_system_prompt)
+            .build(&matw_core::Context::new(PathBuf::from("/tmp")), &[]);
+
+        assert!(prompt.starts_with("From flag.\n\n# Environment"));
+        assert!(prompt.ends_with("Config append.\n\nFlag append."));
+    }
 }
@@ -0,0 +1,115 @@
+//! `matw mcp` subcommands
+//!
+//! `matw mcp serve` exposes the built-in tools to other MCP clients, such as
+//! editors and agents, over stdio or HTTP, with the project's files and
+//! session transcripts as resources. Over HTTP every request needs a bearer
+//! token, file tools cannot reach outside the project, and only read-only
+//! tools are offered unless writes are allowed.
+
+use anyhow::Result;
+use crate::config::sessions_dir;
+use matw_mcp::{register_tools_with_context, serve_stdio, HttpServer, MCPServer, ProjectFiles, SessionTranscripts};
+use matw_tools::{PathPolicy, Tool, ToolContext, ToolFilter, ToolRegistry};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Tools that only make sense inside a matw session
+const SESSION_TOOLS: &[&str] = &["todo_write"];
+
+/// Built-in tools offered over MCP, after the config's enable/disable filter
+pub fn served_tools(filter: ToolFilter) -> Vec<Arc<dyn Tool>> {
+    let registry = ToolRegistry::with_builtins();
+    registry.set_filter(filter);
+    registry
+        .tools()
+        .into_iter()
+        .filter(|tool| !SESSION_TOOLS.contains(&tool.name()))
+        .collect()
+}
+
+/// Tools offered over HTTP: the read-only ones, plus the rest (such as `bash`
+/// and `write`) only if `allow_write`
+pub fn served_http_tools(filter: ToolFilter, allow_write: bool) -> Vec<Arc<dyn Tool>> {
+    served_tools(filter)
+        .into_iter()
+        .filter(|tool| allow_write || tool.is_read_only())
+        .collect()
+}
+
+async fn tool_server(working_dir: PathBuf, tools: Vec<Arc<dyn Tool>>, path_policy: PathPolicy) -> MCPServer {
+    let server = MCPServer::new();
+    server.register_resources(Box::new(ProjectFiles::new(&working_dir))).await;
+    server
+        .register_resources(Box::new(SessionTranscripts::new(sessions_dir(), &working_dir)))
+        .await;
+    let context = ToolContext::new(working_dir).with_path_policy(path_policy);
+    register_tools_with_context(&server, tools, context).await;
+    server
+}
+
+/// Serve the built-in tools and resources over stdio, resolving paths against
+/// `working_dir` and handling paths outside it by `path_policy`
+pub async fn serve(working_dir: PathBuf, filter: ToolFilter, path_policy: PathPolicy) -> Result<()> {
+    serve_stdio(&tool_server(working_dir, served_tools(filter), path_policy).await).await?;
+    Ok(())
+}
+
+/// The server behind `serve_http`. No one is asked to approve calls made over
+/// HTTP, so file tools always refuse paths outside `working_dir`, whatever
+/// the configured path policy.
+pub async fn http_server(working_dir: PathBuf, filter: ToolFilter, token: String, allow_write: bool) -> HttpServer {
+    let tools = served_http_tools(filter, allow_write);
+    HttpServer::new(tool_server(working_dir, tools, PathPolicy::Deny).await).with_bearer_token(token)
+}
+
+/// Serve the built-in tools and resources over Streamable HTTP at `/mcp` and legacy
+/// HTTP+SSE at `/sse`, requiring a bearer token. Without `token` one is
+/// generated and printed.
+pub async fn serve_http(
+    working_dir: PathBuf,
+    filter: ToolFilter,
+    addr: SocketAddr,
+    token: Option<String>,
+    allow_write: bool,
+) -> Result<()> {
+    let generated = token.is_none();
+    let token = token.unwrap_or_else(|| Uuid::new_v4().simple().to_string());
+    let server = http_server(working_dir, filter, token.clone(), allow_write).await;
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    eprintln!("Serving MCP on http://{}/mcp", listener.local_addr()?);
+    if generated {
+        eprintln!("Clients must send: Authorization: Bearer {}", token);
+    }
+    matw_mcp::serve_http(Arc::new(server), listener).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_served_tools_follow_filter() {
+        let filter = ToolFilter {
+            enabled: None,
+            disabled: vec!["bash".to_string()],
+        };
+        let names: Vec<String> = served_tools(filter).iter().map(|t| t.name().to_string()).collect();
+        assert_eq!(names, vec!["glob", "read", "write"]);
+    }
+
+    #[test]
+    fn test_http_tools_are_read_only_unless_allowed() {
+        let names = |allow_write| -> Vec<String> {
+            served_http_tools(ToolFilter::default(), allow_write)
+                .iter()
+                .map(|t| t.name().to_string())
+                .collect()
+        };
+        assert_eq!(names(false), vec!["glob", "read"]);
+        assert_eq!(names(true), vec!["bash", "glob", "read", "write"]);
+    }
+}
@@ -1,7 +1,9 @@
 use anyhow::Result;
+use matw_agent::instructions;
 use matw_core::{Context, GitInfo, Session};
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use uuid::Uuid;
 
 /// Detect Git repository information
 pub fn detect_git_info(dir: &Path) -> Option<GitInfo> {
@@ -43,36 +45,54 @@ pub fn detect_git_info(dir: &Path) -> Option<GitInfo> {
         .map(|s| PathBuf::from(s.trim()))
         .unwrap_or_else(|| dir.to_path_buf());
 
+    // Check for uncommitted changes
+    let dirty = Command::new("git")
+        .args(["-C", dir.to_str()?, "status", "--porcelain"])
+        .output()
+        .ok()
+        .is_some_and(|o| !o.stdout.is_empty());
+
     Some(GitInfo {
         branch,
         commit,
         root,
+        dirty,
     })
 }
 
-/// Load CLAUDE.md content if it exists
-pub fn load_claude_md(git_root: &Path) -> Option<String> {
-    let claude_md_path = git_root.join("CLAUDE.md");
-    std::fs::read_to_string(claude_md_path).ok()
-}
-
 /// Initialize a new session with context
 pub fn initialize_session(working_dir: PathBuf) -> Result<Session> {
     let git_info = detect_git_info(&working_dir);
 
-    let claude_md = if let Some(ref git) = git_info {
-        load_claude_md(&git.root)
-    } else {
-        None
-    };
+    let git_root = git_info.as_ref().map(|git| git.root.clone());
 
-    let mut context = Context::with_details(working_dir.clone(), git_info, claude_md);
+    let mut context = Context::with_details(working_dir.clone(), git_info, None);
     context.set_environment(std::env::vars().collect());
 
+    let user_dir = instructions::user_instructions_dir();
+    for file in instructions::discover(&working_dir, git_root.as_deref(), user_dir.as_deref()) {
+        context.add_instructions(file);
+    }
+
     let session = Session::with_context(context);
     Ok(session)
 }
 
+/// Resume a session saved in `dir`
+///
+/// The environment is never saved, so it is taken from this process, and
+/// Git information is detected again since the repository may have moved on.
+pub fn resume_session(dir: &Path, id: Uuid) -> Result<Session> {
+    let mut session = Session::load_from(dir, id)?;
+    let context = session.context_mut();
+    if let Some(git_info) = detect_git_info(context.working_dir()) {
+        context.set_git_info(git_info);
+    }
+    context.set_environment(std::env::vars().collect());
+    session.resume();
+    Ok(session)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -87,6 +107,21 @@ mod tests {
         assert_eq!(session.context().working_dir(), temp);
     }
 
+    #[test]
+    fn test_resume_session() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut session = initialize_session(temp.path().to_path_buf()).unwrap();
+        session.context_mut().set_plan(Some("Ship it".to_string()));
+        session.close();
+        session.save(temp.path()).unwrap();
+
+        let resumed = resume_session(temp.path(), session.id()).unwrap();
+        assert_eq!(resumed.state(), SessionState::Active);
+        assert_eq!(resumed.context().plan(), Some("Ship it"));
+        assert!(!resumed.context().environment().is_empty());
+        assert!(resume_session(temp.path(), Uuid::new_v4()).is_err());
+    }
+
     #[test]
     fn test_initialize_session_with_git() {
         let temp = std::env::temp_dir().join("matw-test-git");
@@ -0,0 +1,65 @@
+//! `matw trust`
+//!
+//! Shows each of the project's files that run commands, such as
+//! `.matw/hooks.toml`, that is new or changed since it was last approved, and
+//! records the user's approval. Until then matw ignores the file.
+
+use anyhow::Result;
+use matw_core::TrustStore;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+/// Ask about the unapproved files of `project_dir`, saving approvals to `trust_file`
+pub fn run(project_dir: &Path, trust_file: &Path, mut input: impl BufRead, mut output: impl Write) -> Result<()> {
+    let mut trust = TrustStore::load(trust_file)?;
+    let files = trust.unapproved(project_dir);
+    if files.is_empty() {
+        writeln!(output, "Nothing to approve in {}", project_dir.display())?;
+        return Ok(());
+    }
+
+    for file in files {
+        let content = std::fs::read_to_string(&file)?;
+        writeln!(output, "{}:\n\n{}\n", file.display(), content.trim_end())?;
+        write!(output, "Run the commands in this file in matw sessions here? [y/N] ")?;
+        output.flush()?;
+
+        let mut answer = String::new();
+        input.read_line(&mut answer)?;
+        if answer.trim().eq_ignore_ascii_case("y") {
+            trust.approve(&file, content);
+            writeln!(output, "Approved {}", file.display())?;
+        } else {
+            writeln!(output, "Not approved; {} stays ignored", file.display())?;
+        }
+    }
+
+    trust.save(trust_file)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approves_on_yes_only() {
+        let temp = tempfile::tempdir().unwrap();
+        let hooks = temp.path().join(".matw").join("hooks.toml");
+        std::fs::create_dir_all(hooks.parent().unwrap()).unwrap();
+        std::fs::write(&hooks, "[[hooks]]\nevent = \"Stop\"\ncommand = \"cargo test\"\n").unwrap();
+        let trust_file = temp.path().join("trusted.json");
+
+        let mut output = Vec::new();
+        run(temp.path(), &trust_file, "n\n".as_bytes(), &mut output).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains("cargo test"));
+        assert!(TrustStore::load(&trust_file).unwrap().read_approved(&hooks).is_none());
+
+        run(temp.path(), &trust_file, "y\n".as_bytes(), Vec::new()).unwrap();
+        assert!(TrustStore::load(&trust_file).unwrap().read_approved(&hooks).is_some());
+
+        let mut output = Vec::new();
+        run(temp.path(), &trust_file, "".as_bytes(), &mut output).unwrap();
+        assert!(String::from_utf8(output).unwrap().starts_with("Nothing to approve"));
+    }
+}
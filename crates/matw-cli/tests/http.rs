@@ -0,0 +1,40 @@
+//! Integration tests for `matw mcp serve --http`, with a client talking to
+//! the server on a local port
+
+use matw_cli::mcp::http_server;
+use matw_mcp::McpClient;
+use matw_tools::ToolFilter;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+#[tokio::test]
+async fn test_paths_outside_the_project_are_refused() {
+    let temp = tempfile::TempDir::new().unwrap();
+    let project = temp.path().join("project");
+    std::fs::create_dir(&project).unwrap();
+    std::fs::write(project.join("notes.txt"), "notes").unwrap();
+    let secret = temp.path().join("secret.txt");
+    std::fs::write(&secret, "secret").unwrap();
+
+    let server = http_server(project, ToolFilter::default(), "s3cret".to_string(), false).await;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+    tokio::spawn(matw_mcp::serve_http(Arc::new(server), listener));
+
+    let (tx, _events) = mpsc::unbounded_channel();
+    let headers = [("Authorization".to_string(), "Bearer s3cret".to_string())];
+    let client = McpClient::connect_http(&url, &headers, tx).unwrap();
+    client.initialize().await.unwrap();
+    let cancel = CancellationToken::new();
+
+    let inside = client.call_tool("read", json!({"path": "notes.txt"}), &cancel).await.unwrap();
+    assert!(!inside.is_error);
+
+    for path in [secret.to_str().unwrap(), "../secret.txt"] {
+        let outside = client.call_tool("read", json!({"path": path}), &cancel).await.unwrap();
+        assert!(outside.is_error, "{} was read", path);
+    }
+}
@@ -0,0 +1,100 @@
+//! Integration tests for `matw mcp serve`, talking to the binary over pipes
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+struct Server {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Server {
+    fn start(dir: &Path) -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_matw"))
+            .args(["--dir", dir.to_str().unwrap(), "mcp", "serve"])
+            // Keep the user's config out of the test
+            .env("HOME", dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        Self { child, stdin, stdout }
+    }
+
+    fn send(&mut self, line: &str) -> Value {
+        writeln!(self.stdin, "{}", line).unwrap();
+        self.stdin.flush().unwrap();
+        let mut response = String::new();
+        self.stdout.read_line(&mut response).unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+
+    fn request(&mut self, id: u64, method: &str, params: Value) -> Value {
+        let request = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        self.send(&request.to_string())
+    }
+
+    /// Close stdin and wait for the server to exit
+    fn finish(self) -> bool {
+        let Server { mut child, stdin, .. } = self;
+        drop(stdin);
+        child.wait().unwrap().success()
+    }
+}
+
+#[test]
+fn test_lists_builtin_tools() {
+    let temp = tempfile::TempDir::new().unwrap();
+    let mut server = Server::start(temp.path());
+
+    let response = server.request(1, "tools/list", json!({}));
+
+    let mut names: Vec<&str> = response["result"]["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tool| tool["name"].as_str().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["bash", "glob", "read", "write"]);
+    assert!(server.finish());
+}
+
+#[test]
+fn test_tools_run_in_working_directory() {
+    let temp = tempfile::TempDir::new().unwrap();
+    std::fs::write(temp.path().join("notes.txt"), "first draft").unwrap();
+    let mut server = Server::start(temp.path());
+
+    let read = server.request(1, "tools/call", json!({"name": "read", "arguments": {"path": "notes.txt"}}));
+    assert!(read["result"]["content"][0]["text"].as_str().unwrap().contains("first draft"));
+
+    // Read state is shared across calls, so the file may now be overwritten
+    let write = server.request(
+        2,
+        "tools/call",
+        json!({"name": "write", "arguments": {"path": "notes.txt", "content": "final"}}),
+    );
+    assert!(write["error"].is_null(), "{}", write);
+    assert_eq!(std::fs::read_to_string(temp.path().join("notes.txt")).unwrap(), "final");
+    assert!(server.finish());
+}
+
+#[test]
+fn test_malformed_line_gets_parse_error() {
+    let temp = tempfile::TempDir::new().unwrap();
+    let mut server = Server::start(temp.path());
+
+    let response = server.send("{oops");
+    assert_eq!(response["error"]["code"], -32700);
+
+    // The server keeps going after a bad line
+    let response = server.request(2, "tools/list", json!({}));
+    assert_eq!(response["id"], 2);
+    assert!(server.finish());
+}
@@ -1,3 +1,4 @@
+use crate::todo::Todo;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
@@ -7,14 +8,50 @@ pub struct GitInfo {
     pub branch: String,
     pub commit: String,
     pub root: PathBuf,
+    /// Whether the working tree has uncommitted changes
+    #[serde(default)]
+    pub dirty: bool,
+}
+
+/// A project instruction file (e.g. `MATW.md`) loaded into the context
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstructionFile {
+    pub path: PathBuf,
+    /// Contents with `@path` imports expanded
+    pub content: String,
+    /// Files pulled in through `@path` imports
+    #[serde(default)]
+    pub imports: Vec<PathBuf>,
+}
+
+/// What the agent may do in a session
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionMode {
+    /// All tools are available
+    #[default]
+    Normal,
+    /// Only read-only tools are available until the user approves a plan
+    Plan,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Context {
     working_dir: PathBuf,
     git_info: Option<GitInfo>,
+    /// Never saved with the session: it may hold secrets
+    #[serde(skip)]
     environment: HashMap<String, String>,
     claude_md: Option<String>,
+    #[serde(default)]
+    instructions: Vec<InstructionFile>,
+    #[serde(default)]
+    mode: SessionMode,
+    /// The plan the user approved when leaving plan mode
+    #[serde(default)]
+    plan: Option<String>,
+    #[serde(default)]
+    todos: Vec<Todo>,
 }
 
 impl Context {
@@ -24,6 +61,10 @@ impl Context {
             git_info: None,
             environment: HashMap::new(),
             claude_md: None,
+            instructions: Vec::new(),
+            mode: SessionMode::default(),
+            plan: None,
+            todos: Vec::new(),
         }
     }
 
@@ -33,6 +74,10 @@ impl Context {
             git_info,
             environment: HashMap::new(),
             claude_md,
+            instructions: Vec::new(),
+            mode: SessionMode::default(),
+            plan: None,
+            todos: Vec::new(),
         }
     }
 
@@ -67,4 +112,49 @@ impl Context {
     pub fn set_claude_md(&mut self, content: String) {
         self.claude_md = Some(content);
     }
+
+    /// Instruction files loaded so far, in load order
+    pub fn instructions(&self) -> &[InstructionFile] {
+        &self.instructions
+    }
+
+    /// Whether an instruction file with this path has been loaded
+    pub fn has_instructions(&self, path: &Path) -> bool {
+        self.instructions.iter().any(|f| f.path == path)
+    }
+
+    /// Add an instruction file, returning false if its path was already loaded
+    pub fn add_instructions(&mut self, file: InstructionFile) -> bool {
+        if self.has_instructions(&file.path) {
+            return false;
+        }
+        self.instructions.push(file);
+        true
+    }
+
+    pub fn mode(&self) -> SessionMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: SessionMode) {
+        self.mode = mode;
+    }
+
+    /// The approved plan, if any
+    pub fn plan(&self) -> Option<&str> {
+        self.plan.as_deref()
+    }
+
+    pub fn set_plan(&mut self, plan: Option<String>) {
+        self.plan = plan;
+    }
+
+    /// The session's task list
+    pub fn todos(&self) -> &[Todo] {
+        &self.todos
+    }
+
+    pub fn set_todos(&mut self, todos: Vec<Todo>) {
+        self.todos = todos;
+    }
 }
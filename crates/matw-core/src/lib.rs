@@ -7,10 +7,14 @@ pub mod message;
 pub mod role;
 pub mod content;
 pub mod error;
+pub mod todo;
+pub mod trust;
 
-pub use context::{Context, GitInfo};
+pub use context::{Context, GitInfo, InstructionFile, SessionMode};
 pub use session::{Session, SessionState};
 pub use role::Role;
 pub use content::Content;
 pub use message::Message;
 pub use error::{MatwError, Result};
+pub use todo::{Todo, TodoStatus};
+pub use trust::TrustStore;
@@ -1,6 +1,7 @@
+use crate::error::{MatwError, Result};
 use crate::{context::Context, message::Message};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -69,6 +70,13 @@ impl Session {
         self.messages.push(message);
     }
 
+    /// Remove the message with this id and every message after it,
+    /// returning them. Returns `None` if no message has the id.
+    pub fn truncate_from(&mut self, message_id: Uuid) -> Option<Vec<Message>> {
+        let i = self.messages.iter().position(|m| m.id() == message_id)?;
+        Some(self.messages.split_off(i))
+    }
+
     pub fn close(&mut self) {
         self.state = SessionState::Closed;
     }
@@ -84,12 +92,49 @@ impl Session {
     pub fn to_ai_request(&self) -> Vec<&Message> {
         self.messages.iter().collect()
     }
+
+    /// Write to `<dir>/<session id>.json`, replacing an earlier save.
+    /// The context's environment is left out.
+    pub fn save(&self, dir: &Path) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.json", self.id));
+        std::fs::write(&path, serde_json::to_string(self)?)?;
+        Ok(path)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Load the session with this id saved in `dir`
+    pub fn load_from(dir: &Path, id: Uuid) -> Result<Self> {
+        let path = dir.join(format!("{}.json", id));
+        if !path.exists() {
+            return Err(MatwError::SessionNotFound(id.to_string()));
+        }
+        Self::load(&path)
+    }
+
+    /// Load every session saved in `dir`, skipping files that are not
+    /// sessions. A missing directory has none.
+    pub fn load_all(dir: &Path) -> Vec<Self> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| Self::load(&path).ok())
+            .collect()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::Message;
+    use crate::{Message, SessionMode};
 
     #[test]
     fn test_new_session() {
@@ -118,6 +163,59 @@ mod tests {
         assert_ne!(s1.id(), s2.id());
     }
 
+    #[test]
+    fn test_truncate_from() {
+        let mut session = Session::new(PathBuf::from("/tmp"));
+        session.add_message(Message::new_user("first".to_string()));
+        let second = Message::new_user("second".to_string());
+        let id = second.id();
+        session.add_message(second);
+        session.add_message(Message::new_assistant("reply".to_string()));
+
+        let removed = session.truncate_from(id).unwrap();
+        assert_eq!(removed.len(), 2);
+        assert_eq!(session.message_count(), 1);
+        assert!(session.truncate_from(id).is_none());
+    }
+
+    #[test]
+    fn test_save_and_load_keeps_mode_and_plan() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut session = Session::new(PathBuf::from("/project"));
+        session.add_message(Message::new_user("hello".to_string()));
+        session.context_mut().set_mode(SessionMode::Plan);
+        session.context_mut().set_plan(Some("1. Read\n2. Write".to_string()));
+        session.context_mut().set_env("API_KEY".to_string(), "secret".to_string());
+
+        let path = session.save(temp.path()).unwrap();
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("secret"));
+
+        let loaded = Session::load_from(temp.path(), session.id()).unwrap();
+        assert_eq!(loaded.id(), session.id());
+        assert_eq!(loaded.message_count(), 1);
+        assert_eq!(loaded.context().mode(), SessionMode::Plan);
+        assert_eq!(loaded.context().plan(), Some("1. Read\n2. Write"));
+        assert!(loaded.context().environment().is_empty());
+    }
+
+    #[test]
+    fn test_load_missing_session() {
+        let temp = tempfile::tempdir().unwrap();
+        let result = Session::load_from(temp.path(), Uuid::new_v4());
+        assert!(matches!(result, Err(MatwError::SessionNotFound(_))));
+    }
+
+    #[test]
+    fn test_load_all_skips_other_files() {
+        let temp = tempfile::tempdir().unwrap();
+        Session::new(PathBuf::from("/project")).save(temp.path()).unwrap();
+        std::fs::write(temp.path().join("notes.json"), "{}").unwrap();
+        std::fs::write(temp.path().join("README"), "sessions").unwrap();
+
+        assert_eq!(Session::load_all(temp.path()).len(), 1);
+        assert!(Session::load_all(&temp.path().join("missing")).is_empty());
+    }
+
     #[test]
     fn test_close_session() {
         let mut session = Session::new(PathBuf::from("/tmp"));
@@ -0,0 +1,30 @@
+//! Session task lists
+//!
+//! The agent keeps a list of the steps of multi-step work in the session, so
+//! it survives long transcripts and is saved with the session.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoStatus {
+    Pending,
+    InProgress,
+    Completed,
+}
+
+/// One task in the session's list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Todo {
+    pub content: String,
+    pub status: TodoStatus,
+}
+
+impl Todo {
+    pub fn new(content: impl Into<String>, status: TodoStatus) -> Self {
+        Self {
+            content: content.into(),
+            status,
+        }
+    }
+}
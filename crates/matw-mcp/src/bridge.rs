@@ -1,17 +1,24 @@
 //! Bridge adapter to connect matw-tools to MCP
 
-use super::protocol::ContentItem;
+use super::protocol::{ContentItem, ToolResult};
 use super::server::{MCTool, MCPServer};
-use matw_tools::Tool as MatwTool;
+use matw_tools::{Tool as MatwTool, ToolContext};
 use std::sync::Arc;
 
 pub struct ToolAdapter {
     tool: Arc<dyn MatwTool>,
+    context: ToolContext,
 }
 
 impl ToolAdapter {
+    /// Wrap a tool, running it against the server process's working directory
     pub fn new(tool: Arc<dyn MatwTool>) -> Self {
-        Self { tool }
+        Self::with_context(tool, ToolContext::default())
+    }
+
+    /// Wrap a tool, running it against the given context
+    pub fn with_context(tool: Arc<dyn MatwTool>, context: ToolContext) -> Self {
+        Self { tool, context }
     }
 }
 
@@ -29,19 +36,102 @@ impl MCTool for ToolAdapter {
         self.tool.parameters_schema().clone()
     }
 
-    async fn execute(&self, args: serde_json::Value) -> Result<Vec<ContentItem>, String> {
-        let output = self.tool.execute(args).await
+    async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, String> {
+        self.tool.validate(&args).map_err(|e| e.to_string())?;
+
+        let output = self.tool.execute(args, &self.context).await
             .map_err(|e| e.to_string())?;
 
-        Ok(vec![ContentItem::Text {
-            text: output.content,
-        }])
+        Ok(ToolResult {
+            content: vec![ContentItem::Text { text: output.content }],
+            is_error: output.is_error,
+        })
     }
 }
 
+/// Register tools that run against the server process's working directory
 pub async fn register_tools(server: &MCPServer, tools: Vec<Arc<dyn MatwTool>>) {
+    register_tools_with_context(server, tools, ToolContext::default()).await;
+}
+
+/// Register tools that share one context, so that a file read with one tool
+/// may then be written with another
+pub async fn register_tools_with_context(server: &MCPServer, tools: Vec<Arc<dyn MatwTool>>, context: ToolContext) {
     for tool in tools {
-        let adapter = Box::new(ToolAdapter::new(tool));
+        let adapter = Box::new(ToolAdapter::with_context(tool, context.clone()));
         server.register_tool(adapter).await;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::JsonRpcRequest;
+    use matw_tools::tools::GlobTool;
+    use matw_tools::{ToolError, ToolOutput};
+    use serde_json::{json, Value};
+
+    /// Reports failure through its output rather than an error
+    struct Failing;
+
+    #[async_trait::async_trait]
+    impl MatwTool for Failing {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        fn description(&self) -> &str {
+            "Always fails"
+        }
+
+        fn parameters_schema(&self) -> Value {
+            json!({"type": "object"})
+        }
+
+        async fn execute(&self, _input: Value, _ctx: &ToolContext) -> Result<ToolOutput, ToolError> {
+            Ok(ToolOutput {
+                content: "exit status 1".to_string(),
+                is_error: true,
+                usage: None,
+            })
+        }
+    }
+
+    async fn call(server: &MCPServer, name: &str, arguments: Value) -> Value {
+        let response = server
+            .handle_request(JsonRpcRequest {
+                jsonrpc: "2.0".to_string(),
+                id: json!(1),
+                method: "tools/call".to_string(),
+                params: Some(json!({"name": name, "arguments": arguments})),
+            })
+            .await;
+        serde_json::to_value(response).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_invalid_arguments_are_a_tool_error() {
+        let server = MCPServer::new();
+        register_tools(&server, vec![Arc::new(GlobTool::new())]).await;
+
+        let response = call(&server, "glob", json!({"pattern": 5})).await;
+        assert_eq!(response["result"]["isError"], true);
+        let text = response["result"]["content"][0]["text"].as_str().unwrap();
+        assert!(text.contains("`pattern`: expected string, got integer"));
+
+        let unknown = call(&server, "nope", json!({})).await;
+        assert_eq!(unknown["error"]["code"], -32602);
+    }
+
+    #[tokio::test]
+    async fn test_failed_output_keeps_is_error() {
+        let server = MCPServer::new();
+        register_tools(&server, vec![Arc::new(Failing)]).await;
+
+        let response = call(&server, "failing", json!({})).await;
+        assert_eq!(
+            response["result"],
+            json!({"content": [{"type": "text", "text": "exit status 1"}], "isError": true})
+        );
+    }
+}
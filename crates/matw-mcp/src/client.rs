@@ -0,0 +1,600 @@
+//! MCP client
+//!
+//! Talks to one MCP server: a child process launched from config, spoken to
+//! with newline-delimited JSON-RPC over its stdin and stdout, or a remote
+//! server over one of the HTTP transports in `http_client`. Whatever the
+//! transport, incoming messages go through an `Inbox`: responses go to the
+//! request waiting for them, the server's `ping` is answered, and
+//! notifications the caller cares about are passed on as `ClientEvent`s.
+//! Stdout lines that are not JSON are skipped, since some servers log there.
+
+use super::config::{ServerConfig, TransportKind};
+use super::error::McpError;
+use super::http_client::{LegacySse, StreamableHttp};
+use super::protocol::*;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// Something the server told the client outside of a response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientEvent {
+    /// The server sent `notifications/tools/list_changed`
+    ToolsChanged,
+    /// The connection closed, usually because the server exited
+    Closed,
+}
+
+/// Carries messages to the server; replies come back through the `Inbox`
+#[async_trait]
+pub(crate) trait Transport: Send + Sync {
+    async fn send(&self, message: Value) -> Result<(), McpError>;
+
+    /// Called with the version agreed in `initialize`, for transports that
+    /// send it along with every message
+    fn set_protocol_version(&self, _version: &str) {}
+}
+
+/// Where a transport delivers what the server sends
+pub(crate) struct Inbox {
+    pending: Mutex<HashMap<u64, oneshot::Sender<Result<Value, JsonRpcError>>>>,
+    closed: AtomicBool,
+    events: mpsc::UnboundedSender<ClientEvent>,
+}
+
+impl Inbox {
+    pub(crate) fn new(events: mpsc::UnboundedSender<ClientEvent>) -> Arc<Self> {
+        Arc::new(Self {
+            pending: Mutex::new(HashMap::new()),
+            closed: AtomicBool::new(false),
+            events,
+        })
+    }
+
+    /// Handle a message or batch from the server, returning the replies to
+    /// send back
+    pub(crate) fn receive(&self, message: Value) -> Vec<Value> {
+        let messages = match message {
+            Value::Array(batch) => batch,
+            message => vec![message],
+        };
+        messages.into_iter().filter_map(|message| self.dispatch(message)).collect()
+    }
+
+    /// Mark the connection closed, failing every request still waiting
+    pub(crate) fn close(&self) {
+        if !self.closed.swap(true, Ordering::SeqCst) {
+            // Dropping the senders fails the requests
+            self.pending.lock().unwrap().clear();
+            let _ = self.events.send(ClientEvent::Closed);
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    fn dispatch(&self, message: Value) -> Option<Value> {
+        let id = message.get("id").cloned();
+        let method = message.get("method").and_then(Value::as_str);
+
+        match (id, method) {
+            // A request from the server
+            (Some(id), Some(method)) => {
+                let response = match method {
+                    "ping" => JsonRpcResponse::success(id, json!({})),
+                    _ => JsonRpcResponse::failure(
+                        id,
+                        JsonRpcError::new(JsonRpcError::METHOD_NOT_FOUND, "Method not found"),
+                    ),
+                };
+                Some(serde_json::to_value(response).expect("responses serialize"))
+            }
+            (None, Some("notifications/tools/list_changed")) => {
+                let _ = self.events.send(ClientEvent::ToolsChanged);
+                None
+            }
+            (Some(id), None) => {
+                let tx = id.as_u64().and_then(|id| self.pending.lock().unwrap().remove(&id))?;
+                let outcome = match message.get("error") {
+                    Some(error) => Err(serde_json::from_value(error.clone()).unwrap_or_else(|_| {
+                        JsonRpcError::new(JsonRpcError::INTERNAL_ERROR, error.to_string())
+                    })),
+                    None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+                };
+                let _ = tx.send(outcome);
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A connection to one MCP server
+pub struct McpClient {
+    transport: Box<dyn Transport>,
+    inbox: Arc<Inbox>,
+    next_id: AtomicU64,
+    timeout: Duration,
+    server: Mutex<Option<InitializeResult>>,
+}
+
+impl McpClient {
+    fn new(transport: Box<dyn Transport>, inbox: Arc<Inbox>) -> Self {
+        Self {
+            transport,
+            inbox,
+            next_id: AtomicU64::new(1),
+            timeout: Duration::from_secs(60),
+            server: Mutex::new(None),
+        }
+    }
+
+    /// Connect as `config` says: launch the server, or reach it at its URL.
+    /// Call `initialize` before anything else.
+    pub async fn start(
+        config: &ServerConfig,
+        working_dir: &Path,
+        events: mpsc::UnboundedSender<ClientEvent>,
+    ) -> Result<Self, McpError> {
+        let client = match config.transport_kind()? {
+            TransportKind::Stdio => Self::spawn(config, working_dir, events)?,
+            TransportKind::Http => {
+                let url = config.url.as_deref().unwrap_or_default();
+                Self::connect_http(url, &config.resolved_headers()?, events)?
+            }
+            TransportKind::Sse => {
+                let url = config.url.as_deref().unwrap_or_default();
+                Self::connect_sse(url, &config.resolved_headers()?, events).await?
+            }
+        };
+        Ok(client.with_timeout(config.timeout()))
+    }
+
+    /// Talk to a server reading from `reader` and writing to `writer`.
+    /// Call `initialize` before anything else.
+    pub fn connect<R, W>(reader: R, writer: W, events: mpsc::UnboundedSender<ClientEvent>) -> Self
+    where
+        R: AsyncBufRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let inbox = Inbox::new(events);
+        let writer: Writer = Arc::new(tokio::sync::Mutex::new(Box::new(writer)));
+        let reader = tokio::spawn(read_loop(reader, writer.clone(), inbox.clone()));
+        let transport = StdioTransport {
+            writer,
+            reader,
+            _child: None,
+        };
+        Self::new(Box::new(transport), inbox)
+    }
+
+    /// Launch a server process in `working_dir` and talk to it over its
+    /// stdin and stdout. Its stderr is discarded.
+    pub fn spawn(
+        config: &ServerConfig,
+        working_dir: &Path,
+        events: mpsc::UnboundedSender<ClientEvent>,
+    ) -> Result<Self, McpError> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .envs(&config.env)
+            .current_dir(working_dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|source| McpError::Spawn {
+                command: config.command.clone(),
+                source,
+            })?;
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let inbox = Inbox::new(events);
+        let writer: Writer = Arc::new(tokio::sync::Mutex::new(Box::new(stdin)));
+        let reader = tokio::spawn(read_loop(BufReader::new(stdout), writer.clone(), inbox.clone()));
+        let transport = StdioTransport {
+            writer,
+            reader,
+            _child: Some(child),
+        };
+        Ok(Self::new(Box::new(transport), inbox).with_timeout(config.timeout()))
+    }
+
+    /// Talk to a server over Streamable HTTP at `url`, sending `headers`
+    /// with every request
+    pub fn connect_http(
+        url: &str,
+        headers: &[(String, String)],
+        events: mpsc::UnboundedSender<ClientEvent>,
+    ) -> Result<Self, McpError> {
+        let inbox = Inbox::new(events);
+        let transport = StreamableHttp::new(url, headers, inbox.clone())?;
+        Ok(Self::new(Box::new(transport), inbox))
+    }
+
+    /// Talk to a server over the legacy HTTP+SSE transport: open the event
+    /// stream at `url` and wait for the endpoint to POST messages to
+    pub async fn connect_sse(
+        url: &str,
+        headers: &[(String, String)],
+        events: mpsc::UnboundedSender<ClientEvent>,
+    ) -> Result<Self, McpError> {
+        let inbox = Inbox::new(events);
+        let transport = LegacySse::connect(url, headers, inbox.clone()).await?;
+        Ok(Self::new(Box::new(transport), inbox))
+    }
+
+    /// How long to wait for each response
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Whether the connection has closed; every request then fails
+    pub fn is_closed(&self) -> bool {
+        self.inbox.is_closed()
+    }
+
+    /// What the server reported in `initialize`
+    pub fn server_info(&self) -> Option<InitializeResult> {
+        self.server.lock().unwrap().clone()
+    }
+
+    /// Perform the MCP handshake: `initialize`, then `notifications/initialized`
+    pub async fn initialize(&self) -> Result<InitializeResult, McpError> {
+        let params = InitializeParams {
+            protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
+            capabilities: json!({}),
+            client_info: Implementation {
+                name: "matw".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+        };
+        let result = self
+            .request("initialize", Some(serde_json::to_value(params).expect("initialize params serialize")))
+            .await?;
+        let result: InitializeResult =
+            serde_json::from_value(result).map_err(|e| McpError::InvalidResponse(e.to_string()))?;
+
+        if !SUPPORTED_PROTOCOL_VERSIONS.contains(&result.protocol_version.as_str()) {
+            return Err(McpError::UnsupportedVersion(result.protocol_version));
+        }
+
+        self.transport.set_protocol_version(&result.protocol_version);
+        self.notify("notifications/initialized", None).await?;
+        *self.server.lock().unwrap() = Some(result.clone());
+        Ok(result)
+    }
+
+    /// List the server's tools, following `nextCursor` through every page
+    pub async fn list_tools(&self) -> Result<Vec<Tool>, McpError> {
+        self.list_all("tools/list", "tools").await
+    }
+
+    /// Call a tool. A failure inside the tool is a result with `is_error`
+    /// set, not an `Err`. Cancelling sends `notifications/cancelled`.
+    pub async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Value,
+        cancel: &CancellationToken,
+    ) -> Result<ToolResult, McpError> {
+        let params = json!({ "name": name, "arguments": arguments });
+        let result = self.send_request("tools/call", Some(params), cancel).await?;
+
+        let content = match result.get("content") {
+            Some(Value::Array(items)) => items.iter().map(content_item).collect(),
+            _ => return Err(McpError::InvalidResponse("tools/call result has no content".to_string())),
+        };
+        let is_error = ["isError", "is_error"]
+            .iter()
+            .find_map(|key| result.get(key).and_then(Value::as_bool))
+            .unwrap_or(false);
+        Ok(ToolResult { content, is_error })
+    }
+
+    /// List the server's resources
+    pub async fn list_resources(&self) -> Result<Vec<Resource>, McpError> {
+        self.list_all("resources/list", "resources").await
+    }
+
+    /// List the templates of resources the server does not list
+    pub async fn list_resource_templates(&self) -> Result<Vec<ResourceTemplate>, McpError> {
+        self.list_all("resources/templates/list", "resourceTemplates").await
+    }
+
+    pub async fn read_resource(&self, uri: &str) -> Result<Vec<ResourceContents>, McpError> {
+        let result: ReadResourceResult = parse(self.request("resources/read", Some(json!({ "uri": uri }))).await?)?;
+        Ok(result.contents)
+    }
+
+    /// List the server's prompts
+    pub async fn list_prompts(&self) -> Result<Vec<Prompt>, McpError> {
+        self.list_all("prompts/list", "prompts").await
+    }
+
+    /// Fill in a prompt with `arguments`
+    pub async fn get_prompt(
+        &self,
+        name: &str,
+        arguments: &HashMap<String, String>,
+    ) -> Result<GetPromptResult, McpError> {
+        let params = json!({ "name": name, "arguments": arguments });
+        parse(self.request("prompts/get", Some(params)).await?)
+    }
+
+    /// The items of every page of a list method, following `nextCursor`
+    async fn list_all<T: DeserializeOwned>(&self, method: &str, key: &str) -> Result<Vec<T>, McpError> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = cursor.map(|cursor| json!({ "cursor": cursor }));
+            let mut page = self.request(method, params).await?;
+            let page_items = page
+                .get_mut(key)
+                .map(Value::take)
+                .ok_or_else(|| McpError::InvalidResponse(format!("{} result has no {}", method, key)))?;
+            items.extend(parse::<Vec<T>>(page_items)?);
+
+            match page.get("nextCursor").and_then(Value::as_str) {
+                Some(next) => cursor = Some(next.to_string()),
+                None => return Ok(items),
+            }
+        }
+    }
+
+    /// Send a request and wait for its result
+    pub async fn request(&self, method: &str, params: Option<Value>) -> Result<Value, McpError> {
+        self.send_request(method, params, &CancellationToken::new()).await
+    }
+
+    /// Send a notification, which gets no response
+    pub async fn notify(&self, method: &str, params: Option<Value>) -> Result<(), McpError> {
+        let notification = JsonRpcNotification {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params,
+        };
+        self.transport.send(serde_json::to_value(notification).expect("notifications serialize")).await
+    }
+
+    async fn send_request(
+        &self,
+        method: &str,
+        params: Option<Value>,
+        cancel: &CancellationToken,
+    ) -> Result<Value, McpError> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        self.inbox.pending.lock().unwrap().insert(id, tx);
+        // The inbox is marked closed before what is pending is dropped, so
+        // a request added after that is caught here
+        if self.is_closed() {
+            self.inbox.pending.lock().unwrap().remove(&id);
+            return Err(McpError::Closed);
+        }
+
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: json!(id),
+            method: method.to_string(),
+            params,
+        };
+        let request = serde_json::to_value(request).expect("requests serialize");
+        if let Err(e) = self.transport.send(request).await {
+            self.inbox.pending.lock().unwrap().remove(&id);
+            return Err(e);
+        }
+
+        let abandoned = tokio::select! {
+            response = rx => {
+                return match response {
+                    Ok(Ok(result)) => Ok(result),
+                    Ok(Err(error)) => Err(McpError::Rpc(error)),
+                    Err(_) => Err(McpError::Closed),
+                };
+            }
+            _ = tokio::time::sleep(self.timeout) => McpError::Timeout {
+                method: method.to_string(),
+                secs: self.timeout.as_secs(),
+            },
+            _ = cancel.cancelled() => McpError::Cancelled,
+        };
+
+        self.inbox.pending.lock().unwrap().remove(&id);
+        let params = json!({ "requestId": id, "reason": abandoned.to_string() });
+        let _ = self.notify("notifications/cancelled", Some(params)).await;
+        Err(abandoned)
+    }
+}
+
+/// Decode a result, reporting a mismatch as an invalid response
+fn parse<T: DeserializeOwned>(result: Value) -> Result<T, McpError> {
+    serde_json::from_value(result).map_err(|e| McpError::InvalidResponse(e.to_string()))
+}
+
+/// A content item the protocol types do not cover is passed on as its JSON
+fn content_item(item: &Value) -> ContentItem {
+    serde_json::from_value(item.clone()).unwrap_or_else(|_| ContentItem::Text { text: item.to_string() })
+}
+
+type Writer = Arc<tokio::sync::Mutex<Box<dyn AsyncWrite + Send + Unpin>>>;
+
+/// Newline-delimited JSON-RPC over a pair of streams
+struct StdioTransport {
+    writer: Writer,
+    reader: JoinHandle<()>,
+    /// The server process, killed when the transport is dropped
+    _child: Option<Child>,
+}
+
+#[async_trait]
+impl Transport for StdioTransport {
+    async fn send(&self, message: Value) -> Result<(), McpError> {
+        write_message(&self.writer, &message).await
+    }
+}
+
+impl Drop for StdioTransport {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+async fn write_message(writer: &Writer, message: &Value) -> Result<(), McpError> {
+    let mut encoded = serde_json::to_vec(message).map_err(std::io::Error::other)?;
+    encoded.push(b'\n');
+
+    let mut writer = writer.lock().await;
+    writer.write_all(&encoded).await.map_err(closed_on_broken_pipe)?;
+    writer.flush().await.map_err(closed_on_broken_pipe)?;
+    Ok(())
+}
+
+fn closed_on_broken_pipe(e: std::io::Error) -> McpError {
+    if e.kind() == std::io::ErrorKind::BrokenPipe {
+        McpError::Closed
+    } else {
+        McpError::IO(e)
+    }
+}
+
+async fn read_loop<R>(reader: R, writer: Writer, inbox: Arc<Inbox>)
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut lines = reader.lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let Ok(message) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        for reply in inbox.receive(message) {
+            let _ = write_message(&writer, &reply).await;
+        }
+    }
+    inbox.close();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{register_tools, stdio, MCPServer};
+    use matw_tools::tools::GlobTool;
+
+    /// A client connected to an in-process server, which runs until the
+    /// client is dropped
+    fn connect_in_process(server: MCPServer) -> (McpClient, mpsc::UnboundedReceiver<ClientEvent>) {
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(async move {
+            let (read, write) = tokio::io::split(server_io);
+            stdio::serve(&server, BufReader::new(read), write).await
+        });
+
+        let (read, write) = tokio::io::split(client_io);
+        let (tx, rx) = mpsc::unbounded_channel();
+        (McpClient::connect(BufReader::new(read), write, tx), rx)
+    }
+
+    #[tokio::test]
+    async fn test_handshake_and_tool_call() {
+        let server = MCPServer::new().with_info("in-process", "1.0");
+        register_tools(&server, vec![Arc::new(GlobTool::new())]).await;
+        let (client, _events) = connect_in_process(server);
+
+        let info = client.initialize().await.unwrap();
+        assert_eq!(info.server_info.name, "in-process");
+        assert_eq!(info.protocol_version, LATEST_PROTOCOL_VERSION);
+
+        let tools = client.list_tools().await.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "glob");
+
+        let result = client
+            .call_tool("glob", json!({"pattern": "*.toml"}), &CancellationToken::new())
+            .await
+            .unwrap();
+        assert!(!result.is_error);
+
+        let error = client.request("nope", None).await.unwrap_err();
+        assert!(matches!(error, McpError::Rpc(JsonRpcError { code: -32601, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_reads_resources() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("notes.md"), "# Notes").unwrap();
+        let server = MCPServer::new();
+        server.register_resources(Box::new(crate::ProjectFiles::new(dir.path()))).await;
+        let (client, _events) = connect_in_process(server);
+
+        let info = client.initialize().await.unwrap();
+        assert!(info.capabilities.resources.is_some());
+        assert!(info.capabilities.prompts.is_none());
+
+        let resources = client.list_resources().await.unwrap();
+        assert_eq!(resources.len(), 1);
+        assert_eq!(resources[0].name, "notes.md");
+        assert_eq!(resources[0].mime_type.as_deref(), Some("text/markdown"));
+
+        let contents = client.read_resource(&resources[0].uri).await.unwrap();
+        assert_eq!(contents[0].text.as_deref(), Some("# Notes"));
+        assert!(client.list_resource_templates().await.unwrap().is_empty());
+
+        let error = client.read_resource("file:///etc/passwd").await.unwrap_err();
+        assert!(matches!(error, McpError::Rpc(JsonRpcError { code: JsonRpcError::RESOURCE_NOT_FOUND, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_answers_ping_and_reports_notifications() {
+        let (client_io, mut server_io) = tokio::io::duplex(4096);
+        let (read, write) = tokio::io::split(client_io);
+        let (tx, mut events) = mpsc::unbounded_channel();
+        let client = McpClient::connect(BufReader::new(read), write, tx);
+
+        server_io
+            .write_all(b"not json\n{\"jsonrpc\":\"2.0\",\"id\":\"s1\",\"method\":\"ping\"}\n{\"jsonrpc\":\"2.0\",\"method\":\"notifications/tools/list_changed\"}\n")
+            .await
+            .unwrap();
+
+        let mut lines = BufReader::new(&mut server_io).lines();
+        let pong: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(pong, json!({"jsonrpc": "2.0", "id": "s1", "result": {}}));
+        assert_eq!(events.recv().await, Some(ClientEvent::ToolsChanged));
+
+        drop(lines);
+        drop(server_io);
+        assert_eq!(events.recv().await, Some(ClientEvent::Closed));
+        assert!(matches!(client.request("ping", None).await, Err(McpError::Closed)));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_sends_cancellation() {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let (read, write) = tokio::io::split(client_io);
+        let (tx, _events) = mpsc::unbounded_channel();
+        let client = McpClient::connect(BufReader::new(read), write, tx).with_timeout(Duration::from_millis(50));
+
+        let error = client.request("tools/list", None).await.unwrap_err();
+        assert!(matches!(error, McpError::Timeout { .. }));
+
+        let mut lines = BufReader::new(server_io).lines();
+        let request: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        let cancelled: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(cancelled["method"], "notifications/cancelled");
+        assert_eq!(cancelled["params"]["requestId"], request["id"]);
+    }
+}
@@ -0,0 +1,313 @@
+//! MCP server config
+//!
+//! Servers matw connects to are listed in `~/.matw/mcp.toml` and
+//! `<project>/.matw/mcp.toml`, one `[servers.<name>]` table each:
+//!
+//! ```toml
+//! [servers.github]
+//! command = "npx"
+//! args = ["-y", "@modelcontextprotocol/server-github"]
+//! env = { GITHUB_TOKEN = "..." }
+//! ```
+//!
+//! Remote servers are given a `url` instead, and spoken to over the MCP
+//! Streamable HTTP transport, or the legacy HTTP+SSE transport with
+//! `transport = "sse"`. Header values may refer to environment variables as
+//! `${NAME}`, to keep tokens out of the file:
+//!
+//! ```toml
+//! [servers.docs]
+//! url = "https://mcp.example.com/mcp"
+//! headers = { Authorization = "Bearer ${DOCS_TOKEN}" }
+//! ```
+//!
+//! A project server replaces a user server of the same name. Since the
+//! project file comes with the repository, its servers are only started once
+//! the user has approved it with `matw trust`.
+
+use super::error::McpError;
+use matw_core::TrustStore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// Name of the MCP config file in `~/.matw/` and `<project>/.matw/`
+pub const MCP_FILE: &str = "mcp.toml";
+
+/// How a client talks to a server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportKind {
+    /// Newline-delimited JSON-RPC over a child process's stdin and stdout
+    Stdio,
+    /// Streamable HTTP: JSON-RPC POSTed to one endpoint
+    Http,
+    /// The legacy HTTP+SSE transport of protocol version 2024-11-05
+    Sse,
+}
+
+/// A server launched as a child process, or reached at a URL
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerConfig {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// Added to the environment matw runs in
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Sent with every HTTP request, e.g. `Authorization`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// Chosen from `command` or `url` if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transport: Option<TransportKind>,
+    /// Seconds to wait for each response before giving up
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    60
+}
+
+impl ServerConfig {
+    /// A server launched with `command`
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            args: Vec::new(),
+            env: BTreeMap::new(),
+            url: None,
+            headers: BTreeMap::new(),
+            transport: None,
+            timeout_secs: default_timeout_secs(),
+        }
+    }
+
+    /// A server reached over Streamable HTTP at `url`
+    pub fn http(url: impl Into<String>) -> Self {
+        Self {
+            url: Some(url.into()),
+            ..Self::new("")
+        }
+    }
+
+    pub fn with_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn with_transport(mut self, transport: TransportKind) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    pub fn with_timeout_secs(mut self, secs: u64) -> Self {
+        self.timeout_secs = secs;
+        self
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    /// The transport to use, checking that what it needs is set
+    pub fn transport_kind(&self) -> Result<TransportKind, McpError> {
+        let kind = match (self.transport, &self.url) {
+            (Some(kind), _) => kind,
+            (None, Some(_)) => TransportKind::Http,
+            (None, None) => TransportKind::Stdio,
+        };
+        match kind {
+            TransportKind::Stdio if self.command.is_empty() => {
+                Err(McpError::Config("a stdio server needs a `command`".to_string()))
+            }
+            TransportKind::Http | TransportKind::Sse if self.url.is_none() => {
+                Err(McpError::Config("an HTTP server needs a `url`".to_string()))
+            }
+            kind => Ok(kind),
+        }
+    }
+
+    /// Header values with `${NAME}` replaced by environment variables
+    pub fn resolved_headers(&self) -> Result<Vec<(String, String)>, McpError> {
+        self.headers
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), expand_env(value)?)))
+            .collect()
+    }
+}
+
+fn expand_env(value: &str) -> Result<String, McpError> {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        let var = std::env::var(name)
+            .map_err(|_| McpError::Config(format!("environment variable {} is not set", name)))?;
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&var);
+        rest = &rest[start + 3 + len..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Servers as stored in a config file, by name
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct McpConfig {
+    #[serde(default)]
+    pub servers: BTreeMap<String, ServerConfig>,
+}
+
+impl McpConfig {
+    /// Load servers from a TOML file, or return an empty config if it does not exist
+    pub fn load(path: &Path) -> Result<Self, McpError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| McpError::Config(format!("{}: {}", path.display(), e)))?;
+        Self::parse(&content, path)
+    }
+
+    /// Parse servers read from `path`
+    pub fn parse(content: &str, path: &Path) -> Result<Self, McpError> {
+        toml::from_str(content).map_err(|e| McpError::Config(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Load servers from `~/.matw/mcp.toml`, and from
+    /// `<project>/.matw/mcp.toml` if `trust` approves it as it is
+    pub fn load_all(project_dir: &Path, trust: &TrustStore) -> Result<Self, McpError> {
+        let mut config = Self::default();
+        if let Some(home) = dirs::home_dir() {
+            config.merge(Self::load(&home.join(".matw").join(MCP_FILE))?);
+        }
+        let project = project_dir.join(".matw").join(MCP_FILE);
+        if let Some(content) = trust.read_approved(&project) {
+            config.merge(Self::parse(&content, &project)?);
+        }
+        Ok(config)
+    }
+
+    /// Add the servers of `other`, replacing those with the same name
+    pub fn merge(&mut self, other: McpConfig) {
+        self.servers.extend(other.servers);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_servers_need_approval() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join(".matw").join(MCP_FILE);
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(&file, "[servers.pwn]\ncommand = \"sh\"\nargs = [\"-c\", \"touch pwned\"]\n").unwrap();
+
+        let mut trust = TrustStore::default();
+        let config = McpConfig::load_all(temp.path(), &trust).unwrap();
+        assert!(!config.servers.contains_key("pwn"));
+
+        trust.approve(&file, std::fs::read_to_string(&file).unwrap());
+        let config = McpConfig::load_all(temp.path(), &trust).unwrap();
+        assert_eq!(config.servers["pwn"].command, "sh");
+    }
+
+    #[test]
+    fn test_parse_servers() {
+        let config: McpConfig = toml::from_str(
+            r#"
+            [servers.github]
+            command = "npx"
+            args = ["-y", "server-github"]
+            env = { GITHUB_TOKEN = "secret" }
+
+            [servers.docs]
+            command = "docs-server"
+            timeout_secs = 5
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.servers["github"],
+            ServerConfig::new("npx")
+                .with_args(["-y", "server-github"])
+                .with_env("GITHUB_TOKEN", "secret")
+        );
+        assert_eq!(config.servers["docs"].timeout(), Duration::from_secs(5));
+        assert_eq!(config.servers["docs"].transport_kind().unwrap(), TransportKind::Stdio);
+    }
+
+    #[test]
+    fn test_remote_server() {
+        let config: McpConfig = toml::from_str(
+            r#"
+            [servers.docs]
+            url = "http://localhost:3000/sse"
+            transport = "sse"
+            headers = { Authorization = "Bearer ${MATW_TEST_MCP_TOKEN}", X-Team = "core" }
+
+            [servers.broken]
+            transport = "http"
+            "#,
+        )
+        .unwrap();
+
+        let docs = &config.servers["docs"];
+        assert_eq!(docs.transport_kind().unwrap(), TransportKind::Sse);
+        assert!(docs.resolved_headers().is_err());
+        std::env::set_var("MATW_TEST_MCP_TOKEN", "t0ken");
+        assert_eq!(
+            docs.resolved_headers().unwrap(),
+            vec![
+                ("Authorization".to_string(), "Bearer t0ken".to_string()),
+                ("X-Team".to_string(), "core".to_string()),
+            ]
+        );
+
+        assert_eq!(ServerConfig::http("http://x").transport_kind().unwrap(), TransportKind::Http);
+        assert!(config.servers["broken"].transport_kind().is_err());
+    }
+
+    #[test]
+    fn test_project_replaces_user_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(MCP_FILE);
+        std::fs::write(&path, "[servers.docs]\ncommand = \"project-docs\"\n").unwrap();
+
+        let mut config = McpConfig::default();
+        config.servers.insert("docs".to_string(), ServerConfig::new("user-docs"));
+        config.servers.insert("github".to_string(), ServerConfig::new("gh"));
+        config.merge(McpConfig::load(&path).unwrap());
+
+        assert_eq!(config.servers["docs"].command, "project-docs");
+        assert_eq!(config.servers["github"].command, "gh");
+        assert!(McpConfig::load(&dir.path().join("missing.toml")).unwrap().servers.is_empty());
+    }
+}
@@ -0,0 +1,42 @@
+use super::protocol::JsonRpcError;
+use thiserror::Error;
+
+/// Errors talking to an MCP server
+#[derive(Debug, Error)]
+pub enum McpError {
+    #[error("Failed to start {command}: {source}")]
+    Spawn {
+        command: String,
+        source: std::io::Error,
+    },
+
+    #[error("IO error: {0}")]
+    IO(#[from] std::io::Error),
+
+    #[error("HTTP request failed: {0}")]
+    Http(String),
+
+    #[error("HTTP {status}: {body}")]
+    Status { status: u16, body: String },
+
+    #[error("Server closed the connection")]
+    Closed,
+
+    #[error("{method} timed out after {secs} s")]
+    Timeout { method: String, secs: u64 },
+
+    #[error("Cancelled")]
+    Cancelled,
+
+    #[error("Server returned error {}: {}", .0.code, .0.message)]
+    Rpc(JsonRpcError),
+
+    #[error("Unsupported protocol version: {0}")]
+    UnsupportedVersion(String),
+
+    #[error("Invalid response: {0}")]
+    InvalidResponse(String),
+
+    #[error("Config error: {0}")]
+    Config(String),
+}
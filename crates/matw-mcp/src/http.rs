@@ -0,0 +1,405 @@
+//! HTTP transports
+//!
+//! Serves an `MCPServer` over Streamable HTTP at `/mcp`, and over the legacy
+//! HTTP+SSE transport at `/sse` (the event stream) and `/messages` (where
+//! its messages are POSTed). Each client gets its own session: a server
+//! sharing the tools but with its own handshake.
+//!
+//! On `/mcp` the session starts with `initialize`, whose response carries
+//! the session id in `Mcp-Session-Id`; later requests without it are
+//! refused with 400, and those with an unknown one with 404, telling the
+//! client to start over. Sessions left idle are forgotten, and new ones are
+//! refused with 503 while too many are open. Requests from browsers on
+//! other origins are refused, and a bearer token may be required of every
+//! request.
+
+use super::http_client::{PROTOCOL_VERSION_HEADER, SESSION_HEADER};
+use super::protocol::{JsonRpcError, JsonRpcResponse, SUPPORTED_PROTOCOL_VERSIONS};
+use super::server::MCPServer;
+use axum::body::Bytes;
+use axum::extract::{Query, Request, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures::stream::{self, StreamExt};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// How long a session on `/mcp` may go unused before it is forgotten
+const DEFAULT_SESSION_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Sessions that may be open at once, on either transport
+const DEFAULT_MAX_SESSIONS: usize = 64;
+
+/// An `MCPServer` served over HTTP
+pub struct HttpServer {
+    server: MCPServer,
+    sessions: Mutex<HashMap<String, StreamableSession>>,
+    legacy_sessions: Mutex<HashMap<String, LegacySession>>,
+    bearer_token: Option<String>,
+    sse_responses: bool,
+    session_timeout: Duration,
+    max_sessions: usize,
+    /// Sessions being opened, counted against `max_sessions` until they are
+    pending_sessions: AtomicUsize,
+}
+
+/// A client of Streamable HTTP. It has no connection that would tell us it
+/// went away, so it is forgotten once idle for the session timeout.
+struct StreamableSession {
+    server: Arc<MCPServer>,
+    last_used: Instant,
+}
+
+/// A client of the legacy transport, whose responses go out on its event stream
+struct LegacySession {
+    server: Arc<MCPServer>,
+    outgoing: mpsc::UnboundedSender<Value>,
+}
+
+impl HttpServer {
+    pub fn new(server: MCPServer) -> Self {
+        Self {
+            server,
+            sessions: Mutex::new(HashMap::new()),
+            legacy_sessions: Mutex::new(HashMap::new()),
+            bearer_token: None,
+            sse_responses: false,
+            session_timeout: DEFAULT_SESSION_TIMEOUT,
+            max_sessions: DEFAULT_MAX_SESSIONS,
+            pending_sessions: AtomicUsize::new(0),
+        }
+    }
+
+    /// Refuse requests without `Authorization: Bearer <token>`
+    pub fn with_bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.bearer_token = Some(token.into());
+        self
+    }
+
+    /// Answer requests on `/mcp` with an event stream instead of a JSON body
+    pub fn with_sse_responses(mut self, enabled: bool) -> Self {
+        self.sse_responses = enabled;
+        self
+    }
+
+    /// Forget sessions on `/mcp` that go unused for `timeout` (30 minutes by default)
+    pub fn with_session_timeout(mut self, timeout: Duration) -> Self {
+        self.session_timeout = timeout;
+        self
+    }
+
+    /// Refuse new sessions while `max` are open (64 by default)
+    pub fn with_max_sessions(mut self, max: usize) -> Self {
+        self.max_sessions = max;
+        self
+    }
+
+    /// Number of open sessions on either transport
+    pub fn session_count(&self) -> usize {
+        self.sessions.lock().unwrap().len() + self.legacy_sessions.lock().unwrap().len()
+    }
+
+    /// The session on `/mcp` with this id, unless it has been idle too long
+    fn session(&self, id: &str) -> Option<Arc<MCPServer>> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get_mut(id)?;
+        if session.last_used.elapsed() >= self.session_timeout {
+            sessions.remove(id);
+            return None;
+        }
+        session.last_used = Instant::now();
+        Some(session.server.clone())
+    }
+
+    /// Forget idle sessions, then claim room for another one if there is
+    /// any. Checking and claiming under one lock keeps concurrent
+    /// handshakes from all squeezing into the last place.
+    fn reserve_session(&self) -> Option<SessionSlot<'_>> {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, session| session.last_used.elapsed() < self.session_timeout);
+        let open = sessions.len() + self.legacy_sessions.lock().unwrap().len();
+        if open + self.pending_sessions.load(Ordering::SeqCst) >= self.max_sessions {
+            return None;
+        }
+        self.pending_sessions.fetch_add(1, Ordering::SeqCst);
+        Some(SessionSlot { state: self })
+    }
+
+    /// Routes for both transports, sharing this server's sessions
+    pub fn router(self: Arc<Self>) -> Router {
+        Router::new()
+            .route("/mcp", post(post_message).get(open_stream).delete(end_session))
+            .route("/sse", get(open_legacy_stream))
+            .route("/messages", post(post_legacy_message))
+            .layer(middleware::from_fn_with_state(self.clone(), check_request))
+            .with_state(self)
+    }
+}
+
+/// Room claimed for a session being opened, given back when dropped: by then
+/// the session is in its map, or its handshake failed
+struct SessionSlot<'a> {
+    state: &'a HttpServer,
+}
+
+impl Drop for SessionSlot<'_> {
+    fn drop(&mut self) {
+        self.state.pending_sessions.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Serve on `listener` until the task is dropped
+pub async fn serve_http(server: Arc<HttpServer>, listener: TcpListener) -> std::io::Result<()> {
+    axum::serve(listener, server.router()).await
+}
+
+/// A JSON-RPC error in an HTTP error response
+fn error_response(status: StatusCode, code: i32, message: &str) -> Response {
+    let body = JsonRpcResponse::failure(Value::Null, JsonRpcError::new(code, message));
+    (status, Json(body)).into_response()
+}
+
+/// Refuse requests from browsers on other origins, which could otherwise
+/// reach a local server, and requests without the bearer token
+async fn check_request(State(state): State<Arc<HttpServer>>, request: Request, next: Next) -> Response {
+    let headers = request.headers();
+    if let Some(origin) = headers.get(header::ORIGIN) {
+        if !is_local_origin(origin.to_str().unwrap_or_default()) {
+            return error_response(StatusCode::FORBIDDEN, JsonRpcError::INVALID_REQUEST, "Origin not allowed");
+        }
+    }
+
+    if let Some(token) = &state.bearer_token {
+        let expected = format!("Bearer {}", token);
+        let authorized = headers
+            .get(header::AUTHORIZATION)
+            .is_some_and(|value| constant_time_eq(value.as_bytes(), expected.as_bytes()));
+        if !authorized {
+            let mut response = error_response(StatusCode::UNAUTHORIZED, JsonRpcError::INVALID_REQUEST, "Unauthorized");
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            return response;
+        }
+    }
+
+    next.run(request).await
+}
+
+/// Compare without returning early, so the time taken does not tell how much
+/// of a guessed token was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn is_local_origin(origin: &str) -> bool {
+    let host = origin.split("://").nth(1).unwrap_or(origin);
+    let host = host.split('/').next().unwrap_or_default();
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+fn too_many_sessions() -> Response {
+    error_response(StatusCode::SERVICE_UNAVAILABLE, JsonRpcError::INVALID_REQUEST, "Too many sessions")
+}
+
+fn is_initialize(message: &Value) -> bool {
+    message.get("method").and_then(Value::as_str) == Some("initialize") && message.get("id").is_some()
+}
+
+async fn post_message(State(state): State<Arc<HttpServer>>, headers: HeaderMap, body: Bytes) -> Response {
+    if let Some(version) = headers.get(PROTOCOL_VERSION_HEADER) {
+        let version = version.to_str().unwrap_or_default();
+        if !SUPPORTED_PROTOCOL_VERSIONS.contains(&version) {
+            let message = format!("Unsupported protocol version: {}", version);
+            return error_response(StatusCode::BAD_REQUEST, JsonRpcError::INVALID_REQUEST, &message);
+        }
+    }
+
+    let message: Value = match serde_json::from_slice(&body) {
+        Ok(message) => message,
+        Err(e) => {
+            let message = format!("Parse error: {}", e);
+            return error_response(StatusCode::BAD_REQUEST, JsonRpcError::PARSE_ERROR, &message);
+        }
+    };
+
+    let new_session = is_initialize(&message);
+    let (session_id, server, slot) = if new_session {
+        let Some(slot) = state.reserve_session() else {
+            return too_many_sessions();
+        };
+        (Uuid::new_v4().to_string(), Arc::new(state.server.new_session()), Some(slot))
+    } else {
+        let Some(id) = headers.get(SESSION_HEADER).and_then(|v| v.to_str().ok()) else {
+            let message = "Missing Mcp-Session-Id header";
+            return error_response(StatusCode::BAD_REQUEST, JsonRpcError::INVALID_REQUEST, message);
+        };
+        let Some(server) = state.session(id) else {
+            return error_response(StatusCode::NOT_FOUND, JsonRpcError::INVALID_REQUEST, "Session not found");
+        };
+        (id.to_string(), server, None)
+    };
+
+    let answer = server.handle_message(message).await;
+
+    // Only a successful handshake opens a session
+    let opened = new_session && server.protocol_version().is_some();
+    if opened {
+        let session = StreamableSession {
+            server: server.clone(),
+            last_used: Instant::now(),
+        };
+        state.sessions.lock().unwrap().insert(session_id.clone(), session);
+    }
+    drop(slot);
+    if server.is_shut_down() {
+        state.sessions.lock().unwrap().remove(&session_id);
+    }
+
+    let mut response = match answer {
+        None => StatusCode::ACCEPTED.into_response(),
+        Some(answer) if state.sse_responses => {
+            let event = Event::default().event("message").data(answer.to_string());
+            Sse::new(stream::once(async move { Ok::<_, Infallible>(event) })).into_response()
+        }
+        Some(answer) => Json(answer).into_response(),
+    };
+    if opened {
+        let id = HeaderValue::from_str(&session_id).expect("uuids are valid header values");
+        response.headers_mut().insert(SESSION_HEADER, id);
+    }
+    response
+}
+
+/// The server sends nothing on its own, so it offers no stream
+async fn open_stream() -> Response {
+    let mut response = StatusCode::METHOD_NOT_ALLOWED.into_response();
+    response
+        .headers_mut()
+        .insert(header::ALLOW, HeaderValue::from_static("POST, DELETE"));
+    response
+}
+
+async fn end_session(State(state): State<Arc<HttpServer>>, headers: HeaderMap) -> Response {
+    let Some(id) = headers.get(SESSION_HEADER).and_then(|v| v.to_str().ok()) else {
+        return error_response(StatusCode::BAD_REQUEST, JsonRpcError::INVALID_REQUEST, "Missing Mcp-Session-Id header");
+    };
+    match state.sessions.lock().unwrap().remove(id) {
+        Some(_) => StatusCode::OK.into_response(),
+        None => error_response(StatusCode::NOT_FOUND, JsonRpcError::INVALID_REQUEST, "Session not found"),
+    }
+}
+
+/// Removes a legacy session when its event stream is dropped, i.e. when
+/// the client disconnects
+struct LegacyGuard {
+    state: Arc<HttpServer>,
+    id: String,
+}
+
+impl Drop for LegacyGuard {
+    fn drop(&mut self) {
+        self.state.legacy_sessions.lock().unwrap().remove(&self.id);
+    }
+}
+
+async fn open_legacy_stream(State(state): State<Arc<HttpServer>>) -> Response {
+    let Some(slot) = state.reserve_session() else {
+        return too_many_sessions();
+    };
+    let id = Uuid::new_v4().to_string();
+    let (outgoing, incoming) = mpsc::unbounded_channel::<Value>();
+    let session = LegacySession {
+        server: Arc::new(state.server.new_session()),
+        outgoing,
+    };
+    state.legacy_sessions.lock().unwrap().insert(id.clone(), session);
+    drop(slot);
+
+    let endpoint = Event::default().event("endpoint").data(format!("/messages?sessionId={}", id));
+    let guard = LegacyGuard { state, id };
+    let messages = stream::unfold((incoming, guard), |(mut incoming, guard)| async move {
+        let message = incoming.recv().await?;
+        let event = Event::default().event("message").data(message.to_string());
+        Some((event, (incoming, guard)))
+    });
+
+    let events = stream::once(async { endpoint }).chain(messages).map(Ok::<_, Infallible>);
+    Sse::new(events).keep_alive(KeepAlive::default()).into_response()
+}
+
+#[derive(Deserialize)]
+struct LegacyQuery {
+    #[serde(rename = "sessionId")]
+    session_id: String,
+}
+
+async fn post_legacy_message(
+    State(state): State<Arc<HttpServer>>,
+    Query(query): Query<LegacyQuery>,
+    body: Bytes,
+) -> Response {
+    let session = state
+        .legacy_sessions
+        .lock()
+        .unwrap()
+        .get(&query.session_id)
+        .map(|s| (s.server.clone(), s.outgoing.clone()));
+    let Some((server, outgoing)) = session else {
+        return error_response(StatusCode::NOT_FOUND, JsonRpcError::INVALID_REQUEST, "Session not found");
+    };
+
+    let answer = match serde_json::from_slice(&body) {
+        Ok(message) => server.handle_message(message).await,
+        Err(e) => {
+            let message = format!("Parse error: {}", e);
+            return error_response(StatusCode::BAD_REQUEST, JsonRpcError::PARSE_ERROR, &message);
+        }
+    };
+    if let Some(answer) = answer {
+        let _ = outgoing.send(answer);
+    }
+    if server.is_shut_down() {
+        // Ends the event stream once the response has gone out
+        state.legacy_sessions.lock().unwrap().remove(&query.session_id);
+    }
+    StatusCode::ACCEPTED.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_origins() {
+        assert!(is_local_origin("http://localhost:3000"));
+        assert!(is_local_origin("http://127.0.0.1"));
+        assert!(is_local_origin("http://[::1]:8080"));
+        assert!(!is_local_origin("https://evil.example"));
+        assert!(!is_local_origin("http://localhost.evil.example"));
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"Bearer s3cret", b"Bearer s3cret"));
+        assert!(!constant_time_eq(b"Bearer s3creT", b"Bearer s3cret"));
+        assert!(!constant_time_eq(b"Bearer s3cre", b"Bearer s3cret"));
+        assert!(!constant_time_eq(b"", b"Bearer s3cret"));
+    }
+}
@@ -0,0 +1,348 @@
+//! HTTP client transports
+//!
+//! `StreamableHttp` POSTs each message to the server's MCP endpoint. The
+//! answer is a JSON body, an event stream carrying the response, or
+//! `202 Accepted` for notifications. The session id the server hands out in
+//! `Mcp-Session-Id` goes with every later request, and the session is ended
+//! with a DELETE when the transport is dropped. Once the handshake is done a
+//! GET stream is opened for messages the server sends on its own, if the
+//! server offers one.
+//!
+//! `LegacySse` is the HTTP+SSE transport of protocol version 2024-11-05: a
+//! GET event stream whose first `endpoint` event names the URL to POST
+//! messages to, with every response arriving on the stream.
+
+use super::client::{Inbox, Transport};
+use super::error::McpError;
+use super::sse::SseParser;
+use async_trait::async_trait;
+use futures::StreamExt;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE};
+use reqwest::{Response, StatusCode, Url};
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+/// Header carrying the session id of Streamable HTTP
+pub const SESSION_HEADER: &str = "mcp-session-id";
+
+/// Header carrying the protocol version agreed in `initialize`
+pub const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+
+/// How long a legacy server may take to name its message endpoint
+const ENDPOINT_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn header_map(headers: &[(String, String)]) -> Result<HeaderMap, McpError> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        let invalid = |e: &dyn std::fmt::Display| McpError::Config(format!("header {}: {}", name, e));
+        let header = HeaderName::from_bytes(name.as_bytes()).map_err(|e| invalid(&e))?;
+        let value = HeaderValue::from_str(value).map_err(|e| invalid(&e))?;
+        map.insert(header, value);
+    }
+    Ok(map)
+}
+
+fn parse_url(url: &str) -> Result<Url, McpError> {
+    Url::parse(url).map_err(|e| McpError::Config(format!("{}: {}", url, e)))
+}
+
+/// Fail on anything but a success status, keeping the body for the error
+async fn check_status(response: Response) -> Result<Response, McpError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(McpError::Status {
+        status: status.as_u16(),
+        body: body.trim().to_string(),
+    })
+}
+
+fn is_event_stream(response: &Response) -> bool {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"))
+}
+
+/// The server's MCP endpoint and the session with it
+struct Endpoint {
+    http: reqwest::Client,
+    url: Url,
+    headers: HeaderMap,
+    session_id: Mutex<Option<String>>,
+    protocol_version: Mutex<Option<String>>,
+}
+
+impl Endpoint {
+    fn request(&self, method: reqwest::Method) -> reqwest::RequestBuilder {
+        let mut request = self.http.request(method, self.url.clone()).headers(self.headers.clone());
+        if let Some(id) = self.session_id.lock().unwrap().as_deref() {
+            request = request.header(SESSION_HEADER, id);
+        }
+        if let Some(version) = self.protocol_version.lock().unwrap().as_deref() {
+            request = request.header(PROTOCOL_VERSION_HEADER, version);
+        }
+        request
+    }
+
+    async fn post(&self, message: &Value, inbox: &Inbox) -> Result<Response, McpError> {
+        let had_session = self.session_id.lock().unwrap().is_some();
+        let response = self
+            .request(reqwest::Method::POST)
+            .header(ACCEPT, "application/json, text/event-stream")
+            .json(message)
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_connect() {
+                    inbox.close();
+                }
+                McpError::Http(e.to_string())
+            })?;
+
+        if had_session && response.status() == StatusCode::NOT_FOUND {
+            // The server ended the session, so the client must start over
+            inbox.close();
+            return Err(McpError::Closed);
+        }
+        let response = check_status(response).await?;
+
+        if let Some(id) = response.headers().get(SESSION_HEADER).and_then(|v| v.to_str().ok()) {
+            *self.session_id.lock().unwrap() = Some(id.to_string());
+        }
+        Ok(response)
+    }
+}
+
+/// Deliver the messages of an event stream until it ends, posting replies
+async fn read_stream(response: Response, endpoint: Arc<Endpoint>, inbox: Arc<Inbox>) {
+    let mut parser = SseParser::new();
+    let mut body = response.bytes_stream();
+    while let Some(Ok(chunk)) = body.next().await {
+        for event in parser.push(&chunk).into_iter().filter(|e| e.event == "message") {
+            let Ok(message) = serde_json::from_str(&event.data) else {
+                continue;
+            };
+            for reply in inbox.receive(message) {
+                let _ = endpoint.post(&reply, &inbox).await;
+            }
+        }
+    }
+}
+
+/// The Streamable HTTP transport
+pub(crate) struct StreamableHttp {
+    endpoint: Arc<Endpoint>,
+    inbox: Arc<Inbox>,
+    /// Tasks reading event streams from the server
+    streams: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl StreamableHttp {
+    pub(crate) fn new(url: &str, headers: &[(String, String)], inbox: Arc<Inbox>) -> Result<Self, McpError> {
+        let endpoint = Endpoint {
+            http: reqwest::Client::new(),
+            url: parse_url(url)?,
+            headers: header_map(headers)?,
+            session_id: Mutex::new(None),
+            protocol_version: Mutex::new(None),
+        };
+        Ok(Self {
+            endpoint: Arc::new(endpoint),
+            inbox,
+            streams: Mutex::new(Vec::new()),
+        })
+    }
+
+    fn read_in_background(&self, response: Response) {
+        let task = tokio::spawn(read_stream(response, self.endpoint.clone(), self.inbox.clone()));
+        let mut streams = self.streams.lock().unwrap();
+        streams.retain(|s| !s.is_finished());
+        streams.push(task);
+    }
+
+    /// Open the stream for messages the server sends on its own. Servers
+    /// without one answer 405.
+    async fn listen(&self) {
+        let response = self
+            .endpoint
+            .request(reqwest::Method::GET)
+            .header(ACCEPT, "text/event-stream")
+            .send()
+            .await;
+        if let Ok(response) = response {
+            if response.status().is_success() && is_event_stream(&response) {
+                self.read_in_background(response);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for StreamableHttp {
+    async fn send(&self, message: Value) -> Result<(), McpError> {
+        let response = self.endpoint.post(&message, &self.inbox).await?;
+
+        if is_event_stream(&response) {
+            self.read_in_background(response);
+        } else if response.status() != StatusCode::ACCEPTED {
+            let body = response.bytes().await.map_err(|e| McpError::Http(e.to_string()))?;
+            if let Ok(answer) = serde_json::from_slice::<Value>(&body) {
+                for reply in self.inbox.receive(answer) {
+                    // Replies get 202 Accepted, so nothing comes back from these
+                    let endpoint = self.endpoint.clone();
+                    let inbox = self.inbox.clone();
+                    tokio::spawn(async move { endpoint.post(&reply, &inbox).await });
+                }
+            }
+        }
+
+        if message.get("method").and_then(Value::as_str) == Some("notifications/initialized") {
+            self.listen().await;
+        }
+        Ok(())
+    }
+
+    fn set_protocol_version(&self, version: &str) {
+        *self.endpoint.protocol_version.lock().unwrap() = Some(version.to_string());
+    }
+}
+
+impl Drop for StreamableHttp {
+    fn drop(&mut self) {
+        for stream in self.streams.lock().unwrap().iter() {
+            stream.abort();
+        }
+
+        // End the session on the server, if a runtime is left to do it
+        let has_session = self.endpoint.session_id.lock().unwrap().is_some();
+        if let (true, Ok(runtime)) = (has_session, tokio::runtime::Handle::try_current()) {
+            let request = self.endpoint.request(reqwest::Method::DELETE);
+            runtime.spawn(async move {
+                let _ = request.send().await;
+            });
+        }
+    }
+}
+
+/// The legacy HTTP+SSE transport
+pub(crate) struct LegacySse {
+    http: reqwest::Client,
+    post_url: Url,
+    headers: HeaderMap,
+    reader: JoinHandle<()>,
+}
+
+impl LegacySse {
+    /// Open the event stream and wait for the server to name the URL to POST to
+    pub(crate) async fn connect(
+        url: &str,
+        headers: &[(String, String)],
+        inbox: Arc<Inbox>,
+    ) -> Result<Self, McpError> {
+        let url = parse_url(url)?;
+        let headers = header_map(headers)?;
+        let http = reqwest::Client::new();
+
+        let response = http
+            .get(url.clone())
+            .headers(headers.clone())
+            .header(ACCEPT, "text/event-stream")
+            .send()
+            .await
+            .map_err(|e| McpError::Http(e.to_string()))?;
+        let response = check_status(response).await?;
+
+        let (tx, rx) = oneshot::channel();
+        let replies = (http.clone(), headers.clone());
+        let reader = tokio::spawn(read_legacy_stream(response, url, replies, inbox, tx));
+
+        let post_url = match tokio::time::timeout(ENDPOINT_TIMEOUT, rx).await {
+            Ok(Ok(post_url)) => post_url,
+            Ok(Err(_)) => return Err(McpError::Closed),
+            Err(_) => {
+                reader.abort();
+                return Err(McpError::Timeout {
+                    method: "endpoint event".to_string(),
+                    secs: ENDPOINT_TIMEOUT.as_secs(),
+                });
+            }
+        };
+
+        Ok(Self {
+            http,
+            post_url,
+            headers,
+            reader,
+        })
+    }
+}
+
+async fn read_legacy_stream(
+    response: Response,
+    base: Url,
+    (http, headers): (reqwest::Client, HeaderMap),
+    inbox: Arc<Inbox>,
+    endpoint_tx: oneshot::Sender<Url>,
+) {
+    let mut endpoint_tx = Some(endpoint_tx);
+    let mut post_url: Option<Url> = None;
+    let mut parser = SseParser::new();
+    let mut body = response.bytes_stream();
+
+    while let Some(Ok(chunk)) = body.next().await {
+        for event in parser.push(&chunk) {
+            match event.event.as_str() {
+                "endpoint" => {
+                    let Ok(url) = base.join(event.data.trim()) else {
+                        continue;
+                    };
+                    post_url = Some(url.clone());
+                    if let Some(tx) = endpoint_tx.take() {
+                        let _ = tx.send(url);
+                    }
+                }
+                "message" => {
+                    let Ok(message) = serde_json::from_str(&event.data) else {
+                        continue;
+                    };
+                    for reply in inbox.receive(message) {
+                        if let Some(url) = &post_url {
+                            let _ = http.post(url.clone()).headers(headers.clone()).json(&reply).send().await;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    inbox.close();
+}
+
+#[async_trait]
+impl Transport for LegacySse {
+    async fn send(&self, message: Value) -> Result<(), McpError> {
+        let response = self
+            .http
+            .post(self.post_url.clone())
+            .headers(self.headers.clone())
+            .json(&message)
+            .send()
+            .await
+            .map_err(|e| McpError::Http(e.to_string()))?;
+        check_status(response).await?;
+        Ok(())
+    }
+}
+
+impl Drop for LegacySse {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
@@ -1,10 +1,28 @@
 //! MATW MCP - Model Context Protocol plugin system
 //!
-//! Provides MCP (Model Context Protocol) implementation for MATW.
+//! Provides MCP (Model Context Protocol) implementation for MATW: a server
+//! exposing matw's tools, project files and session transcripts, and a
+//! client that offers other servers' tools, resources and prompts.
 
 pub mod bridge;
+pub mod client;
+pub mod config;
+pub mod error;
+pub mod http;
+pub mod http_client;
+pub mod manager;
 pub mod protocol;
+pub mod resources;
 pub mod server;
+pub mod sse;
+pub mod stdio;
 
-pub use bridge::{register_tools, ToolAdapter};
-pub use server::{MCPServer, MCTool};
+pub use bridge::{register_tools, register_tools_with_context, ToolAdapter};
+pub use client::{ClientEvent, McpClient};
+pub use config::{McpConfig, ServerConfig, TransportKind, MCP_FILE};
+pub use error::McpError;
+pub use http::{serve_http, HttpServer};
+pub use manager::{tool_name, Backoff, McpManager, RemoteTool};
+pub use resources::{ProjectFiles, SessionTranscripts};
+pub use server::{MCPServer, MCPrompt, MCTool, ResourceProvider};
+pub use stdio::serve_stdio;
@@ -0,0 +1,537 @@
+//! MCP servers as matw tools
+//!
+//! `McpManager` connects to the servers in `McpConfig` and registers each of
+//! their tools in a `ToolRegistry` as `mcp__<server>__<tool>`, under
+//! `ToolSource::Mcp`. A supervisor task per server re-lists the tools when
+//! the server sends `notifications/tools/list_changed`, and restarts the
+//! server with exponential backoff when it exits. While a server is down
+//! its tools stay registered and fail with an error saying so; once it
+//! runs out of restarts they are removed.
+//!
+//! The manager also gathers the resources and prompts of the running
+//! servers that offer them, for the user to mention and run.
+
+use super::client::{ClientEvent, McpClient};
+use super::config::{McpConfig, ServerConfig};
+use super::error::McpError;
+use super::protocol::{
+    ContentItem, GetPromptResult, Prompt, Resource, ResourceContents, ServerCapabilities, Tool as RemoteToolInfo,
+};
+use async_trait::async_trait;
+use matw_tools::{Tool, ToolContext, ToolError, ToolOutput, ToolRegistry, ToolSource};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// When to restart a server that exited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    /// Delay before the first restart, doubled for each one after
+    pub initial: Duration,
+    pub max: Duration,
+    /// Restarts in a row before the server is given up on
+    pub max_restarts: u32,
+    /// A server that ran this long before exiting starts over with no restarts counted
+    pub stable_after: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+            max_restarts: 5,
+            stable_after: Duration::from_secs(60),
+        }
+    }
+}
+
+impl Backoff {
+    /// Delay before restart number `restarts` (counting from zero)
+    pub fn delay(&self, restarts: u32) -> Duration {
+        self.initial
+            .checked_mul(2u32.saturating_pow(restarts))
+            .map_or(self.max, |delay| delay.min(self.max))
+    }
+}
+
+/// Longest tool name model APIs accept
+pub const MAX_TOOL_NAME_LEN: usize = 64;
+
+/// Longest server name, leaving room in tool names for the tool's own name
+pub const MAX_SERVER_NAME_LEN: usize = 32;
+
+/// Check that a server name can be part of tool names: letters, digits,
+/// `_` and `-`, without the `__` that separates it from the tool's name
+pub fn validate_server_name(name: &str) -> Result<(), McpError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_SERVER_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        && !name.contains("__");
+    if valid {
+        Ok(())
+    } else {
+        Err(McpError::Config(format!(
+            "invalid MCP server name `{}`: use 1 to {} letters, digits, `_` or `-`, without `__`",
+            name, MAX_SERVER_NAME_LEN
+        )))
+    }
+}
+
+/// Name under which a server's tool is registered. A name that would be
+/// longer than [`MAX_TOOL_NAME_LEN`] is cut short and ends in a hash of the
+/// tool's full name, so it stays unique.
+pub fn tool_name(server: &str, tool: &str) -> String {
+    let name = format!("mcp__{}__{}", sanitize(server), sanitize(tool));
+    if name.len() <= MAX_TOOL_NAME_LEN {
+        return name;
+    }
+    let hash = format!("_{:08x}", fnv1a(tool) as u32);
+    // Sanitized names are ASCII, so any byte offset is a char boundary
+    format!("{}{}", &name[..MAX_TOOL_NAME_LEN - hash.len()], hash)
+}
+
+/// 64-bit FNV-1a, a hash that stays the same across builds
+fn fnv1a(text: &str) -> u64 {
+    text.bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// Model APIs only accept letters, digits, `_` and `-` in tool names
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect()
+}
+
+/// One configured server and its current connection, if any
+struct ServerConnection {
+    name: String,
+    config: ServerConfig,
+    working_dir: PathBuf,
+    client: RwLock<Option<Arc<McpClient>>>,
+}
+
+impl ServerConnection {
+    /// The connection, unless the server is down
+    fn client(&self) -> Option<Arc<McpClient>> {
+        self.client.read().unwrap().clone().filter(|c| !c.is_closed())
+    }
+
+    /// Launch or reach the server, perform the handshake and list its
+    /// tools. Events from this connection arrive on the returned receiver.
+    async fn connect(&self) -> Result<(Vec<RemoteToolInfo>, mpsc::UnboundedReceiver<ClientEvent>), McpError> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let client = McpClient::start(&self.config, &self.working_dir, tx).await?;
+        client.initialize().await?;
+        let tools = client.list_tools().await?;
+        *self.client.write().unwrap() = Some(Arc::new(client));
+        Ok((tools, rx))
+    }
+
+    /// Drop the connection, killing a launched server or ending the HTTP session
+    fn disconnect(&self) {
+        self.client.write().unwrap().take();
+    }
+
+    fn source(&self) -> ToolSource {
+        ToolSource::Mcp(self.name.clone())
+    }
+}
+
+/// A tool offered by an MCP server
+pub struct RemoteTool {
+    server: Arc<ServerConnection>,
+    name: String,
+    remote_name: String,
+    description: String,
+    schema: Value,
+}
+
+impl RemoteTool {
+    fn new(server: Arc<ServerConnection>, info: RemoteToolInfo) -> Self {
+        let description = if info.description.is_empty() {
+            format!("{} from MCP server {}", info.name, server.name)
+        } else {
+            info.description
+        };
+        Self {
+            name: tool_name(&server.name, &info.name),
+            remote_name: info.name,
+            description,
+            schema: info.input_schema,
+            server,
+        }
+    }
+
+    /// The tool's name on its server
+    pub fn remote_name(&self) -> &str {
+        &self.remote_name
+    }
+
+    pub fn server_name(&self) -> &str {
+        &self.server.name
+    }
+}
+
+#[async_trait]
+impl Tool for RemoteTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn parameters_schema(&self) -> Value {
+        self.schema.clone()
+    }
+
+    async fn execute(&self, input: Value, ctx: &ToolContext) -> Result<ToolOutput, ToolError> {
+        let client = self.server.client().ok_or_else(|| {
+            ToolError::ExecutionFailed(format!("MCP server {} is not running; it is being restarted", self.server.name))
+        })?;
+
+        let result = client
+            .call_tool(&self.remote_name, input, ctx.cancellation())
+            .await
+            .map_err(|e| match e {
+                McpError::Cancelled => ToolError::Cancelled,
+                e => ToolError::ExecutionFailed(format!("MCP server {}: {}", self.server.name, e)),
+            })?;
+
+        let content: Vec<String> = result.content.iter().map(render).collect();
+        Ok(ToolOutput {
+            content: content.join("\n"),
+            is_error: result.is_error,
+            usage: None,
+        })
+    }
+}
+
+/// Tool output is text for the model; other content is described
+fn render(item: &ContentItem) -> String {
+    match item {
+        ContentItem::Text { text } => text.clone(),
+        ContentItem::Image { mime_type, data } => format!("[{} image, {} bytes base64]", mime_type, data.len()),
+        ContentItem::Resource { resource } => match &resource.text {
+            Some(text) => text.clone(),
+            None => format!("[resource {}]", resource.uri),
+        },
+    }
+}
+
+/// Replace the tools registered for a server, returning how many were registered.
+/// A tool whose name is taken by another source is skipped.
+fn register(registry: &ToolRegistry, server: &Arc<ServerConnection>, tools: Vec<RemoteToolInfo>) -> usize {
+    registry.unregister_source(&server.source());
+    tools
+        .into_iter()
+        .filter(|info| {
+            let tool = Arc::new(RemoteTool::new(server.clone(), info.clone()));
+            registry.register(tool, server.source()).is_ok()
+        })
+        .count()
+}
+
+/// Running MCP servers whose tools are in a registry. Dropping the manager
+/// stops the servers and removes their tools.
+pub struct McpManager {
+    registry: Arc<ToolRegistry>,
+    working_dir: PathBuf,
+    backoff: Backoff,
+    servers: Vec<Arc<ServerConnection>>,
+    supervisors: Vec<JoinHandle<()>>,
+}
+
+impl McpManager {
+    /// Manage servers launched in `working_dir`, registering their tools in `registry`
+    pub fn new(registry: Arc<ToolRegistry>, working_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            registry,
+            working_dir: working_dir.into(),
+            backoff: Backoff::default(),
+            servers: Vec::new(),
+            supervisors: Vec::new(),
+        }
+    }
+
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Start a server and register its tools, returning how many were
+    /// registered. A server that fails to start is not retried.
+    pub async fn start(&mut self, name: &str, config: ServerConfig) -> Result<usize, McpError> {
+        validate_server_name(name)?;
+        let server = Arc::new(ServerConnection {
+            name: name.to_string(),
+            config,
+            working_dir: self.working_dir.clone(),
+            client: RwLock::new(None),
+        });
+        let (tools, events) = server.connect().await?;
+        let registered = register(&self.registry, &server, tools);
+
+        let supervisor = supervise(server.clone(), events, self.registry.clone(), self.backoff);
+        self.supervisors.push(tokio::spawn(supervisor));
+        self.servers.push(server);
+        Ok(registered)
+    }
+
+    /// Start every server in `config`, returning those that failed
+    pub async fn start_all(&mut self, config: &McpConfig) -> Vec<(String, McpError)> {
+        let mut failures = Vec::new();
+        for (name, server) in &config.servers {
+            if let Err(e) = self.start(name, server.clone()).await {
+                failures.push((name.clone(), e));
+            }
+        }
+        failures
+    }
+
+    /// Names of the servers started, running or not
+    pub fn server_names(&self) -> Vec<&str> {
+        self.servers.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    /// Whether the named server is connected
+    pub fn is_running(&self, name: &str) -> bool {
+        self.servers.iter().any(|s| s.name == name && s.client().is_some())
+    }
+
+    fn client(&self, server: &str) -> Result<Arc<McpClient>, McpError> {
+        let connection = self
+            .servers
+            .iter()
+            .find(|s| s.name == server)
+            .ok_or_else(|| McpError::Config(format!("no MCP server named {}", server)))?;
+        connection.client().ok_or(McpError::Closed)
+    }
+
+    /// Running servers whose capabilities pass `offers`
+    fn clients_offering(&self, offers: impl Fn(&ServerCapabilities) -> bool) -> Vec<(&str, Arc<McpClient>)> {
+        self.servers
+            .iter()
+            .filter_map(|s| Some((s.name.as_str(), s.client()?)))
+            .filter(|(_, client)| client.server_info().is_some_and(|info| offers(&info.capabilities)))
+            .collect()
+    }
+
+    /// Resources of every running server that offers them, with the
+    /// server's name. Servers that fail to list them are left out.
+    pub async fn resources(&self) -> Vec<(String, Resource)> {
+        let mut resources = Vec::new();
+        for (server, client) in self.clients_offering(|c| c.resources.is_some()) {
+            if let Ok(listed) = client.list_resources().await {
+                resources.extend(listed.into_iter().map(|r| (server.to_string(), r)));
+            }
+        }
+        resources
+    }
+
+    pub async fn read_resource(&self, server: &str, uri: &str) -> Result<Vec<ResourceContents>, McpError> {
+        self.client(server)?.read_resource(uri).await
+    }
+
+    /// Prompts of every running server that offers them, with the
+    /// server's name. Servers that fail to list them are left out.
+    pub async fn prompts(&self) -> Vec<(String, Prompt)> {
+        let mut prompts = Vec::new();
+        for (server, client) in self.clients_offering(|c| c.prompts.is_some()) {
+            if let Ok(listed) = client.list_prompts().await {
+                prompts.extend(listed.into_iter().map(|p| (server.to_string(), p)));
+            }
+        }
+        prompts
+    }
+
+    pub async fn get_prompt(
+        &self,
+        server: &str,
+        prompt: &str,
+        arguments: &HashMap<String, String>,
+    ) -> Result<GetPromptResult, McpError> {
+        self.client(server)?.get_prompt(prompt, arguments).await
+    }
+}
+
+impl Drop for McpManager {
+    fn drop(&mut self) {
+        for supervisor in &self.supervisors {
+            supervisor.abort();
+        }
+        for server in &self.servers {
+            self.registry.unregister_source(&server.source());
+            server.disconnect();
+        }
+    }
+}
+
+/// Follow one server's events until it is given up on
+async fn supervise(
+    server: Arc<ServerConnection>,
+    mut events: mpsc::UnboundedReceiver<ClientEvent>,
+    registry: Arc<ToolRegistry>,
+    backoff: Backoff,
+) {
+    let mut started = Instant::now();
+    let mut restarts = 0;
+
+    while let Some(event) = events.recv().await {
+        match event {
+            ClientEvent::ToolsChanged => {
+                let Some(client) = server.client() else {
+                    continue;
+                };
+                if let Ok(tools) = client.list_tools().await {
+                    register(&registry, &server, tools);
+                }
+            }
+            ClientEvent::Closed => {
+                server.disconnect();
+                if started.elapsed() >= backoff.stable_after {
+                    restarts = 0;
+                }
+
+                // Each connection has its own channel, so events from one
+                // that failed during a restart are never seen here
+                events = loop {
+                    if restarts >= backoff.max_restarts {
+                        registry.unregister_source(&server.source());
+                        return;
+                    }
+                    tokio::time::sleep(backoff.delay(restarts)).await;
+                    restarts += 1;
+
+                    if let Ok((tools, events)) = server.connect().await {
+                        register(&registry, &server, tools);
+                        started = Instant::now();
+                        break events;
+                    }
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A stdio MCP server in shell: one tool, `crash`, which makes it exit
+    const FAKE_SERVER: &str = r#"
+while read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  case "$line" in
+    *'"initialize"'*)
+      echo "{\"jsonrpc\":\"2.0\",\"id\":$id,\"result\":{\"protocolVersion\":\"2025-06-18\",\"capabilities\":{\"tools\":{\"listChanged\":true}},\"serverInfo\":{\"name\":\"fake\",\"version\":\"1\"}}}" ;;
+    *'"tools/list"'*)
+      echo "{\"jsonrpc\":\"2.0\",\"id\":$id,\"result\":{\"tools\":[{\"name\":\"crash\",\"inputSchema\":{\"type\":\"object\"}}]}}" ;;
+    *'"tools/call"'*)
+      exit 1 ;;
+  esac
+done
+"#;
+
+    fn fake_server() -> ServerConfig {
+        ServerConfig::new("sh").with_args(["-c", FAKE_SERVER]).with_timeout_secs(5)
+    }
+
+    async fn wait_for(mut condition: impl FnMut() -> bool) {
+        for _ in 0..200 {
+            if condition() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("condition not reached");
+    }
+
+    #[test]
+    fn test_tool_name_and_backoff() {
+        assert_eq!(tool_name("git hub", "create.issue"), "mcp__git_hub__create_issue");
+
+        let long = "a".repeat(70);
+        let name = tool_name("github", &long);
+        assert_eq!(name.len(), MAX_TOOL_NAME_LEN);
+        assert!(name.starts_with("mcp__github__aaaa"));
+        assert_ne!(name, tool_name("github", &format!("{}b", long)));
+        assert_eq!(name, tool_name("github", &long));
+
+        let backoff = Backoff::default();
+        assert_eq!(backoff.delay(0), Duration::from_secs(1));
+        assert_eq!(backoff.delay(3), Duration::from_secs(8));
+        assert_eq!(backoff.delay(40), Duration::from_secs(60));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_server_names_are_rejected() {
+        let registry = Arc::new(ToolRegistry::new());
+        let mut manager = McpManager::new(registry, std::env::temp_dir());
+        for name in ["", "git hub", "a__b", &"x".repeat(MAX_SERVER_NAME_LEN + 1)] {
+            let err = manager.start(name, ServerConfig::new("true")).await.unwrap_err();
+            assert!(err.to_string().contains("invalid MCP server name"), "{}", err);
+        }
+        assert!(validate_server_name("git-hub_2").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_restarts_crashed_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = Arc::new(ToolRegistry::new());
+        let mut manager = McpManager::new(registry.clone(), dir.path()).with_backoff(Backoff {
+            initial: Duration::from_millis(10),
+            ..Backoff::default()
+        });
+
+        assert_eq!(manager.start("fake", fake_server()).await.unwrap(), 1);
+        let tool = registry.get("mcp__fake__crash").unwrap();
+        assert_eq!(tool.description(), "crash from MCP server fake");
+
+        let error = tool.execute(serde_json::json!({}), &ToolContext::default()).await.unwrap_err();
+        assert!(error.to_string().contains("MCP server fake"));
+
+        wait_for(|| manager.is_running("fake")).await;
+        assert!(registry.get("mcp__fake__crash").is_some());
+
+        drop(manager);
+        assert!(registry.get("mcp__fake__crash").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = Arc::new(ToolRegistry::new());
+        let mut manager = McpManager::new(registry.clone(), dir.path()).with_backoff(Backoff {
+            initial: Duration::from_millis(1),
+            max_restarts: 0,
+            ..Backoff::default()
+        });
+        manager.start("fake", fake_server()).await.unwrap();
+
+        let tool = registry.get("mcp__fake__crash").unwrap();
+        let _ = tool.execute(serde_json::json!({}), &ToolContext::default()).await;
+        wait_for(|| registry.get("mcp__fake__crash").is_none()).await;
+        assert!(!manager.is_running("fake"));
+    }
+
+    #[tokio::test]
+    async fn test_start_failure_is_reported() {
+        let registry = Arc::new(ToolRegistry::new());
+        let mut manager = McpManager::new(registry, std::env::temp_dir());
+        let mut config = McpConfig::default();
+        config.servers.insert("missing".to_string(), ServerConfig::new("/nonexistent/mcp-server"));
+
+        let failures = manager.start_all(&config).await;
+        assert_eq!(failures.len(), 1);
+        assert!(matches!(failures[0].1, McpError::Spawn { .. }));
+    }
+}
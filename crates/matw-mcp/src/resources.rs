@@ -0,0 +1,354 @@
+//! Resources matw serves
+//!
+//! `ProjectFiles` offers the files of the working directory as `file://`
+//! resources, skipping hidden files and what `.gitignore` leaves out, even
+//! outside a git repository. A file can be read only if it would be listed,
+//! so a client cannot reach outside the project or into ignored files such as
+//! `.env`.
+//!
+//! `SessionTranscripts` offers the conversations of saved matw sessions in
+//! the project as Markdown, at `matw://session/<id>`. Only the messages are
+//! rendered, never the session's context.
+
+use super::protocol::{Resource, ResourceContents, ResourceTemplate};
+use super::server::ResourceProvider;
+use async_trait::async_trait;
+use base64::Engine;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::WalkBuilder;
+use matw_core::{Content, Role, Session};
+use reqwest::Url;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Files larger than this are neither listed nor read
+pub const MAX_FILE_BYTES: u64 = 1024 * 1024;
+
+/// URI prefix of session transcripts
+pub const SESSION_URI_PREFIX: &str = "matw://session/";
+
+/// MIME type from a file's extension, for the common cases
+fn mime_type(path: &Path) -> Option<&'static str> {
+    let mime = match path.extension()?.to_str()? {
+        "rs" => "text/x-rust",
+        "md" => "text/markdown",
+        "txt" => "text/plain",
+        "toml" => "application/toml",
+        "json" => "application/json",
+        "yaml" | "yml" => "application/yaml",
+        "html" => "text/html",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "ts" => "text/x-typescript",
+        "py" => "text/x-python",
+        "sh" => "text/x-shellscript",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "pdf" => "application/pdf",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/// The files of a project directory
+pub struct ProjectFiles {
+    root: PathBuf,
+}
+
+impl ProjectFiles {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        Self {
+            root: root.canonicalize().unwrap_or(root),
+        }
+    }
+
+    /// Files to offer, with their sizes, sorted by path
+    async fn files(&self) -> Vec<(PathBuf, u64)> {
+        let root = self.root.clone();
+        let walk = tokio::task::spawn_blocking(move || {
+            let mut files: Vec<(PathBuf, u64)> = WalkBuilder::new(&root)
+                .git_ignore(true)
+                .git_global(true)
+                .require_git(false)
+                .build()
+                .flatten()
+                .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+                .filter_map(|entry| {
+                    let size = entry.metadata().ok()?.len();
+                    (size <= MAX_FILE_BYTES).then(|| (entry.into_path(), size))
+                })
+                .collect();
+            files.sort();
+            files
+        });
+        walk.await.unwrap_or_default()
+    }
+
+    /// Whether the listing leaves out `path`, a canonical path under the root
+    fn is_hidden_or_ignored(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return true;
+        };
+        if relative.components().any(|c| c.as_os_str().to_string_lossy().starts_with('.')) {
+            return true;
+        }
+
+        // The nearest directory with a matching rule decides, as in the walk
+        for dir in path.ancestors().skip(1).take_while(|dir| dir.starts_with(&self.root)) {
+            let mut builder = GitignoreBuilder::new(dir);
+            for name in [".gitignore", ".ignore"] {
+                let file = dir.join(name);
+                if file.is_file() {
+                    builder.add(file);
+                }
+            }
+            let Ok(rules) = builder.build() else {
+                return true;
+            };
+            let matched = rules.matched_path_or_any_parents(path, false);
+            if matched.is_ignore() {
+                return true;
+            }
+            if matched.is_whitelist() {
+                return false;
+            }
+        }
+        Gitignore::global().0.matched_path_or_any_parents(path, false).is_ignore()
+    }
+}
+
+#[async_trait]
+impl ResourceProvider for ProjectFiles {
+    async fn list(&self) -> Vec<Resource> {
+        self.files()
+            .await
+            .into_iter()
+            .filter_map(|(path, size)| {
+                let uri = Url::from_file_path(&path).ok()?;
+                let name = path.strip_prefix(&self.root).unwrap_or(&path).display().to_string();
+                Some(Resource {
+                    uri: uri.to_string(),
+                    name,
+                    description: None,
+                    mime_type: mime_type(&path).map(str::to_string),
+                    size: Some(size),
+                })
+            })
+            .collect()
+    }
+
+    async fn read(&self, uri: &str) -> Option<Result<Vec<ResourceContents>, String>> {
+        let url = Url::parse(uri).ok().filter(|url| url.scheme() == "file")?;
+        let path = url.to_file_path().ok()?.canonicalize().ok()?;
+        if !path.starts_with(&self.root) || self.is_hidden_or_ignored(&path) {
+            return None;
+        }
+        let metadata = tokio::fs::metadata(&path).await.ok()?;
+        if !metadata.is_file() || metadata.len() > MAX_FILE_BYTES {
+            return None;
+        }
+
+        let bytes = match tokio::fs::read(&path).await {
+            Ok(bytes) => bytes,
+            Err(e) => return Some(Err(format!("{}: {}", path.display(), e))),
+        };
+        let mime = mime_type(&path);
+        let contents = match String::from_utf8(bytes) {
+            Ok(text) => ResourceContents {
+                uri: uri.to_string(),
+                mime_type: Some(mime.unwrap_or("text/plain").to_string()),
+                text: Some(text),
+                blob: None,
+            },
+            Err(e) => ResourceContents {
+                uri: uri.to_string(),
+                mime_type: Some(mime.unwrap_or("application/octet-stream").to_string()),
+                text: None,
+                blob: Some(base64::engine::general_purpose::STANDARD.encode(e.into_bytes())),
+            },
+        };
+        Some(Ok(vec![contents]))
+    }
+}
+
+/// The first line of a session's first prompt
+fn title(session: &Session) -> String {
+    session
+        .messages()
+        .iter()
+        .filter(|m| m.role() == Role::User)
+        .find_map(|m| match m.content() {
+            Content::Text(text) => text.lines().find(|line| !line.trim().is_empty()),
+            _ => None,
+        })
+        .unwrap_or("Empty session")
+        .to_string()
+}
+
+/// A session's conversation as Markdown, one section per message
+fn to_markdown(session: &Session) -> String {
+    let working_dir = session.context().working_dir().display();
+    let mut out = format!("# {}\n\nSession {} in {}\n", title(session), session.id(), working_dir);
+    for message in session.messages() {
+        let time = message.timestamp().format("%Y-%m-%d %H:%M:%S UTC");
+        match message.content() {
+            Content::Text(text) => {
+                out.push_str(&format!("\n## {} ({})\n\n{}\n", message.role(), time, text));
+            }
+            Content::ToolUse { name, input, .. } => {
+                out.push_str(&format!("\n## Tool call: {} ({})\n\n```json\n{}\n```\n", name, time, input));
+            }
+            Content::ToolResult { content, is_error, .. } => {
+                let heading = if *is_error { "Tool error" } else { "Tool result" };
+                out.push_str(&format!("\n## {} ({})\n\n```\n{}\n```\n", heading, time, content));
+            }
+        }
+    }
+    out
+}
+
+/// Transcripts of the saved sessions run in one project
+pub struct SessionTranscripts {
+    dir: PathBuf,
+    working_dir: PathBuf,
+}
+
+impl SessionTranscripts {
+    /// Sessions saved in `dir` that ran in `working_dir`
+    pub fn new(dir: impl Into<PathBuf>, working_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            working_dir: working_dir.into(),
+        }
+    }
+
+    fn is_ours(&self, session: &Session) -> bool {
+        session.context().working_dir() == self.working_dir && session.message_count() > 0
+    }
+}
+
+#[async_trait]
+impl ResourceProvider for SessionTranscripts {
+    async fn list(&self) -> Vec<Resource> {
+        let mut sessions: Vec<Session> = Session::load_all(&self.dir)
+            .into_iter()
+            .filter(|s| self.is_ours(s))
+            .collect();
+        // Most recently active first
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.messages().last().map(|m| m.timestamp())));
+
+        sessions
+            .iter()
+            .map(|s| {
+                let last_active = s.messages().last().map(|m| m.timestamp().format("%Y-%m-%d %H:%M UTC"));
+                Resource {
+                    uri: format!("{}{}", SESSION_URI_PREFIX, s.id()),
+                    name: title(s),
+                    description: last_active
+                        .map(|time| format!("matw session with {} messages, last active {}", s.message_count(), time)),
+                    mime_type: Some("text/markdown".to_string()),
+                    size: None,
+                }
+            })
+            .collect()
+    }
+
+    fn templates(&self) -> Vec<ResourceTemplate> {
+        vec![ResourceTemplate {
+            uri_template: format!("{}{{id}}", SESSION_URI_PREFIX),
+            name: "Session transcript".to_string(),
+            description: Some("A saved matw session in this project, by session id".to_string()),
+            mime_type: Some("text/markdown".to_string()),
+        }]
+    }
+
+    async fn read(&self, uri: &str) -> Option<Result<Vec<ResourceContents>, String>> {
+        let id = Uuid::parse_str(uri.strip_prefix(SESSION_URI_PREFIX)?).ok()?;
+        let session = Session::load_from(&self.dir, id).ok().filter(|s| self.is_ours(s))?;
+        Some(Ok(vec![ResourceContents {
+            uri: uri.to_string(),
+            mime_type: Some("text/markdown".to_string()),
+            text: Some(to_markdown(&session)),
+            blob: None,
+        }]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matw_core::Message;
+
+    #[tokio::test]
+    async fn test_project_files_stay_in_project() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().join("project");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join(".gitignore"), "secret.txt\n").unwrap();
+        std::fs::write(root.join("secret.txt"), "hunter2").unwrap();
+        std::fs::write(root.join(".env"), "TOKEN=1").unwrap();
+        std::fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(root.join("logo.png"), [0x89, b'P', b'N', b'G', 0xff]).unwrap();
+        std::fs::write(temp.path().join("outside.txt"), "outside").unwrap();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/.gitignore"), "*.log\n!keep.log\n").unwrap();
+        std::fs::write(root.join("src/debug.log"), "debug").unwrap();
+        std::fs::write(root.join("src/keep.log"), "keep").unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(root.join(".git/config"), "[core]").unwrap();
+        std::fs::write(root.join("big.txt"), vec![b'a'; MAX_FILE_BYTES as usize + 1]).unwrap();
+
+        let files = ProjectFiles::new(&root);
+        let names: Vec<String> = files.list().await.into_iter().map(|r| r.name).collect();
+        assert_eq!(names, vec!["logo.png", "main.rs", "src/keep.log"]);
+
+        let root = root.canonicalize().unwrap();
+        let uri = |path: &Path| Url::from_file_path(path).unwrap().to_string();
+        let main = files.read(&uri(&root.join("main.rs"))).await.unwrap().unwrap();
+        assert_eq!(main[0].text.as_deref(), Some("fn main() {}"));
+        assert_eq!(main[0].mime_type.as_deref(), Some("text/x-rust"));
+
+        let logo = files.read(&uri(&root.join("logo.png"))).await.unwrap().unwrap();
+        assert_eq!(logo[0].blob.as_deref(), Some("iVBOR/8="));
+
+        assert!(files.read(&uri(&root.join("secret.txt"))).await.is_none());
+        assert!(files.read(&uri(&root.join(".env"))).await.is_none());
+        assert!(files.read(&uri(&root.join("../outside.txt"))).await.is_none());
+        assert!(files.read(&uri(&root.join("src/debug.log"))).await.is_none());
+        assert!(files.read(&uri(&root.join("src/keep.log"))).await.unwrap().is_ok());
+        assert!(files.read(&uri(&root.join(".git/config"))).await.is_none());
+        assert!(files.read(&uri(&root.join("big.txt"))).await.is_none());
+        assert!(files.read(&uri(&root.join("src"))).await.is_none());
+        assert!(files.read("matw://session/1").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_transcripts_of_this_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut session = Session::new(PathBuf::from("/project"));
+        session.add_message(Message::new_user("\nAdd a README\nplease".to_string()));
+        session.add_message(Message::new_assistant("Done".to_string()));
+        session.context_mut().set_env("API_KEY".to_string(), "secret".to_string());
+        session.save(dir.path()).unwrap();
+
+        let mut other = Session::new(PathBuf::from("/elsewhere"));
+        other.add_message(Message::new_user("Other project".to_string()));
+        other.save(dir.path()).unwrap();
+
+        let transcripts = SessionTranscripts::new(dir.path(), "/project");
+        let resources = transcripts.list().await;
+        assert_eq!(resources.len(), 1);
+        assert_eq!(resources[0].name, "Add a README");
+        assert_eq!(resources[0].uri, format!("matw://session/{}", session.id()));
+
+        let contents = transcripts.read(&resources[0].uri).await.unwrap().unwrap();
+        let text = contents[0].text.as_deref().unwrap();
+        assert!(text.starts_with("# Add a README"));
+        assert!(text.contains("## assistant"));
+        assert!(!text.contains("secret"));
+        assert!(transcripts.read(&format!("matw://session/{}", other.id())).await.is_none());
+    }
+}
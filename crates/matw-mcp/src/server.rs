@@ -1,12 +1,41 @@
 //! MCP server implementation
+//!
+//! Handles the MCP lifecycle (`initialize`, `notifications/initialized`,
+//! `ping` and `shutdown`) and the tool, resource and prompt methods.
+//! Transports pass each incoming message to `handle_message`, which also
+//! takes JSON-RPC batches and notifications; notifications get no response.
+//!
+//! Resources come from `ResourceProvider`s, asked in the order they were
+//! registered, and prompts from `MCPrompt`s. The `resources` and `prompts`
+//! capabilities are only advertised once something is registered. List
+//! methods return `PAGE_SIZE` items at a time, with `nextCursor` for the rest.
 
 use super::protocol::*;
+use serde_json::{json, Value};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use tokio::sync::RwLock;
 
+/// Items per page of `resources/list`, `resources/templates/list` and `prompts/list`
+pub const PAGE_SIZE: usize = 100;
+
 pub struct MCPServer {
     tools: Arc<RwLock<HashMap<String, Box<dyn MCTool>>>>,
+    resources: Arc<RwLock<Vec<Box<dyn ResourceProvider>>>>,
+    prompts: Arc<RwLock<HashMap<String, Box<dyn MCPrompt>>>>,
+    info: Implementation,
+    instructions: Option<String>,
+    client: Mutex<Option<ClientSession>>,
+    shutdown: AtomicBool,
+}
+
+/// The client that initialized this server
+#[derive(Debug, Clone)]
+struct ClientSession {
+    info: Implementation,
+    protocol_version: &'static str,
+    initialized: bool,
 }
 
 #[async_trait::async_trait]
@@ -14,13 +43,78 @@ pub trait MCTool: Send + Sync {
     fn name(&self) -> &str;
     fn description(&self) -> &str;
     fn input_schema(&self) -> serde_json::Value;
-    async fn execute(&self, args: serde_json::Value) -> Result<Vec<ContentItem>, String>;
+    /// Run the tool. An `Err` is a failure of the tool, not of the
+    /// protocol: it is sent as a result with `isError` set.
+    async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, String>;
+}
+
+/// A source of resources, such as the project's files
+#[async_trait::async_trait]
+pub trait ResourceProvider: Send + Sync {
+    async fn list(&self) -> Vec<Resource>;
+
+    /// Templates for resources that can be read but are not listed
+    fn templates(&self) -> Vec<ResourceTemplate> {
+        Vec::new()
+    }
+
+    /// Read a resource, or return `None` if the URI is not this provider's
+    async fn read(&self, uri: &str) -> Option<Result<Vec<ResourceContents>, String>>;
+}
+
+/// A prompt template the client can fill in and send to its model
+#[async_trait::async_trait]
+pub trait MCPrompt: Send + Sync {
+    fn name(&self) -> &str;
+    fn description(&self) -> &str;
+    fn arguments(&self) -> Vec<PromptArgument>;
+    /// Build the messages; every required argument is present
+    async fn get(&self, args: HashMap<String, String>) -> Result<Vec<PromptMessage>, String>;
 }
 
 impl MCPServer {
     pub fn new() -> Self {
         Self {
             tools: Arc::new(RwLock::new(HashMap::new())),
+            resources: Arc::new(RwLock::new(Vec::new())),
+            prompts: Arc::new(RwLock::new(HashMap::new())),
+            info: Implementation {
+                name: "matw".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+            instructions: None,
+            client: Mutex::new(None),
+            shutdown: AtomicBool::new(false),
+        }
+    }
+
+    /// Name and version reported to clients in `initialize`
+    pub fn with_info(mut self, name: impl Into<String>, version: impl Into<String>) -> Self {
+        self.info = Implementation {
+            name: name.into(),
+            version: version.into(),
+        };
+        self
+    }
+
+    /// Hints for the client's model on how to use this server
+    pub fn with_instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = Some(instructions.into());
+        self
+    }
+
+    /// A server for another client: the same tools, resources, prompts,
+    /// info and instructions, but its own handshake. What is registered on
+    /// either is shared.
+    pub fn new_session(&self) -> Self {
+        Self {
+            tools: self.tools.clone(),
+            resources: self.resources.clone(),
+            prompts: self.prompts.clone(),
+            info: self.info.clone(),
+            instructions: self.instructions.clone(),
+            client: Mutex::new(None),
+            shutdown: AtomicBool::new(false),
         }
     }
 
@@ -29,81 +123,394 @@ impl MCPServer {
         tools.insert(tool.name().to_string(), tool);
     }
 
+    pub async fn register_resources(&self, provider: Box<dyn ResourceProvider>) {
+        self.resources.write().await.push(provider);
+    }
+
+    pub async fn register_prompt(&self, prompt: Box<dyn MCPrompt>) {
+        let mut prompts = self.prompts.write().await;
+        prompts.insert(prompt.name().to_string(), prompt);
+    }
+
+    /// The client that sent `initialize`, if any
+    pub fn client_info(&self) -> Option<Implementation> {
+        self.client.lock().unwrap().as_ref().map(|c| c.info.clone())
+    }
+
+    /// The protocol version agreed in `initialize`
+    pub fn protocol_version(&self) -> Option<&'static str> {
+        self.client.lock().unwrap().as_ref().map(|c| c.protocol_version)
+    }
+
+    /// Whether the client has sent `notifications/initialized`
+    pub fn is_initialized(&self) -> bool {
+        self.client.lock().unwrap().as_ref().is_some_and(|c| c.initialized)
+    }
+
+    /// Whether the client asked the server to shut down; transports stop
+    /// reading once it has
+    pub fn is_shut_down(&self) -> bool {
+        self.shutdown.load(Ordering::SeqCst)
+    }
+
+    /// Handle one incoming JSON-RPC message: a request, a notification or a
+    /// batch of them. Returns the response to send, if any.
+    pub async fn handle_message(&self, message: Value) -> Option<Value> {
+        match message {
+            Value::Array(batch) if batch.is_empty() => Some(invalid_request(Value::Null, "empty batch")),
+            Value::Array(batch) => {
+                let mut responses = Vec::new();
+                for message in batch {
+                    if let Some(response) = self.handle_single(message).await {
+                        responses.push(response);
+                    }
+                }
+                (!responses.is_empty()).then_some(Value::Array(responses))
+            }
+            message => self.handle_single(message).await,
+        }
+    }
+
+    async fn handle_single(&self, message: Value) -> Option<Value> {
+        let Some(id) = message.get("id").cloned() else {
+            // Notifications get no response, not even an error
+            if let Ok(notification) = serde_json::from_value::<JsonRpcNotification>(message) {
+                self.handle_notification(notification);
+            }
+            return None;
+        };
+
+        let response = match serde_json::from_value::<JsonRpcRequest>(message) {
+            Ok(request) => self.handle_request(request).await,
+            Err(e) => return Some(invalid_request(id, &e.to_string())),
+        };
+        Some(serde_json::to_value(response).expect("responses serialize"))
+    }
+
+    pub fn handle_notification(&self, notification: JsonRpcNotification) {
+        if notification.method == "notifications/initialized" {
+            if let Some(client) = self.client.lock().unwrap().as_mut() {
+                client.initialized = true;
+            }
+        }
+        // Others, such as notifications/cancelled, need nothing from us
+    }
+
     pub async fn handle_request(&self, request: JsonRpcRequest) -> JsonRpcResponse {
         let result = match request.method.as_str() {
+            "initialize" => self.initialize(request.params).await,
+            "ping" => Ok(json!({})),
+            "shutdown" => {
+                self.shutdown.store(true, Ordering::SeqCst);
+                Ok(json!({}))
+            }
             "tools/list" => self.list_tools().await,
             "tools/call" => self.call_tool(request.params).await,
-            _ => Err(JsonRpcError {
-                code: -32601,
-                message: "Method not found".to_string(),
-                data: None,
-            }),
+            "resources/list" => self.list_resources(request.params).await,
+            "resources/templates/list" => self.list_resource_templates(request.params).await,
+            "resources/read" => self.read_resource(request.params).await,
+            "prompts/list" => self.list_prompts(request.params).await,
+            "prompts/get" => self.get_prompt(request.params).await,
+            _ => Err(JsonRpcError::new(JsonRpcError::METHOD_NOT_FOUND, "Method not found")),
         };
 
         match result {
-            Ok(result) => JsonRpcResponse {
-                jsonrpc: "2.0".to_string(),
-                id: request.id,
-                result: Some(result),
-                error: None,
-            },
-            Err(error) => JsonRpcResponse {
-                jsonrpc: "2.0".to_string(),
-                id: request.id,
-                result: None,
-                error: Some(error),
-            },
+            Ok(result) => JsonRpcResponse::success(request.id, result),
+            Err(error) => JsonRpcResponse::failure(request.id, error),
         }
     }
 
+    async fn initialize(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let params: InitializeParams = params
+            .and_then(|params| serde_json::from_value(params).ok())
+            .ok_or_else(|| JsonRpcError::new(JsonRpcError::INVALID_PARAMS, "Invalid initialize params"))?;
+
+        let protocol_version = negotiate_version(&params.protocol_version);
+        *self.client.lock().unwrap() = Some(ClientSession {
+            info: params.client_info,
+            protocol_version,
+            initialized: false,
+        });
+
+        let result = InitializeResult {
+            protocol_version: protocol_version.to_string(),
+            capabilities: ServerCapabilities {
+                tools: Some(ListChanged { list_changed: false }),
+                resources: (!self.resources.read().await.is_empty()).then_some(ListChanged { list_changed: false }),
+                prompts: (!self.prompts.read().await.is_empty()).then_some(ListChanged { list_changed: false }),
+            },
+            server_info: self.info.clone(),
+            instructions: self.instructions.clone(),
+        };
+        Ok(serde_json::to_value(result).expect("initialize result serializes"))
+    }
+
     async fn list_tools(&self) -> Result<serde_json::Value, JsonRpcError> {
         let tools = self.tools.read().await;
-        let tool_list: Vec<Tool> = tools.values().map(|t| {
+        let mut tool_list: Vec<Tool> = tools.values().map(|t| {
             Tool {
                 name: t.name().to_string(),
                 description: t.description().to_string(),
                 input_schema: t.input_schema(),
             }
         }).collect();
+        tool_list.sort_by(|a, b| a.name.cmp(&b.name));
 
         Ok(serde_json::json!({ "tools": tool_list }))
     }
 
+    /// Only a malformed call or an unknown tool is a JSON-RPC error; a tool
+    /// that fails, including on bad arguments, gives a result with `isError`
+    /// so the client's model can see what went wrong
     async fn call_tool(&self, params: Option<serde_json::Value>) -> Result<serde_json::Value, JsonRpcError> {
-        let params = params.ok_or_else(|| JsonRpcError {
-            code: -32602,
-            message: "Invalid params".to_string(),
-            data: None,
-        })?;
+        let params = params.ok_or_else(|| JsonRpcError::new(JsonRpcError::INVALID_PARAMS, "Invalid params"))?;
 
-        let call: ToolCall = serde_json::from_value(params).map_err(|_| JsonRpcError {
-            code: -32602,
-            message: "Invalid tool call".to_string(),
-            data: None,
-        })?;
+        let call: ToolCall = serde_json::from_value(params)
+            .map_err(|_| JsonRpcError::new(JsonRpcError::INVALID_PARAMS, "Invalid tool call"))?;
 
         let tools = self.tools.read().await;
-        let tool = tools.get(&call.name).ok_or_else(|| JsonRpcError {
-            code: -32602,
-            message: format!("Tool not found: {}", call.name),
-            data: None,
+        let tool = tools.get(&call.name).ok_or_else(|| {
+            JsonRpcError::new(JsonRpcError::INVALID_PARAMS, format!("Tool not found: {}", call.name))
         })?;
 
-        let content = tool.execute(call.arguments).await.map_err(|e| JsonRpcError {
-            code: -32603,
-            message: e,
-            data: None,
+        let result = tool.execute(call.arguments).await.unwrap_or_else(ToolResult::error);
+        Ok(serde_json::to_value(result).expect("tool results serialize"))
+    }
+
+    async fn list_resources(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let mut resources = Vec::new();
+        for provider in self.resources.read().await.iter() {
+            resources.extend(provider.list().await);
+        }
+        let (resources, next_cursor) = paginate(resources, params.as_ref())?;
+        Ok(serde_json::to_value(ListResourcesResult { resources, next_cursor }).expect("resources serialize"))
+    }
+
+    async fn list_resource_templates(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let templates = self.resources.read().await.iter().flat_map(|p| p.templates()).collect();
+        let (resource_templates, next_cursor) = paginate(templates, params.as_ref())?;
+        let result = ListResourceTemplatesResult {
+            resource_templates,
+            next_cursor,
+        };
+        Ok(serde_json::to_value(result).expect("resource templates serialize"))
+    }
+
+    async fn read_resource(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let uri = params
+            .as_ref()
+            .and_then(|p| p.get("uri"))
+            .and_then(Value::as_str)
+            .ok_or_else(|| JsonRpcError::new(JsonRpcError::INVALID_PARAMS, "Missing uri"))?;
+
+        for provider in self.resources.read().await.iter() {
+            match provider.read(uri).await {
+                Some(Ok(contents)) => {
+                    return Ok(serde_json::to_value(ReadResourceResult { contents }).expect("contents serialize"))
+                }
+                Some(Err(e)) => return Err(JsonRpcError::new(JsonRpcError::INTERNAL_ERROR, e)),
+                None => {}
+            }
+        }
+        let error = JsonRpcError::new(JsonRpcError::RESOURCE_NOT_FOUND, "Resource not found");
+        Err(JsonRpcError {
+            data: Some(json!({ "uri": uri })),
+            ..error
+        })
+    }
+
+    async fn list_prompts(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let mut prompts: Vec<Prompt> = self
+            .prompts
+            .read()
+            .await
+            .values()
+            .map(|p| Prompt {
+                name: p.name().to_string(),
+                description: Some(p.description().to_string()).filter(|d| !d.is_empty()),
+                arguments: p.arguments(),
+            })
+            .collect();
+        prompts.sort_by(|a, b| a.name.cmp(&b.name));
+        let (prompts, next_cursor) = paginate(prompts, params.as_ref())?;
+        Ok(serde_json::to_value(ListPromptsResult { prompts, next_cursor }).expect("prompts serialize"))
+    }
+
+    async fn get_prompt(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        #[derive(serde::Deserialize)]
+        struct GetPromptParams {
+            name: String,
+            #[serde(default)]
+            arguments: HashMap<String, String>,
+        }
+        let params: GetPromptParams = params
+            .and_then(|params| serde_json::from_value(params).ok())
+            .ok_or_else(|| JsonRpcError::new(JsonRpcError::INVALID_PARAMS, "Invalid prompts/get params"))?;
+
+        let prompts = self.prompts.read().await;
+        let prompt = prompts.get(&params.name).ok_or_else(|| {
+            JsonRpcError::new(JsonRpcError::INVALID_PARAMS, format!("Prompt not found: {}", params.name))
         })?;
+        if let Some(missing) = prompt
+            .arguments()
+            .iter()
+            .find(|arg| arg.required && !params.arguments.contains_key(&arg.name))
+        {
+            let message = format!("Missing required argument: {}", missing.name);
+            return Err(JsonRpcError::new(JsonRpcError::INVALID_PARAMS, message));
+        }
 
-        Ok(serde_json::to_value(ToolResult {
-            content,
-            is_error: false,
-        }).unwrap())
+        let messages = prompt
+            .get(params.arguments)
+            .await
+            .map_err(|e| JsonRpcError::new(JsonRpcError::INTERNAL_ERROR, e))?;
+        let result = GetPromptResult {
+            description: Some(prompt.description().to_string()).filter(|d| !d.is_empty()),
+            messages,
+        };
+        Ok(serde_json::to_value(result).expect("prompt messages serialize"))
     }
 }
 
+/// The page of `items` starting at `cursor`, and the cursor of the next one
+fn paginate<T>(items: Vec<T>, params: Option<&Value>) -> Result<(Vec<T>, Option<String>), JsonRpcError> {
+    let start = match params.and_then(|p| p.get("cursor")) {
+        None | Some(Value::Null) => 0,
+        Some(cursor) => cursor
+            .as_str()
+            .and_then(|c| c.parse::<usize>().ok())
+            .filter(|&start| start <= items.len())
+            .ok_or_else(|| JsonRpcError::new(JsonRpcError::INVALID_PARAMS, "Invalid cursor"))?,
+    };
+    let end = (start + PAGE_SIZE).min(items.len());
+    let next_cursor = (end < items.len()).then(|| end.to_string());
+    Ok((items.into_iter().skip(start).take(PAGE_SIZE).collect(), next_cursor))
+}
+
+fn invalid_request(id: Value, reason: &str) -> Value {
+    let error = JsonRpcError::new(JsonRpcError::INVALID_REQUEST, format!("Invalid request: {}", reason));
+    serde_json::to_value(JsonRpcResponse::failure(id, error)).expect("responses serialize")
+}
+
 impl Default for MCPServer {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_initialize_handshake() {
+        let server = MCPServer::new().with_info("test", "1.0");
+        let response = server
+            .handle_message(json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "initialize",
+                "params": {
+                    "protocolVersion": "1999-01-01",
+                    "capabilities": {},
+                    "clientInfo": {"name": "client", "version": "2.0"}
+                }
+            }))
+            .await
+            .unwrap();
+
+        assert_eq!(response["result"]["protocolVersion"], LATEST_PROTOCOL_VERSION);
+        assert_eq!(response["result"]["serverInfo"], json!({"name": "test", "version": "1.0"}));
+        assert!(!server.is_initialized());
+
+        let none = server
+            .handle_message(json!({"jsonrpc": "2.0", "method": "notifications/initialized"}))
+            .await;
+        assert!(none.is_none());
+        assert!(server.is_initialized());
+        assert_eq!(server.client_info().unwrap().name, "client");
+    }
+
+    #[tokio::test]
+    async fn test_batch_skips_notifications() {
+        let server = MCPServer::new();
+        let response = server
+            .handle_message(json!([
+                {"jsonrpc": "2.0", "id": 1, "method": "ping"},
+                {"jsonrpc": "2.0", "method": "notifications/cancelled", "params": {"requestId": 1}},
+                {"jsonrpc": "2.0", "id": 2, "method": "nope"}
+            ]))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response,
+            json!([
+                {"jsonrpc": "2.0", "id": 1, "result": {}},
+                {"jsonrpc": "2.0", "id": 2, "error": {"code": -32601, "message": "Method not found"}}
+            ])
+        );
+
+        let notifications_only = json!([{"jsonrpc": "2.0", "method": "notifications/initialized"}]);
+        assert!(server.handle_message(notifications_only).await.is_none());
+        assert_eq!(server.handle_message(json!([])).await.unwrap()["error"]["code"], -32600);
+    }
+
+    struct Numbered(usize);
+
+    #[async_trait::async_trait]
+    impl ResourceProvider for Numbered {
+        async fn list(&self) -> Vec<Resource> {
+            (0..self.0)
+                .map(|i| Resource {
+                    uri: format!("n:///{}", i),
+                    name: i.to_string(),
+                    description: None,
+                    mime_type: None,
+                    size: None,
+                })
+                .collect()
+        }
+
+        async fn read(&self, _uri: &str) -> Option<Result<Vec<ResourceContents>, String>> {
+            None
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resources_are_paginated() {
+        let server = MCPServer::new();
+        let initialize = json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": "initialize",
+            "params": {"protocolVersion": LATEST_PROTOCOL_VERSION, "capabilities": {}, "clientInfo": {"name": "c", "version": "1"}}
+        });
+        let response = server.handle_message(initialize.clone()).await.unwrap();
+        assert!(response["result"]["capabilities"].get("resources").is_none());
+
+        server.register_resources(Box::new(Numbered(PAGE_SIZE + 5))).await;
+        let response = server.handle_message(initialize).await.unwrap();
+        assert_eq!(response["result"]["capabilities"]["resources"], json!({"listChanged": false}));
+
+        let first = server
+            .handle_message(json!({"jsonrpc": "2.0", "id": 1, "method": "resources/list"}))
+            .await
+            .unwrap();
+        assert_eq!(first["result"]["resources"].as_array().unwrap().len(), PAGE_SIZE);
+        let cursor = first["result"]["nextCursor"].clone();
+
+        let rest = server
+            .handle_message(json!({"jsonrpc": "2.0", "id": 2, "method": "resources/list", "params": {"cursor": cursor}}))
+            .await
+            .unwrap();
+        assert_eq!(rest["result"]["resources"].as_array().unwrap().len(), 5);
+        assert!(rest["result"].get("nextCursor").is_none());
+
+        let bad = server
+            .handle_message(json!({"jsonrpc": "2.0", "id": 3, "method": "resources/list", "params": {"cursor": "x"}}))
+            .await
+            .unwrap();
+        assert_eq!(bad["error"]["code"], JsonRpcError::INVALID_PARAMS);
+    }
+}
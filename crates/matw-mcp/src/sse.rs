@@ -0,0 +1,111 @@
+//! Server-sent events
+//!
+//! Incremental parser for `text/event-stream` bodies, used by the HTTP
+//! client transports. Chunks may split lines and UTF-8 sequences anywhere.
+
+/// One dispatched event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    /// `message` unless the stream named it
+    pub event: String,
+    pub data: String,
+    pub id: Option<String>,
+}
+
+/// Collects chunks of an event stream into events
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+    id: Option<String>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a chunk of the stream, returning the events it completes
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                if let Some(event) = self.dispatch() {
+                    events.push(event);
+                }
+                continue;
+            }
+            if line.starts_with(':') {
+                // Comment, often a keep-alive
+                continue;
+            }
+
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line, ""),
+            };
+            match field {
+                "event" => self.event = Some(value.to_string()),
+                "data" => self.data.push(value.to_string()),
+                "id" => self.id = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        events
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        if self.data.is_empty() {
+            return None;
+        }
+        Some(SseEvent {
+            event: event.unwrap_or_else(|| "message".to_string()),
+            data: std::mem::take(&mut self.data).join("\n"),
+            id: self.id.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_split_across_chunks() {
+        let mut parser = SseParser::new();
+        assert!(parser.push(b": keep-alive\n\nevent: endpoint\r\ndata: /messages").is_empty());
+
+        let events = parser.push(b"?sessionId=1\r\n\r\nid: 7\ndata: {\"a\":\ndata: 1}\n\n");
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: "endpoint".to_string(),
+                    data: "/messages?sessionId=1".to_string(),
+                    id: None,
+                },
+                SseEvent {
+                    event: "message".to_string(),
+                    data: "{\"a\":\n1}".to_string(),
+                    id: Some("7".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_utf8_split_across_chunks() {
+        let mut parser = SseParser::new();
+        let bytes = "data: héllo\n\n".as_bytes();
+        assert!(parser.push(&bytes[..8]).is_empty());
+        assert_eq!(parser.push(&bytes[8..])[0].data, "héllo");
+    }
+}
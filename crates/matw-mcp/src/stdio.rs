@@ -0,0 +1,174 @@
+//! Stdio transport
+//!
+//! Serves an `MCPServer` over newline-delimited JSON-RPC: one message or
+//! batch per line on the input, one response per line on the output.
+//! Notifications get no line. Nothing else may be written to the output, so
+//! diagnostics go to stderr.
+
+use super::protocol::{JsonRpcError, JsonRpcResponse};
+use super::server::MCPServer;
+use serde_json::Value;
+use tokio::io::{self, AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+
+/// Serve requests from stdin until it is closed
+pub async fn serve_stdio(server: &MCPServer) -> io::Result<()> {
+    serve(server, BufReader::new(io::stdin()), io::stdout()).await
+}
+
+/// Serve requests read from `reader`, writing responses to `writer`, until
+/// the reader reaches end of input or the client asks for shutdown
+pub async fn serve<R, W>(server: &MCPServer, reader: R, writer: W) -> io::Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    serve_lines(server, reader, writer, None).await
+}
+
+/// Like `serve`, also writing the session to `transcript`: each line
+/// received prefixed with `> ` and each line sent with `< `. This is the
+/// format the client transcript tests replay.
+pub async fn serve_recorded<R, W, T>(server: &MCPServer, reader: R, writer: W, mut transcript: T) -> io::Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+    T: std::io::Write + Send,
+{
+    serve_lines(server, reader, writer, Some(&mut transcript)).await
+}
+
+async fn serve_lines<R, W>(
+    server: &MCPServer,
+    reader: R,
+    mut writer: W,
+    mut transcript: Option<&mut (dyn std::io::Write + Send)>,
+) -> io::Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut lines = reader.lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(transcript) = transcript.as_mut() {
+            writeln!(transcript, "> {}", line)?;
+        }
+
+        if let Some(response) = handle_line(server, &line).await {
+            let mut encoded = serde_json::to_vec(&response).map_err(io::Error::other)?;
+            if let Some(transcript) = transcript.as_mut() {
+                writeln!(transcript, "< {}", String::from_utf8_lossy(&encoded))?;
+                transcript.flush()?;
+            }
+            encoded.push(b'\n');
+            writer.write_all(&encoded).await?;
+            writer.flush().await?;
+        }
+        if server.is_shut_down() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+async fn handle_line(server: &MCPServer, line: &str) -> Option<Value> {
+    match serde_json::from_str(line) {
+        Ok(message) => server.handle_message(message).await,
+        Err(e) => {
+            let error = JsonRpcError::new(JsonRpcError::PARSE_ERROR, format!("Parse error: {}", e));
+            Some(serde_json::to_value(JsonRpcResponse::failure(Value::Null, error)).expect("responses serialize"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::register_tools;
+    use matw_tools::tools::GlobTool;
+    use std::sync::Arc;
+
+    async fn exchange(input: &str) -> Vec<Value> {
+        let server = MCPServer::new();
+        register_tools(&server, vec![Arc::new(GlobTool::new())]).await;
+
+        let mut output = Vec::new();
+        serve(&server, input.as_bytes(), &mut output).await.unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_one_response_per_line() {
+        let responses = exchange(concat!(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "tools/list"}"#,
+            "\n\n",
+            r#"{"jsonrpc": "2.0", "id": "two", "method": "unknown"}"#,
+            "\n",
+        ))
+        .await;
+
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["result"]["tools"][0]["name"], "glob");
+        assert_eq!(responses[1]["id"], "two");
+        assert_eq!(responses[1]["error"]["code"], -32601);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_messages() {
+        let responses = exchange("{not json\n{\"id\": 3, \"method\": 5}\n").await;
+
+        assert_eq!(responses[0]["error"]["code"], -32700);
+        assert_eq!(responses[0]["id"], Value::Null);
+        assert_eq!(responses[1]["error"]["code"], -32600);
+        assert_eq!(responses[1]["id"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_records_transcript() {
+        let server = MCPServer::new();
+        let input = concat!(
+            r#"{"jsonrpc": "2.0", "method": "notifications/initialized"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "ping"}"#,
+            "\n",
+        );
+
+        let mut output = Vec::new();
+        let mut transcript = Vec::new();
+        serve_recorded(&server, input.as_bytes(), &mut output, &mut transcript).await.unwrap();
+
+        assert_eq!(
+            String::from_utf8(transcript).unwrap(),
+            concat!(
+                r#"> {"jsonrpc": "2.0", "method": "notifications/initialized"}"#,
+                "\n",
+                r#"> {"jsonrpc": "2.0", "id": 1, "method": "ping"}"#,
+                "\n",
+                r#"< {"id":1,"jsonrpc":"2.0","result":{}}"#,
+                "\n",
+            )
+        );
+        assert_eq!(String::from_utf8(output).unwrap(), "{\"id\":1,\"jsonrpc\":\"2.0\",\"result\":{}}\n");
+    }
+
+    #[tokio::test]
+    async fn test_stops_after_shutdown() {
+        let responses = exchange(concat!(
+            r#"{"jsonrpc": "2.0", "method": "notifications/initialized"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "shutdown"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "ping"}"#,
+            "\n",
+        ))
+        .await;
+
+        assert_eq!(responses, vec![serde_json::json!({"jsonrpc": "2.0", "id": 1, "result": {}})]);
+    }
+}
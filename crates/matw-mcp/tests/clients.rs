@@ -0,0 +1,75 @@
+//! Replays client sessions against the stdio server
+//!
+//! `tests/transcripts/` holds sessions recorded from real clients with the
+//! `transcript_server` example (see the README there). `tests/synthetic/`
+//! holds sessions written by hand from the MCP specification, covering
+//! traffic the recorded clients do not send. A file holds the messages the
+//! client sends (`> `) and the responses the server must write (`< `), in
+//! order. Lines starting with `#` are comments, and `${VERSION}` stands for
+//! this crate's version.
+
+mod common;
+
+use matw_mcp::stdio::serve;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+async fn replay(path: &Path) {
+    let script = std::fs::read_to_string(path)
+        .unwrap()
+        .replace("${VERSION}", env!("CARGO_PKG_VERSION"));
+    let mut input = String::new();
+    let mut expected: Vec<Value> = Vec::new();
+    for line in script.lines() {
+        if let Some(message) = line.strip_prefix("> ") {
+            input.push_str(message);
+            input.push('\n');
+        } else if let Some(response) = line.strip_prefix("< ") {
+            expected.push(serde_json::from_str(response).unwrap());
+        }
+    }
+
+    let server = common::fixture_server().await;
+    let mut output = Vec::new();
+    serve(&server, input.as_bytes(), &mut output).await.unwrap();
+
+    let actual: Vec<Value> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(actual, expected, "session {}", path.display());
+}
+
+/// The `.jsonl` sessions in a directory, by name
+fn sessions(dir: &str) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[tokio::test]
+async fn test_recorded_clients() {
+    for path in sessions("tests/transcripts") {
+        replay(&path).await;
+    }
+}
+
+#[tokio::test]
+async fn test_desktop_style_client() {
+    replay(Path::new("tests/synthetic/desktop_style_2024_11_05.jsonl")).await;
+}
+
+#[tokio::test]
+async fn test_inspector_style_client() {
+    replay(Path::new("tests/synthetic/inspector_style_2025_06_18.jsonl")).await;
+}
+
+#[tokio::test]
+async fn test_batching_client() {
+    replay(Path::new("tests/synthetic/batching_client.jsonl")).await;
+}
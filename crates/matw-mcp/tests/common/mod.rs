@@ -0,0 +1,95 @@
+//! The server the client transcripts are recorded and replayed against:
+//! an `echo` tool, one `note:///todo` resource and a `greet` prompt
+
+use async_trait::async_trait;
+use matw_mcp::protocol::{ContentItem, PromptArgument, PromptMessage, Resource, ResourceContents, ToolResult};
+use matw_mcp::{MCPServer, MCPrompt, MCTool, ResourceProvider};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+struct Echo;
+
+#[async_trait]
+impl MCTool for Echo {
+    fn name(&self) -> &str {
+        "echo"
+    }
+
+    fn description(&self) -> &str {
+        "Echo the text back"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({"type": "object", "properties": {"text": {"type": "string"}}, "required": ["text"]})
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult, String> {
+        let text = args["text"].as_str().ok_or("text is required")?;
+        Ok(ToolResult::text(text))
+    }
+}
+
+/// One text resource, `note:///todo`
+struct Notes;
+
+#[async_trait]
+impl ResourceProvider for Notes {
+    async fn list(&self) -> Vec<Resource> {
+        vec![Resource {
+            uri: "note:///todo".to_string(),
+            name: "todo".to_string(),
+            description: None,
+            mime_type: Some("text/plain".to_string()),
+            size: None,
+        }]
+    }
+
+    async fn read(&self, uri: &str) -> Option<Result<Vec<ResourceContents>, String>> {
+        (uri == "note:///todo").then(|| {
+            Ok(vec![ResourceContents {
+                uri: uri.to_string(),
+                mime_type: Some("text/plain".to_string()),
+                text: Some("Write the docs".to_string()),
+                blob: None,
+            }])
+        })
+    }
+}
+
+struct Greet;
+
+#[async_trait]
+impl MCPrompt for Greet {
+    fn name(&self) -> &str {
+        "greet"
+    }
+
+    fn description(&self) -> &str {
+        "Greet someone"
+    }
+
+    fn arguments(&self) -> Vec<PromptArgument> {
+        vec![PromptArgument {
+            name: "name".to_string(),
+            description: None,
+            required: true,
+        }]
+    }
+
+    async fn get(&self, args: HashMap<String, String>) -> Result<Vec<PromptMessage>, String> {
+        Ok(vec![PromptMessage {
+            role: "user".to_string(),
+            content: ContentItem::Text {
+                text: format!("Say hello to {}", args["name"]),
+            },
+        }])
+    }
+}
+
+pub async fn fixture_server() -> MCPServer {
+    let server = MCPServer::new();
+    server.register_tool(Box::new(Echo)).await;
+    server.register_resources(Box::new(Notes)).await;
+    server.register_prompt(Box::new(Greet)).await;
+    server
+}
@@ -0,0 +1,281 @@
+//! The HTTP transports, with the client talking to an in-process server
+//! on a local port
+
+use matw_mcp::http_client::SESSION_HEADER;
+use matw_mcp::{register_tools, ClientEvent, HttpServer, MCPServer, McpClient, McpError};
+use matw_tools::tools::GlobTool;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+/// Serve `server` on a free local port, returning its base URL
+async fn start(server: HttpServer) -> (String, Arc<HttpServer>) {
+    let server = Arc::new(server);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(matw_mcp::serve_http(server.clone(), listener));
+    (url, server)
+}
+
+async fn glob_server() -> MCPServer {
+    let server = MCPServer::new().with_info("http-test", "1.0");
+    register_tools(&server, vec![Arc::new(GlobTool::new())]).await;
+    server
+}
+
+async fn check_client(client: &McpClient) {
+    let info = client.initialize().await.unwrap();
+    assert_eq!(info.server_info.name, "http-test");
+
+    let tools = client.list_tools().await.unwrap();
+    assert_eq!(tools[0].name, "glob");
+
+    let result = client
+        .call_tool("glob", json!({"pattern": "*.toml"}), &CancellationToken::new())
+        .await
+        .unwrap();
+    assert!(!result.is_error);
+}
+
+#[tokio::test]
+async fn test_streamable_http_json_responses() {
+    let (url, server) = start(HttpServer::new(glob_server().await)).await;
+    let (tx, _events) = mpsc::unbounded_channel();
+    let client = McpClient::connect_http(&format!("{}/mcp", url), &[], tx).unwrap();
+
+    check_client(&client).await;
+    assert_eq!(server.session_count(), 1);
+
+    // Dropping the client ends its session
+    drop(client);
+    for _ in 0..100 {
+        if server.session_count() == 0 {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    panic!("session was not ended");
+}
+
+#[tokio::test]
+async fn test_streamable_http_sse_responses() {
+    let (url, _server) = start(HttpServer::new(glob_server().await).with_sse_responses(true)).await;
+    let (tx, _events) = mpsc::unbounded_channel();
+    let client = McpClient::connect_http(&format!("{}/mcp", url), &[], tx).unwrap();
+
+    check_client(&client).await;
+}
+
+#[tokio::test]
+async fn test_legacy_sse() {
+    let (url, server) = start(HttpServer::new(glob_server().await)).await;
+    let (tx, mut events) = mpsc::unbounded_channel();
+    let client = McpClient::connect_sse(&format!("{}/sse", url), &[], tx).await.unwrap();
+
+    check_client(&client).await;
+    assert_eq!(server.session_count(), 1);
+
+    // Shutting down ends the event stream, which closes the connection
+    client.request("shutdown", None).await.unwrap();
+    assert_eq!(events.recv().await, Some(ClientEvent::Closed));
+    assert!(client.is_closed());
+}
+
+#[tokio::test]
+async fn test_bearer_token() {
+    let (url, _server) = start(HttpServer::new(glob_server().await).with_bearer_token("s3cret")).await;
+    let endpoint = format!("{}/mcp", url);
+
+    let (tx, _events) = mpsc::unbounded_channel();
+    let anonymous = McpClient::connect_http(&endpoint, &[], tx).unwrap();
+    match anonymous.initialize().await {
+        Err(McpError::Status { status: 401, .. }) => {}
+        other => panic!("expected 401, got {:?}", other.map(|r| r.server_info)),
+    }
+
+    let (tx, _events) = mpsc::unbounded_channel();
+    let headers = [("Authorization".to_string(), "Bearer s3cret".to_string())];
+    let client = McpClient::connect_http(&endpoint, &headers, tx).unwrap();
+    check_client(&client).await;
+
+    let (tx, _events) = mpsc::unbounded_channel();
+    let client = McpClient::connect_sse(&format!("{}/sse", url), &headers, tx).await.unwrap();
+    check_client(&client).await;
+}
+
+#[tokio::test]
+async fn test_session_rules() {
+    let (url, _server) = start(HttpServer::new(glob_server().await)).await;
+    let endpoint = format!("{}/mcp", url);
+    let http = reqwest::Client::new();
+    let ping = json!({"jsonrpc": "2.0", "id": 1, "method": "ping"});
+
+    // Requests other than initialize need a session
+    let response = http.post(&endpoint).json(&ping).send().await.unwrap();
+    assert_eq!(response.status(), 400);
+    let response = http
+        .post(&endpoint)
+        .header(SESSION_HEADER, "unknown")
+        .json(&ping)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+
+    let initialize = json!({
+        "jsonrpc": "2.0",
+        "id": 0,
+        "method": "initialize",
+        "params": {"protocolVersion": "2025-06-18", "capabilities": {}, "clientInfo": {"name": "curl", "version": "1"}}
+    });
+    let response = http.post(&endpoint).json(&initialize).send().await.unwrap();
+    let session = response.headers()[SESSION_HEADER].to_str().unwrap().to_string();
+
+    // Notifications are accepted without a body
+    let initialized = json!({"jsonrpc": "2.0", "method": "notifications/initialized"});
+    let response = http
+        .post(&endpoint)
+        .header(SESSION_HEADER, &session)
+        .json(&initialized)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+
+    let response = http
+        .post(&endpoint)
+        .header(SESSION_HEADER, &session)
+        .json(&ping)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.json::<Value>().await.unwrap(), json!({"jsonrpc": "2.0", "id": 1, "result": {}}));
+
+    // No stream for server-initiated messages
+    let response = http.get(&endpoint).header(SESSION_HEADER, &session).send().await.unwrap();
+    assert_eq!(response.status(), 405);
+
+    // Foreign origins are refused
+    let response = http
+        .post(&endpoint)
+        .header("Origin", "https://evil.example")
+        .json(&initialize)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+
+    let response = http.delete(&endpoint).header(SESSION_HEADER, &session).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let response = http
+        .post(&endpoint)
+        .header(SESSION_HEADER, &session)
+        .json(&ping)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn test_session_limits() {
+    let server = HttpServer::new(glob_server().await)
+        .with_max_sessions(1)
+        .with_session_timeout(Duration::from_millis(500));
+    let (url, server) = start(server).await;
+    let endpoint = format!("{}/mcp", url);
+    let http = reqwest::Client::new();
+    let initialize = json!({
+        "jsonrpc": "2.0",
+        "id": 0,
+        "method": "initialize",
+        "params": {"protocolVersion": "2025-06-18", "capabilities": {}, "clientInfo": {"name": "curl", "version": "1"}}
+    });
+    let ping = json!({"jsonrpc": "2.0", "id": 1, "method": "ping"});
+
+    let response = http.post(&endpoint).json(&initialize).send().await.unwrap();
+    let session = response.headers()[SESSION_HEADER].to_str().unwrap().to_string();
+
+    // No room for another session on either transport
+    let response = http.post(&endpoint).json(&initialize).send().await.unwrap();
+    assert_eq!(response.status(), 503);
+    let response = http.get(format!("{}/sse", url)).send().await.unwrap();
+    assert_eq!(response.status(), 503);
+
+    // Using a session keeps it open
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let response = http
+        .post(&endpoint)
+        .header(SESSION_HEADER, &session)
+        .json(&ping)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let response = http.post(&endpoint).json(&initialize).send().await.unwrap();
+    assert_eq!(response.status(), 503);
+
+    // An idle session is forgotten, making room for a new one
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    let response = http.post(&endpoint).json(&initialize).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(server.session_count(), 1);
+    let response = http
+        .post(&endpoint)
+        .header(SESSION_HEADER, &session)
+        .json(&ping)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn test_concurrent_handshakes_respect_session_limit() {
+    let (url, server) = start(HttpServer::new(glob_server().await).with_max_sessions(2)).await;
+    let endpoint = format!("{}/mcp", url);
+    let http = reqwest::Client::new();
+    let initialize = json!({
+        "jsonrpc": "2.0",
+        "id": 0,
+        "method": "initialize",
+        "params": {"protocolVersion": "2025-06-18", "capabilities": {}, "clientInfo": {"name": "curl", "version": "1"}}
+    });
+
+    // A failed handshake gives its place back
+    let invalid = json!({"jsonrpc": "2.0", "id": 0, "method": "initialize"});
+    for _ in 0..3 {
+        let response = http.post(&endpoint).json(&invalid).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert!(response.headers().get(SESSION_HEADER).is_none());
+    }
+
+    let requests = (0..16).map(|_| http.post(&endpoint).json(&initialize).send());
+    let statuses: Vec<u16> = futures::future::join_all(requests)
+        .await
+        .into_iter()
+        .map(|response| response.unwrap().status().as_u16())
+        .collect();
+
+    assert_eq!(statuses.iter().filter(|&&status| status == 200).count(), 2);
+    assert_eq!(statuses.iter().filter(|&&status| status == 503).count(), 14);
+    assert_eq!(server.session_count(), 2);
+}
+
+#[tokio::test]
+async fn test_expired_session_closes_client() {
+    let (url, _server) = start(HttpServer::new(glob_server().await)).await;
+    let endpoint = format!("{}/mcp", url);
+    let (tx, mut events) = mpsc::unbounded_channel();
+    let client = McpClient::connect_http(&endpoint, &[], tx).unwrap();
+    client.initialize().await.unwrap();
+
+    // The server forgets the session, e.g. after a restart
+    client.request("shutdown", None).await.unwrap();
+    assert!(matches!(client.request("ping", None).await, Err(McpError::Closed)));
+    assert_eq!(events.recv().await, Some(ClientEvent::Closed));
+}
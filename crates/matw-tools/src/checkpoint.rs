@@ -0,0 +1,242 @@
+//! File checkpoints
+//!
+//! Before a tool changes a file it records the file's contents here, under
+//! the checkpoint of the user message being answered. Rewinding restores
+//! every file changed since a checkpoint and deletes the files the agent
+//! created, along with the directories made for them, without relying on
+//! git.
+//!
+//! Checkpoints are kept in memory, so only changes made since matw started
+//! can be rewound; a resumed session has none for its earlier prompts.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// A file as it was before the first change in a checkpoint
+#[derive(Debug, Clone)]
+struct Backup {
+    path: PathBuf,
+    /// `None` if the file did not exist
+    original: Option<Vec<u8>>,
+    /// Missing parent directories of a new file, deepest first
+    missing_dirs: Vec<PathBuf>,
+}
+
+#[derive(Debug)]
+struct Checkpoint {
+    id: Uuid,
+    backups: Vec<Backup>,
+}
+
+/// Per-session file backups, in checkpoint order
+#[derive(Debug, Default)]
+pub struct CheckpointStore {
+    checkpoints: Mutex<Vec<Checkpoint>>,
+}
+
+impl CheckpointStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start recording changes under `id`, usually a user message id.
+    /// Beginning the current checkpoint again keeps what it holds.
+    pub fn begin(&self, id: Uuid) {
+        let mut checkpoints = self.checkpoints.lock().unwrap();
+        if checkpoints.last().map(|c| c.id) != Some(id) {
+            checkpoints.push(Checkpoint { id, backups: Vec::new() });
+        }
+    }
+
+    /// Back up a file about to be changed. Only the first change to a file
+    /// in a checkpoint is recorded; without a checkpoint nothing is.
+    pub fn record(&self, path: &Path) -> io::Result<()> {
+        let mut checkpoints = self.checkpoints.lock().unwrap();
+        let Some(checkpoint) = checkpoints.last_mut() else {
+            return Ok(());
+        };
+        if checkpoint.backups.iter().any(|b| b.path == path) {
+            return Ok(());
+        }
+
+        let original = match fs::read(path) {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        let missing_dirs = match original {
+            Some(_) => Vec::new(),
+            None => path
+                .ancestors()
+                .skip(1)
+                .take_while(|dir| !dir.exists())
+                .map(Path::to_path_buf)
+                .collect(),
+        };
+        checkpoint.backups.push(Backup {
+            path: path.to_path_buf(),
+            original,
+            missing_dirs,
+        });
+        Ok(())
+    }
+
+    /// Whether changes are recorded under `id`
+    pub fn contains(&self, id: Uuid) -> bool {
+        self.checkpoints.lock().unwrap().iter().any(|c| c.id == id)
+    }
+
+    /// Ids of the checkpoints, oldest first
+    pub fn ids(&self) -> Vec<Uuid> {
+        self.checkpoints.lock().unwrap().iter().map(|c| c.id).collect()
+    }
+
+    /// Files changed since the earliest of the given checkpoints
+    pub fn changed_files(&self, ids: &[Uuid]) -> Vec<PathBuf> {
+        let checkpoints = self.checkpoints.lock().unwrap();
+        let mut files: Vec<PathBuf> = Vec::new();
+        for backup in checkpoints[first_of(&checkpoints, ids)..].iter().flat_map(|c| &c.backups) {
+            if !files.contains(&backup.path) {
+                files.push(backup.path.clone());
+            }
+        }
+        files
+    }
+
+    /// Undo every change made since the earliest of the given checkpoints,
+    /// newest first, and forget those checkpoints. Returns the files
+    /// restored or deleted.
+    ///
+    /// Later checkpoints are undone too even if their ids are not given, so
+    /// files always end up as they were when that checkpoint began.
+    pub fn rewind(&self, ids: &[Uuid]) -> io::Result<Vec<PathBuf>> {
+        let mut checkpoints = self.checkpoints.lock().unwrap();
+        let first = first_of(&checkpoints, ids);
+        let mut touched: Vec<PathBuf> = Vec::new();
+
+        while checkpoints.len() > first {
+            let checkpoint = checkpoints.last().expect("more checkpoints than first");
+            for backup in checkpoint.backups.iter().rev() {
+                restore(backup)?;
+                if !touched.contains(&backup.path) {
+                    touched.push(backup.path.clone());
+                }
+            }
+            checkpoints.pop();
+        }
+        Ok(touched)
+    }
+}
+
+/// Index of the earliest checkpoint with one of the ids, or the end if none has
+fn first_of(checkpoints: &[Checkpoint], ids: &[Uuid]) -> usize {
+    checkpoints
+        .iter()
+        .position(|c| ids.contains(&c.id))
+        .unwrap_or(checkpoints.len())
+}
+
+fn restore(backup: &Backup) -> io::Result<()> {
+    match &backup.original {
+        Some(content) => {
+            if let Some(parent) = backup.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&backup.path, content)
+        }
+        None => {
+            match fs::remove_file(&backup.path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+            // Directories made for the file go too, unless something else is in them
+            for dir in &backup.missing_dirs {
+                match fs::read_dir(dir).map(|mut entries| entries.next().is_none()) {
+                    Ok(true) => fs::remove_dir(dir)?,
+                    Ok(false) => break,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewind_restores_and_deletes() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().join("existing.txt");
+        let created = dir.path().join("created.txt");
+        fs::write(&existing, "v1").unwrap();
+
+        let store = CheckpointStore::new();
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+
+        store.begin(first);
+        store.record(&existing).unwrap();
+        fs::write(&existing, "v2").unwrap();
+
+        store.begin(second);
+        store.record(&existing).unwrap();
+        fs::write(&existing, "v3").unwrap();
+        store.record(&created).unwrap();
+        fs::write(&created, "new").unwrap();
+        store.record(&created).unwrap();
+        fs::write(&created, "newer").unwrap();
+
+        let touched = store.rewind(&[second]).unwrap();
+        assert_eq!(touched, vec![created.clone(), existing.clone()]);
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "v2");
+        assert!(!created.exists());
+        assert_eq!(store.ids(), vec![first]);
+
+        store.begin(second);
+        store.record(&existing).unwrap();
+        fs::write(&existing, "v4").unwrap();
+
+        // Rewinding to the first checkpoint also undoes the ones after it
+        assert_eq!(store.changed_files(&[first]), vec![existing.clone()]);
+        store.rewind(&[first]).unwrap();
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "v1");
+        assert!(store.ids().is_empty());
+    }
+
+    #[test]
+    fn test_rewind_removes_created_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("src/new/a.txt");
+        let second = dir.path().join("src/new/b.txt");
+        let kept = dir.path().join("src/kept.txt");
+
+        let store = CheckpointStore::new();
+        store.begin(Uuid::new_v4());
+        for path in [&first, &second] {
+            store.record(path).unwrap();
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "new").unwrap();
+        }
+        // Written by someone else, so `src` stays
+        fs::write(&kept, "mine").unwrap();
+
+        store.rewind(&store.ids()).unwrap();
+        assert!(!dir.path().join("src/new").exists());
+        assert!(kept.exists());
+    }
+
+    #[test]
+    fn test_record_without_checkpoint_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CheckpointStore::new();
+        store.record(&dir.path().join("a.txt")).unwrap();
+        assert!(store.changed_files(&store.ids()).is_empty());
+    }
+}
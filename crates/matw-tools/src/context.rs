@@ -0,0 +1,272 @@
+//! Tool execution context
+//!
+//! Carries the session state a tool needs to run: the working directory and
+//! project roots used for path resolution, the environment, a cancellation
+//! signal, the permission state of the call, a progress sink, the files
+//! read so far, the session's task list and its file checkpoints.
+
+use crate::checkpoint::CheckpointStore;
+use crate::file_state::FileStateTracker;
+use crate::path::{resolve_path, PathPolicy, ResolvedPath};
+use crate::todo::TodoStore;
+use crate::ToolError;
+use matw_core::Session;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+/// Permission state of the current tool call
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PermissionState {
+    /// No permission decision has been made for this call
+    #[default]
+    Unchecked,
+    /// The call was allowed by a rule or approved by the user
+    Granted,
+    /// Only read-only operations are allowed
+    ReadOnly,
+}
+
+/// Receives progress messages from a running tool
+#[derive(Clone)]
+pub struct ProgressSink(Arc<dyn Fn(&str) + Send + Sync>);
+
+impl ProgressSink {
+    pub fn new(f: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    pub fn report(&self, message: &str) {
+        (self.0)(message)
+    }
+}
+
+impl fmt::Debug for ProgressSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressSink")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ToolContext {
+    working_dir: PathBuf,
+    roots: Vec<PathBuf>,
+    path_policy: PathPolicy,
+    environment: HashMap<String, String>,
+    cancel: CancellationToken,
+    permission: PermissionState,
+    progress: Option<ProgressSink>,
+    file_state: Arc<FileStateTracker>,
+    todos: Arc<TodoStore>,
+    checkpoints: Arc<CheckpointStore>,
+}
+
+impl ToolContext {
+    /// Create a context whose only project root is the working directory
+    pub fn new(working_dir: PathBuf) -> Self {
+        Self {
+            roots: vec![working_dir.clone()],
+            working_dir,
+            path_policy: PathPolicy::default(),
+            environment: HashMap::new(),
+            cancel: CancellationToken::new(),
+            permission: PermissionState::default(),
+            progress: None,
+            file_state: Arc::new(FileStateTracker::new()),
+            todos: Arc::new(TodoStore::new()),
+            checkpoints: Arc::new(CheckpointStore::new()),
+        }
+    }
+
+    /// Create a context from a session, adding the git root as a project root
+    pub fn from_session(session: &Session) -> Self {
+        let context = session.context();
+        let mut ctx = Self::new(context.working_dir().to_path_buf())
+            .with_environment(context.environment().clone());
+        if let Some(git_info) = context.git_info() {
+            ctx = ctx.with_root(git_info.root.clone());
+        }
+        ctx
+    }
+
+    pub fn with_root(mut self, root: PathBuf) -> Self {
+        if !self.roots.contains(&root) {
+            self.roots.push(root);
+        }
+        self
+    }
+
+    pub fn with_path_policy(mut self, policy: PathPolicy) -> Self {
+        self.path_policy = policy;
+        self
+    }
+
+    pub fn with_environment(mut self, environment: HashMap<String, String>) -> Self {
+        self.environment = environment;
+        self
+    }
+
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    pub fn with_permission(mut self, permission: PermissionState) -> Self {
+        self.permission = permission;
+        self
+    }
+
+    pub fn with_progress(mut self, progress: ProgressSink) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Share read-before-write state, e.g. across the tool calls of a session
+    pub fn with_file_state(mut self, file_state: Arc<FileStateTracker>) -> Self {
+        self.file_state = file_state;
+        self
+    }
+
+    /// Share the task list, e.g. across the tool calls of a session
+    pub fn with_todos(mut self, todos: Arc<TodoStore>) -> Self {
+        self.todos = todos;
+        self
+    }
+
+    /// Share file checkpoints, e.g. across the tool calls of a session
+    pub fn with_checkpoints(mut self, checkpoints: Arc<CheckpointStore>) -> Self {
+        self.checkpoints = checkpoints;
+        self
+    }
+
+    pub fn working_dir(&self) -> &Path {
+        &self.working_dir
+    }
+
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    pub fn path_policy(&self) -> PathPolicy {
+        self.path_policy
+    }
+
+    pub fn environment(&self) -> &HashMap<String, String> {
+        &self.environment
+    }
+
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancel
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    pub fn permission(&self) -> PermissionState {
+        self.permission
+    }
+
+    pub fn file_state(&self) -> &FileStateTracker {
+        &self.file_state
+    }
+
+    pub fn todos(&self) -> &TodoStore {
+        &self.todos
+    }
+
+    /// Where mutating tools back up files before changing them
+    pub fn checkpoints(&self) -> &Arc<CheckpointStore> {
+        &self.checkpoints
+    }
+
+    /// Where progress messages go, for passing on to nested work
+    pub fn progress(&self) -> Option<&ProgressSink> {
+        self.progress.as_ref()
+    }
+
+    /// Send a progress message to the sink, if any
+    pub fn report_progress(&self, message: &str) {
+        if let Some(progress) = &self.progress {
+            progress.report(message);
+        }
+    }
+
+    /// Resolve a tool-supplied path against this context
+    pub fn resolve_path(&self, raw: &str) -> Result<ResolvedPath, ToolError> {
+        resolve_path(self, raw)
+    }
+
+    /// Fail with `PermissionDenied` if the call may only read
+    pub fn ensure_writable(&self, action: &str) -> Result<(), ToolError> {
+        if self.permission == PermissionState::ReadOnly {
+            return Err(ToolError::PermissionDenied(format!(
+                "{} is not allowed in read-only mode",
+                action
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for ToolContext {
+    fn default() -> Self {
+        Self::new(std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matw_core::{Context, GitInfo};
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_from_session_includes_git_root() {
+        let git_info = GitInfo {
+            branch: "main".to_string(),
+            commit: "abc123".to_string(),
+            root: PathBuf::from("/repo"),
+            dirty: false,
+        };
+        let context = Context::with_details(PathBuf::from("/repo/crates/app"), Some(git_info), None);
+        let session = Session::with_context(context);
+
+        let ctx = ToolContext::from_session(&session);
+
+        assert_eq!(ctx.working_dir(), Path::new("/repo/crates/app"));
+        assert_eq!(ctx.roots(), &[PathBuf::from("/repo/crates/app"), PathBuf::from("/repo")]);
+    }
+
+    #[test]
+    fn test_from_session_copies_environment() {
+        let mut session = Session::new(PathBuf::from("/tmp"));
+        session.context_mut().set_env("EDITOR".to_string(), "vim".to_string());
+
+        let ctx = ToolContext::from_session(&session);
+
+        assert_eq!(ctx.environment().get("EDITOR").map(String::as_str), Some("vim"));
+    }
+
+    #[test]
+    fn test_progress_sink_receives_messages() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink_received = received.clone();
+        let ctx = ToolContext::new(PathBuf::from("/tmp")).with_progress(ProgressSink::new(move |m| {
+            sink_received.lock().unwrap().push(m.to_string());
+        }));
+
+        ctx.report_progress("halfway");
+
+        assert_eq!(*received.lock().unwrap(), vec!["halfway".to_string()]);
+    }
+
+    #[test]
+    fn test_read_only_blocks_writes() {
+        let ctx = ToolContext::new(PathBuf::from("/tmp")).with_permission(PermissionState::ReadOnly);
+        assert!(matches!(ctx.ensure_writable("write"), Err(ToolError::PermissionDenied(_))));
+        assert!(ToolContext::new(PathBuf::from("/tmp")).ensure_writable("write").is_ok());
+    }
+}
@@ -0,0 +1,159 @@
+//! Read-before-write tracking
+//!
+//! `ReadTool` records the modification time and content hash of every file it
+//! reads. File-mutating tools check the record before changing an existing
+//! file, so the model cannot overwrite a file it never read, or one that was
+//! changed on disk after it last read it.
+
+use crate::ToolError;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// What a file looked like when the model last saw it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileSnapshot {
+    pub modified: Option<SystemTime>,
+    pub hash: u64,
+}
+
+impl FileSnapshot {
+    fn capture(path: &Path, content: &[u8]) -> Self {
+        Self {
+            modified: modified(path),
+            hash: hash_content(content),
+        }
+    }
+}
+
+/// Per-session record of the files the model has read
+#[derive(Debug, Default)]
+pub struct FileStateTracker {
+    files: Mutex<HashMap<PathBuf, FileSnapshot>>,
+}
+
+impl FileStateTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that the model has seen `content` at `path`
+    pub fn record_read(&self, path: &Path, content: &[u8]) {
+        self.record(path, content);
+    }
+
+    /// Record the content a tool just wrote, so the model may write again
+    pub fn record_write(&self, path: &Path, content: &[u8]) {
+        self.record(path, content);
+    }
+
+    pub fn snapshot(&self, path: &Path) -> Option<FileSnapshot> {
+        self.files.lock().unwrap().get(path).copied()
+    }
+
+    /// Check that an existing file may be modified.
+    ///
+    /// New files may always be created. Existing files must have been read,
+    /// and must not have changed since; a changed modification time with
+    /// identical content is accepted.
+    pub fn check_writable(&self, path: &Path) -> Result<(), ToolError> {
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let Some(snapshot) = self.snapshot(path) else {
+            return Err(ToolError::FileState(format!(
+                "{} has not been read yet. Read it first, then retry.",
+                path.display()
+            )));
+        };
+
+        if snapshot.modified.is_some() && snapshot.modified == modified(path) {
+            return Ok(());
+        }
+
+        let current = fs::read(path)?;
+        if hash_content(&current) != snapshot.hash {
+            return Err(ToolError::FileState(format!(
+                "{} has been modified since it was last read. Read it again, then retry.",
+                path.display()
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn record(&self, path: &Path, content: &[u8]) {
+        let snapshot = FileSnapshot::capture(path, content);
+        self.files.lock().unwrap().insert(path.to_path_buf(), snapshot);
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn hash_content(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_new_file_is_writable() {
+        let temp = TempDir::new().unwrap();
+        let tracker = FileStateTracker::new();
+        assert!(tracker.check_writable(&temp.path().join("new.txt")).is_ok());
+    }
+
+    #[test]
+    fn test_unread_file_is_rejected() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("existing.txt");
+        fs::write(&path, "original").unwrap();
+
+        let err = FileStateTracker::new().check_writable(&path).unwrap_err();
+
+        assert!(err.to_string().contains("has not been read"));
+    }
+
+    #[test]
+    fn test_changed_file_is_rejected() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("existing.txt");
+        fs::write(&path, "original").unwrap();
+        let tracker = FileStateTracker::new();
+        tracker.record_read(&path, b"original");
+
+        fs::write(&path, "edited by the user").unwrap();
+        let err = tracker.check_writable(&path).unwrap_err();
+
+        assert!(err.to_string().contains("modified since it was last read"));
+    }
+
+    #[test]
+    fn test_same_content_is_accepted() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("existing.txt");
+        fs::write(&path, "original").unwrap();
+        let tracker = FileStateTracker::new();
+        tracker.files.lock().unwrap().insert(
+            path.clone(),
+            FileSnapshot {
+                modified: None,
+                hash: hash_content(b"original"),
+            },
+        );
+
+        assert!(tracker.check_writable(&path).is_ok());
+    }
+}
@@ -1,5 +1,19 @@
+pub mod checkpoint;
+pub mod context;
+pub mod file_state;
+pub mod path;
+pub mod registry;
+pub mod schema;
+pub mod todo;
 pub mod tool;
 pub mod tools;
 
+pub use checkpoint::CheckpointStore;
+pub use context::{PermissionState, ProgressSink, ToolContext};
+pub use file_state::{FileSnapshot, FileStateTracker};
+pub use path::{PathPolicy, ResolvedPath};
+pub use registry::{RegistryError, ToolFilter, ToolRegistry, ToolSource};
+pub use schema::{validate, SchemaViolation};
+pub use todo::TodoStore;
 pub use tool::{Tool, ToolError, ToolOutput};
 pub use tools::all_tools;
@@ -0,0 +1,265 @@
+//! Path resolution for file tools
+//!
+//! Resolves tool-supplied paths against the session working directory,
+//! canonicalizes symlinks and checks the result against the project roots.
+
+use crate::{ToolContext, ToolError};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// What to do with a path that resolves outside every project root
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PathPolicy {
+    /// Resolve the path but mark it as outside the roots
+    #[default]
+    Flag,
+    /// Refuse to resolve the path
+    Deny,
+}
+
+/// A path resolved against a tool context
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedPath {
+    path: PathBuf,
+    outside_roots: bool,
+}
+
+impl ResolvedPath {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn into_path_buf(self) -> PathBuf {
+        self.path
+    }
+
+    /// Whether the path lies outside every project root
+    pub fn is_outside_roots(&self) -> bool {
+        self.outside_roots
+    }
+}
+
+/// Resolve `raw` against the context's working directory.
+///
+/// Relative paths are joined onto the working directory. Components are
+/// applied in order and every existing prefix is canonicalized, so symlinks
+/// (including `link/..` and links to files that do not exist yet) cannot be
+/// used to escape the roots; components that do not exist yet are kept as
+/// written.
+pub fn resolve_path(ctx: &ToolContext, raw: &str) -> Result<ResolvedPath, ToolError> {
+    if raw.trim().is_empty() {
+        return Err(ToolError::InvalidParameters("path must not be empty".to_string()));
+    }
+
+    let joined = ctx.working_dir().join(raw);
+    let Some(path) = canonicalize_lenient(&joined) else {
+        return Err(ToolError::InvalidParameters(format!(
+            "{}: too many levels of symbolic links",
+            raw
+        )));
+    };
+
+    let outside_roots = !ctx
+        .roots()
+        .iter()
+        .any(|root| path.starts_with(canonicalize_lenient(root).unwrap_or_else(|| root.clone())));
+
+    if outside_roots && ctx.path_policy() == PathPolicy::Deny {
+        return Err(ToolError::PermissionDenied(format!(
+            "{} is outside the project roots",
+            path.display()
+        )));
+    }
+
+    Ok(ResolvedPath { path, outside_roots })
+}
+
+/// Symlinks followed while resolving one path before giving up, as the
+/// kernel does
+const MAX_SYMLINKS: usize = 40;
+
+/// Resolve `path` one component at a time, canonicalizing after each step.
+///
+/// Every prefix that exists is replaced by its canonical form before the next
+/// component is applied, so `..` always pops a real directory rather than the
+/// name of a symlink. A dangling symlink is followed to its target, which is
+/// where a write through it would land. Prefixes that do not exist yet are
+/// kept as written. Returns `None` for a symlink loop.
+fn canonicalize_lenient(path: &Path) -> Option<PathBuf> {
+    let mut links = 0;
+    resolve_components(PathBuf::new(), path, &mut links)
+}
+
+fn resolve_components(mut result: PathBuf, path: &Path, links: &mut usize) -> Option<PathBuf> {
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                result.pop();
+            }
+            other => {
+                result.push(other.as_os_str());
+                if let Ok(canonical) = result.canonicalize() {
+                    result = canonical;
+                } else if let Ok(target) = fs::read_link(&result) {
+                    *links += 1;
+                    if *links > MAX_SYMLINKS {
+                        return None;
+                    }
+                    result.pop();
+                    result = resolve_components(result, &target, links)?;
+                }
+            }
+        }
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn jailed(dir: &Path) -> ToolContext {
+        ToolContext::new(dir.to_path_buf()).with_path_policy(PathPolicy::Deny)
+    }
+
+    #[test]
+    fn test_relative_path_uses_working_dir() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join("a.txt"), "a").unwrap();
+
+        let resolved = resolve_path(&jailed(temp.path()), "a.txt").unwrap();
+
+        assert_eq!(resolved.path(), temp.path().canonicalize().unwrap().join("a.txt"));
+        assert!(!resolved.is_outside_roots());
+    }
+
+    #[test]
+    fn test_nonexistent_path_is_normalized() {
+        let temp = TempDir::new().unwrap();
+
+        let resolved = resolve_path(&jailed(temp.path()), "./new/../dir/file.txt").unwrap();
+
+        assert_eq!(resolved.path(), temp.path().canonicalize().unwrap().join("dir/file.txt"));
+    }
+
+    #[test]
+    fn test_parent_escape_is_denied() {
+        let temp = TempDir::new().unwrap();
+        let project = temp.path().join("project");
+        fs::create_dir(&project).unwrap();
+
+        let result = resolve_path(&jailed(&project), "../secret.txt");
+
+        assert!(matches!(result, Err(ToolError::PermissionDenied(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_escape_is_denied() {
+        let temp = TempDir::new().unwrap();
+        let project = temp.path().join("project");
+        let outside = temp.path().join("outside");
+        fs::create_dir(&project).unwrap();
+        fs::create_dir(&outside).unwrap();
+        fs::write(outside.join("secret.txt"), "secret").unwrap();
+        std::os::unix::fs::symlink(&outside, project.join("link")).unwrap();
+
+        let result = resolve_path(&jailed(&project), "link/secret.txt");
+
+        assert!(matches!(result, Err(ToolError::PermissionDenied(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_parent_of_symlink_follows_the_target() {
+        let temp = TempDir::new().unwrap();
+        let project = temp.path().join("project");
+        let inner = temp.path().join("outside").join("inner");
+        fs::create_dir(&project).unwrap();
+        fs::create_dir_all(&inner).unwrap();
+        fs::write(temp.path().join("outside/secret.txt"), "secret").unwrap();
+        std::os::unix::fs::symlink(&inner, project.join("link")).unwrap();
+
+        let flagged = ToolContext::new(project.clone()).with_path_policy(PathPolicy::Flag);
+        let resolved = resolve_path(&flagged, "link/../secret.txt").unwrap();
+        assert_eq!(
+            resolved.path(),
+            temp.path().canonicalize().unwrap().join("outside/secret.txt")
+        );
+        assert!(resolved.is_outside_roots());
+
+        let result = resolve_path(&jailed(&project), "link/../secret.txt");
+        assert!(matches!(result, Err(ToolError::PermissionDenied(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_dangling_symlink_escape_is_denied() {
+        let temp = TempDir::new().unwrap();
+        let project = temp.path().join("project");
+        let outside = temp.path().join("outside");
+        fs::create_dir(&project).unwrap();
+        fs::create_dir(&outside).unwrap();
+        std::os::unix::fs::symlink(outside.join("new.txt"), project.join("link")).unwrap();
+        std::os::unix::fs::symlink("../outside/missing/new.txt", project.join("relative")).unwrap();
+
+        for raw in ["link", "relative"] {
+            let result = resolve_path(&jailed(&project), raw);
+            assert!(matches!(result, Err(ToolError::PermissionDenied(_))), "{}", raw);
+        }
+
+        let flagged = ToolContext::new(project.clone()).with_path_policy(PathPolicy::Flag);
+        let resolved = resolve_path(&flagged, "link").unwrap();
+        assert_eq!(resolved.path(), outside.canonicalize().unwrap().join("new.txt"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_loop_is_invalid() {
+        let temp = TempDir::new().unwrap();
+        std::os::unix::fs::symlink("b", temp.path().join("a")).unwrap();
+        std::os::unix::fs::symlink("a", temp.path().join("b")).unwrap();
+
+        let result = resolve_path(&jailed(temp.path()), "a/file.txt");
+
+        assert!(matches!(result, Err(ToolError::InvalidParameters(_))));
+    }
+
+    #[test]
+    fn test_flag_policy_marks_outside_paths() {
+        let temp = TempDir::new().unwrap();
+        let project = temp.path().join("project");
+        fs::create_dir(&project).unwrap();
+        let ctx = ToolContext::new(project).with_path_policy(PathPolicy::Flag);
+
+        let resolved = resolve_path(&ctx, "../other.txt").unwrap();
+
+        assert!(resolved.is_outside_roots());
+    }
+
+    #[test]
+    fn test_additional_root_is_allowed() {
+        let temp = TempDir::new().unwrap();
+        let project = temp.path().join("project");
+        let shared = temp.path().join("shared");
+        fs::create_dir(&project).unwrap();
+        fs::create_dir(&shared).unwrap();
+        let ctx = jailed(&project).with_root(shared.clone());
+
+        let resolved = resolve_path(&ctx, shared.join("lib.rs").to_str().unwrap()).unwrap();
+
+        assert!(!resolved.is_outside_roots());
+    }
+
+    #[test]
+    fn test_empty_path_is_invalid() {
+        let temp = TempDir::new().unwrap();
+        let result = resolve_path(&jailed(temp.path()), "");
+        assert!(matches!(result, Err(ToolError::InvalidParameters(_))));
+    }
+}
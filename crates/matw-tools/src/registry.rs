@@ -0,0 +1,290 @@
+//! Tool registry
+//!
+//! Holds the tools available to an agent, whether built in, provided by an MCP
+//! server or defined by the user. Tools are looked up by name, duplicate names
+//! are rejected, and an enable/disable filter decides which tools are offered
+//! to the model.
+
+use crate::{all_tools, Tool};
+use glob::Pattern;
+use matw_ai::ToolDefinition;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+/// Where a registered tool comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolSource {
+    BuiltIn,
+    /// Provided by the named MCP server
+    Mcp(String),
+    User,
+}
+
+impl fmt::Display for ToolSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToolSource::BuiltIn => write!(f, "built-in"),
+            ToolSource::Mcp(server) => write!(f, "MCP server {}", server),
+            ToolSource::User => write!(f, "user"),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RegistryError {
+    #[error("Tool {name} is already registered by {existing}")]
+    Duplicate { name: String, existing: ToolSource },
+}
+
+/// Which tools are offered to the model, as set in config.
+///
+/// Entries are tool names or glob patterns such as `mcp__github__*`. When
+/// `enabled` is set only matching tools are offered; `disabled` always wins.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolFilter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled: Vec<String>,
+}
+
+impl ToolFilter {
+    pub fn allows(&self, name: &str) -> bool {
+        let matches = |patterns: &[String]| {
+            patterns.iter().any(|p| {
+                Pattern::new(p)
+                    .map(|pattern| pattern.matches(name))
+                    .unwrap_or_else(|_| p == name)
+            })
+        };
+
+        if matches(&self.disabled) {
+            return false;
+        }
+        self.enabled.as_deref().is_none_or(matches)
+    }
+}
+
+struct Entry {
+    tool: Arc<dyn Tool>,
+    source: ToolSource,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: Vec<Entry>,
+    index: HashMap<String, usize>,
+    filter: ToolFilter,
+}
+
+/// Thread-safe set of named tools
+#[derive(Default)]
+pub struct ToolRegistry {
+    inner: RwLock<Inner>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a registry containing the built-in tools
+    pub fn with_builtins() -> Self {
+        let registry = Self::new();
+        for tool in all_tools() {
+            registry
+                .register(Arc::from(tool), ToolSource::BuiltIn)
+                .expect("built-in tool names are unique");
+        }
+        registry
+    }
+
+    /// Create a registry from user-supplied tools
+    pub fn from_tools(tools: Vec<Arc<dyn Tool>>) -> Result<Self, RegistryError> {
+        let registry = Self::new();
+        for tool in tools {
+            registry.register(tool, ToolSource::User)?;
+        }
+        Ok(registry)
+    }
+
+    /// Register a tool, failing if another tool already uses its name
+    pub fn register(&self, tool: Arc<dyn Tool>, source: ToolSource) -> Result<(), RegistryError> {
+        let mut inner = self.inner.write().unwrap();
+        let name = tool.name().to_string();
+
+        if let Some(&i) = inner.index.get(&name) {
+            return Err(RegistryError::Duplicate {
+                name,
+                existing: inner.entries[i].source.clone(),
+            });
+        }
+
+        let i = inner.entries.len();
+        inner.entries.push(Entry { tool, source });
+        inner.index.insert(name, i);
+        Ok(())
+    }
+
+    /// Remove a tool by name
+    pub fn unregister(&self, name: &str) -> Option<Arc<dyn Tool>> {
+        let mut inner = self.inner.write().unwrap();
+        let i = inner.index.remove(name)?;
+        let entry = inner.entries.remove(i);
+        for index in inner.index.values_mut() {
+            if *index > i {
+                *index -= 1;
+            }
+        }
+        Some(entry.tool)
+    }
+
+    /// Remove every tool from the given source, returning how many were removed
+    pub fn unregister_source(&self, source: &ToolSource) -> usize {
+        let names: Vec<String> = {
+            let inner = self.inner.read().unwrap();
+            inner
+                .entries
+                .iter()
+                .filter(|e| &e.source == source)
+                .map(|e| e.tool.name().to_string())
+                .collect()
+        };
+        for name in &names {
+            self.unregister(name);
+        }
+        names.len()
+    }
+
+    /// Replace the enable/disable filter
+    pub fn set_filter(&self, filter: ToolFilter) {
+        self.inner.write().unwrap().filter = filter;
+    }
+
+    /// Look up an enabled tool by name
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Tool>> {
+        let inner = self.inner.read().unwrap();
+        if !inner.filter.allows(name) {
+            return None;
+        }
+        inner.index.get(name).map(|&i| inner.entries[i].tool.clone())
+    }
+
+    /// Whether a tool with this name is registered, enabled or not
+    pub fn contains(&self, name: &str) -> bool {
+        self.inner.read().unwrap().index.contains_key(name)
+    }
+
+    pub fn source(&self, name: &str) -> Option<ToolSource> {
+        let inner = self.inner.read().unwrap();
+        inner.index.get(name).map(|&i| inner.entries[i].source.clone())
+    }
+
+    /// Enabled tools in registration order
+    pub fn tools(&self) -> Vec<Arc<dyn Tool>> {
+        let inner = self.inner.read().unwrap();
+        inner
+            .entries
+            .iter()
+            .filter(|e| inner.filter.allows(e.tool.name()))
+            .map(|e| e.tool.clone())
+            .collect()
+    }
+
+    /// Names of enabled tools in registration order
+    pub fn names(&self) -> Vec<String> {
+        self.tools().iter().map(|t| t.name().to_string()).collect()
+    }
+
+    /// Definitions of the enabled tools, for sending to a provider
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        self.tools()
+            .iter()
+            .map(|t| ToolDefinition {
+                name: t.name().to_string(),
+                description: t.description().to_string(),
+                parameters: t.parameters_schema(),
+            })
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.read().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl fmt::Debug for ToolRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.inner.read().unwrap();
+        f.debug_struct("ToolRegistry")
+            .field("tools", &inner.entries.iter().map(|e| e.tool.name()).collect::<Vec<_>>())
+            .field("filter", &inner.filter)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{BashTool, ReadTool};
+
+    #[test]
+    fn test_builtins_registered() {
+        let registry = ToolRegistry::with_builtins();
+        assert_eq!(registry.names(), vec!["bash", "glob", "read", "todo_write", "write"]);
+        assert_eq!(registry.source("read"), Some(ToolSource::BuiltIn));
+    }
+
+    #[test]
+    fn test_duplicate_name_rejected() {
+        let registry = ToolRegistry::with_builtins();
+        let err = registry
+            .register(Arc::new(ReadTool::new()), ToolSource::Mcp("files".to_string()))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Tool read is already registered by built-in");
+    }
+
+    #[test]
+    fn test_filter_disables_tools() {
+        let registry = ToolRegistry::with_builtins();
+        registry.set_filter(ToolFilter {
+            enabled: None,
+            disabled: vec!["bash".to_string()],
+        });
+
+        assert!(registry.get("bash").is_none());
+        assert!(registry.contains("bash"));
+        assert_eq!(registry.definitions().len(), registry.len() - 1);
+    }
+
+    #[test]
+    fn test_filter_enabled_patterns() {
+        let filter = ToolFilter {
+            enabled: Some(vec!["read".to_string(), "mcp__docs__*".to_string()]),
+            disabled: vec!["mcp__docs__delete".to_string()],
+        };
+        assert!(filter.allows("read"));
+        assert!(filter.allows("mcp__docs__search"));
+        assert!(!filter.allows("mcp__docs__delete"));
+        assert!(!filter.allows("bash"));
+    }
+
+    #[test]
+    fn test_unregister_source() {
+        let registry = ToolRegistry::new();
+        registry.register(Arc::new(BashTool::new()), ToolSource::User).unwrap();
+        registry
+            .register(Arc::new(ReadTool::new()), ToolSource::Mcp("files".to_string()))
+            .unwrap();
+
+        assert_eq!(registry.unregister_source(&ToolSource::Mcp("files".to_string())), 1);
+        assert_eq!(registry.names(), vec!["bash"]);
+        assert!(registry.get("bash").is_some());
+    }
+}
@@ -0,0 +1,376 @@
+//! Tool input validation against `Tool::parameters_schema`
+//!
+//! Implements the subset of JSON Schema used by tool parameter schemas:
+//! `type`, `properties`, `required`, `additionalProperties`, `items`, `enum`,
+//! `const`, `anyOf`, `oneOf`, string/array length bounds and numeric bounds.
+//! Unknown keywords are ignored.
+
+use crate::ToolError;
+use serde_json::{Map, Value};
+
+/// A single validation failure, naming the offending field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// Location of the value, e.g. `todos[1].status`; empty for the input itself
+    pub path: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "input: {}", self.message)
+        } else {
+            write!(f, "`{}`: {}", self.path, self.message)
+        }
+    }
+}
+
+/// Validate `input` against `schema`, returning every violation found
+pub fn validate(schema: &Value, input: &Value) -> Result<(), Vec<SchemaViolation>> {
+    validate_at(schema, input, "")
+}
+
+/// Validate tool input, reporting violations as `ToolError::InvalidParameters`
+pub fn validate_input(schema: &Value, input: &Value) -> Result<(), ToolError> {
+    validate(schema, input).map_err(|violations| {
+        let messages: Vec<String> = violations.iter().map(ToString::to_string).collect();
+        ToolError::InvalidParameters(messages.join("; "))
+    })
+}
+
+fn check(schema: &Value, value: &Value, path: &str, out: &mut Vec<SchemaViolation>) {
+    let Some(schema) = schema.as_object() else {
+        // `true` / missing schemas accept everything; `false` accepts nothing
+        if schema == &Value::Bool(false) {
+            out.push(violation(path, "no value is allowed here"));
+        }
+        return;
+    };
+
+    if let Some(expected) = schema.get("type") {
+        if !type_matches(expected, value) {
+            out.push(violation(
+                path,
+                format!("expected {}, got {}", describe_type(expected), type_name(value)),
+            ));
+            // Further keywords would only repeat the type error
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            let options: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            out.push(violation(path, format!("must be one of {}, got {}", options.join(", "), value)));
+        }
+    }
+
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            out.push(violation(path, format!("must be {}, got {}", expected, value)));
+        }
+    }
+
+    check_alternatives(schema, value, path, out);
+
+    match value {
+        Value::Object(object) => check_object(schema, object, path, out),
+        Value::Array(items) => check_array(schema, items, path, out),
+        Value::String(s) => check_string(schema, s, path, out),
+        Value::Number(n) => {
+            if let Some(n) = n.as_f64() {
+                check_number(schema, n, path, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_alternatives(schema: &Map<String, Value>, value: &Value, path: &str, out: &mut Vec<SchemaViolation>) {
+    let passes = |option: &Value| validate_at(option, value, path).is_ok();
+
+    if let Some(options) = schema.get("anyOf").and_then(Value::as_array) {
+        if !options.iter().any(passes) {
+            out.push(violation(path, "does not match any of the allowed schemas"));
+        }
+    }
+
+    if let Some(options) = schema.get("oneOf").and_then(Value::as_array) {
+        let matched = options.iter().filter(|o| passes(o)).count();
+        if matched != 1 {
+            out.push(violation(
+                path,
+                format!("must match exactly one of the allowed schemas, matched {}", matched),
+            ));
+        }
+    }
+}
+
+fn check_object(schema: &Map<String, Value>, object: &Map<String, Value>, path: &str, out: &mut Vec<SchemaViolation>) {
+    let properties = schema.get("properties").and_then(Value::as_object);
+
+    if let Some(required) = schema.get("required").and_then(Value::as_array) {
+        for name in required.iter().filter_map(Value::as_str) {
+            if !object.contains_key(name) {
+                out.push(violation(&join(path, name), "is required"));
+            }
+        }
+    }
+
+    for (name, value) in object {
+        let field = join(path, name);
+        match properties.and_then(|p| p.get(name)) {
+            Some(property) => check(property, value, &field, out),
+            None => match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) => {
+                    let known: Vec<&str> = properties
+                        .map(|p| p.keys().map(String::as_str).collect())
+                        .unwrap_or_default();
+                    out.push(violation(
+                        &field,
+                        format!("is not an allowed property (expected one of: {})", known.join(", ")),
+                    ));
+                }
+                Some(additional @ Value::Object(_)) => check(additional, value, &field, out),
+                _ => {}
+            },
+        }
+    }
+}
+
+fn check_array(schema: &Map<String, Value>, items: &[Value], path: &str, out: &mut Vec<SchemaViolation>) {
+    if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+        if (items.len() as u64) < min {
+            out.push(violation(path, format!("must contain at least {} items, got {}", min, items.len())));
+        }
+    }
+
+    if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+        if items.len() as u64 > max {
+            out.push(violation(path, format!("must contain at most {} items, got {}", max, items.len())));
+        }
+    }
+
+    if let Some(item_schema) = schema.get("items") {
+        for (i, item) in items.iter().enumerate() {
+            check(item_schema, item, &format!("{}[{}]", path, i), out);
+        }
+    }
+}
+
+fn check_string(schema: &Map<String, Value>, s: &str, path: &str, out: &mut Vec<SchemaViolation>) {
+    let len = s.chars().count() as u64;
+
+    if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+        if len < min {
+            let message = if min == 1 {
+                "must not be empty".to_string()
+            } else {
+                format!("must be at least {} characters long", min)
+            };
+            out.push(violation(path, message));
+        }
+    }
+
+    if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+        if len > max {
+            out.push(violation(path, format!("must be at most {} characters long", max)));
+        }
+    }
+}
+
+fn check_number(schema: &Map<String, Value>, n: f64, path: &str, out: &mut Vec<SchemaViolation>) {
+    if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+        if n < min {
+            out.push(violation(path, format!("must be at least {}, got {}", min, n)));
+        }
+    }
+
+    if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+        if n > max {
+            out.push(violation(path, format!("must be at most {}, got {}", max, n)));
+        }
+    }
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str) -> Result<(), Vec<SchemaViolation>> {
+    let mut violations = Vec::new();
+    check(schema, value, path, &mut violations);
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(violations)
+    }
+}
+
+fn type_matches(expected: &Value, value: &Value) -> bool {
+    match expected {
+        Value::String(name) => single_type_matches(name, value),
+        Value::Array(names) => names
+            .iter()
+            .filter_map(Value::as_str)
+            .any(|name| single_type_matches(name, value)),
+        _ => true,
+    }
+}
+
+fn single_type_matches(name: &str, value: &Value) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|f| f.fract() == 0.0),
+        _ => true,
+    }
+}
+
+fn describe_type(expected: &Value) -> String {
+    match expected {
+        Value::Array(names) => {
+            let names: Vec<&str> = names.iter().filter_map(Value::as_str).collect();
+            names.join(" or ")
+        }
+        other => other.as_str().unwrap_or("a valid type").to_string(),
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn join(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
+
+fn violation(path: &str, message: impl Into<String>) -> SchemaViolation {
+    SchemaViolation {
+        path: path.to_string(),
+        message: message.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn messages(schema: Value, input: Value) -> Vec<String> {
+        match validate(&schema, &input) {
+            Ok(()) => vec![],
+            Err(violations) => violations.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    #[test]
+    fn test_valid_input() {
+        let schema = json!({
+            "type": "object",
+            "properties": {"path": {"type": "string"}},
+            "required": ["path"]
+        });
+        assert!(validate(&schema, &json!({"path": "a.txt"})).is_ok());
+    }
+
+    #[test]
+    fn test_missing_required_field() {
+        let schema = json!({"type": "object", "required": ["pattern"]});
+        assert_eq!(messages(schema, json!({})), vec!["`pattern`: is required"]);
+    }
+
+    #[test]
+    fn test_wrong_type_names_field() {
+        let schema = json!({
+            "type": "object",
+            "properties": {"timeout_ms": {"type": "integer"}}
+        });
+        assert_eq!(
+            messages(schema, json!({"timeout_ms": "100"})),
+            vec!["`timeout_ms`: expected integer, got string"]
+        );
+    }
+
+    #[test]
+    fn test_input_must_be_object() {
+        let schema = json!({"type": "object"});
+        assert_eq!(messages(schema, json!("a.txt")), vec!["input: expected object, got string"]);
+    }
+
+    #[test]
+    fn test_nested_array_items() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "todos": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {"status": {"type": "string", "enum": ["pending", "completed"]}}
+                    }
+                }
+            }
+        });
+        let input = json!({"todos": [{"status": "pending"}, {"status": "done"}]});
+        assert_eq!(
+            messages(schema, input),
+            vec![r#"`todos[1].status`: must be one of "pending", "completed", got "done""#]
+        );
+    }
+
+    #[test]
+    fn test_additional_properties() {
+        let schema = json!({
+            "type": "object",
+            "properties": {"path": {"type": "string"}},
+            "additionalProperties": false
+        });
+        assert_eq!(
+            messages(schema, json!({"path": "a", "file": "b"})),
+            vec!["`file`: is not an allowed property (expected one of: path)"]
+        );
+    }
+
+    #[test]
+    fn test_bounds() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "pattern": {"type": "string", "minLength": 1},
+                "timeout_ms": {"type": "integer", "minimum": 1}
+            }
+        });
+        assert_eq!(
+            messages(schema, json!({"pattern": "", "timeout_ms": 0})),
+            vec!["`pattern`: must not be empty", "`timeout_ms`: must be at least 1, got 0"]
+        );
+    }
+
+    #[test]
+    fn test_any_of() {
+        let schema = json!({"anyOf": [{"type": "string"}, {"type": "integer"}]});
+        assert!(validate(&schema, &json!(3)).is_ok());
+        assert_eq!(messages(schema, json!(true)), vec!["input: does not match any of the allowed schemas"]);
+    }
+
+    #[test]
+    fn test_validate_input_error() {
+        let schema = json!({"type": "object", "required": ["path", "content"]});
+        let err = validate_input(&schema, &json!({})).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid parameters: `path`: is required; `content`: is required");
+    }
+}
@@ -0,0 +1,27 @@
+//! Session task list shared with tools
+//!
+//! `TodoWriteTool` replaces the list held here; the agent copies it into the
+//! session after each tool call.
+
+use matw_core::Todo;
+use std::sync::Mutex;
+
+/// Per-session task list
+#[derive(Debug, Default)]
+pub struct TodoStore {
+    todos: Mutex<Vec<Todo>>,
+}
+
+impl TodoStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self) -> Vec<Todo> {
+        self.todos.lock().unwrap().clone()
+    }
+
+    pub fn replace(&self, todos: Vec<Todo>) {
+        *self.todos.lock().unwrap() = todos;
+    }
+}
@@ -1,10 +1,14 @@
+use crate::ToolContext;
 use async_trait::async_trait;
+use matw_ai::Usage;
 use serde_json::Value;
 
 #[derive(Debug, Clone)]
 pub struct ToolOutput {
     pub content: String,
     pub is_error: bool,
+    /// Model usage spent by the tool itself, e.g. by a sub-agent
+    pub usage: Option<Usage>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -20,6 +24,15 @@ pub enum ToolError {
 
     #[error("Not found: {0}")]
     NotFound(String),
+
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
+    #[error("Cancelled")]
+    Cancelled,
+
+    #[error("File state error: {0}")]
+    FileState(String),
 }
 
 #[async_trait]
@@ -28,5 +41,21 @@ pub trait Tool: Send + Sync {
     fn description(&self) -> &str;
     fn parameters_schema(&self) -> Value;
 
-    async fn execute(&self, input: Value) -> Result<ToolOutput, ToolError>;
+    /// Check input against `parameters_schema` before `execute` is called
+    fn validate(&self, input: &Value) -> Result<(), ToolError> {
+        crate::schema::validate_input(&self.parameters_schema(), input)
+    }
+
+    /// Whether the tool only reads state, so calls may run concurrently with
+    /// each other and in read-only mode
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    /// Whether calls may run concurrently with other such calls
+    fn is_concurrency_safe(&self) -> bool {
+        self.is_read_only()
+    }
+
+    async fn execute(&self, input: Value, ctx: &ToolContext) -> Result<ToolOutput, ToolError>;
 }
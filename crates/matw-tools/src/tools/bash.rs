@@ -1,4 +1,4 @@
-use super::super::{Tool, ToolError, ToolOutput};
+use super::super::{Tool, ToolContext, ToolError, ToolOutput};
 use async_trait::async_trait;
 use serde::Deserialize;
 use serde_json::json;
@@ -44,6 +44,7 @@ impl Tool for BashTool {
                 },
                 "timeout_ms": {
                     "type": "integer",
+                    "minimum": 1,
                     "description": "Timeout in milliseconds (default: 120000)"
                 }
             },
@@ -51,43 +52,55 @@ impl Tool for BashTool {
         })
     }
 
-    async fn execute(&self, input: serde_json::Value) -> Result<ToolOutput, ToolError> {
+    async fn execute(&self, input: serde_json::Value, ctx: &ToolContext) -> Result<ToolOutput, ToolError> {
         let input: BashInput = serde_json::from_value(input)
             .map_err(|e| ToolError::InvalidParameters(e.to_string()))?;
 
+        ctx.ensure_writable("Running shell commands")?;
+
         let timeout_ms = input.timeout_ms.unwrap_or(120000);
 
-        // Execute command using tokio
-        let output = tokio::time::timeout(
-            std::time::Duration::from_millis(timeout_ms),
-            execute_command(&input.command)
-        )
-        .await
-        .map_err(|_| ToolError::ExecutionFailed("Command timed out".to_string()))??;
+        ctx.report_progress(&format!("Running: {}", input.command));
+
+        // Execute command using tokio, stopping early if the call is cancelled
+        let output = tokio::select! {
+            result = tokio::time::timeout(
+                std::time::Duration::from_millis(timeout_ms),
+                execute_command(&input.command, ctx)
+            ) => result.map_err(|_| ToolError::ExecutionFailed("Command timed out".to_string()))??,
+            _ = ctx.cancellation().cancelled() => return Err(ToolError::Cancelled),
+        };
 
         Ok(ToolOutput {
             content: output,
             is_error: false,
+            usage: None,
         })
     }
 }
 
-async fn execute_command(command: &str) -> Result<String, ToolError> {
+async fn execute_command(command: &str, ctx: &ToolContext) -> Result<String, ToolError> {
     use tokio::process::Command;
 
     // On Unix-like systems, use sh -c
     #[cfg(unix)]
-    let result = Command::new("sh")
-        .arg("-c")
-        .arg(command)
-        .output()
-        .await;
+    let mut cmd = Command::new("sh");
+    #[cfg(unix)]
+    cmd.arg("-c");
 
     // On Windows, use cmd /C
     #[cfg(windows)]
-    let result = Command::new("cmd")
-        .arg("/C")
+    let mut cmd = Command::new("cmd");
+    #[cfg(windows)]
+    cmd.arg("/C");
+
+    // Run in the session's working directory; the child is killed if the
+    // future is dropped on timeout or cancellation
+    let result = cmd
         .arg(command)
+        .current_dir(ctx.working_dir())
+        .envs(ctx.environment())
+        .kill_on_drop(true)
         .output()
         .await;
 
@@ -122,7 +135,7 @@ mod tests {
         let input = json!({
             "command": "echo hello world"
         });
-        let result = tool.execute(input).await.unwrap();
+        let result = tool.execute(input, &ToolContext::default()).await.unwrap();
 
         assert_eq!(result.content.trim(), "hello world");
         assert!(!result.is_error);
@@ -134,7 +147,7 @@ mod tests {
         let input = json!({
             "command": "exit 1"
         });
-        let result = tool.execute(input).await;
+        let result = tool.execute(input, &ToolContext::default()).await;
 
         assert!(result.is_err());
     }
@@ -146,9 +159,51 @@ mod tests {
             "command": "sleep 10",
             "timeout_ms": 100
         });
-        let result = tool.execute(input).await;
+        let result = tool.execute(input, &ToolContext::default()).await;
 
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("timed out"));
     }
+
+    async fn tool_output(input: serde_json::Value, ctx: &ToolContext) -> String {
+        BashTool::new().execute(input, ctx).await.unwrap().content
+    }
+
+    #[tokio::test]
+    async fn test_bash_runs_in_context_dir() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let ctx = ToolContext::new(temp.path().to_path_buf());
+        let result = tool_output(json!({"command": "pwd"}), &ctx).await;
+
+        let expected = temp.path().canonicalize().unwrap();
+        assert_eq!(std::path::Path::new(result.trim()).canonicalize().unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_bash_uses_context_environment() {
+        let env = [("MATW_TEST_VALUE".to_string(), "from-session".to_string())].into();
+        let ctx = ToolContext::default().with_environment(env);
+        let result = tool_output(json!({"command": "echo $MATW_TEST_VALUE"}), &ctx).await;
+
+        assert_eq!(result.trim(), "from-session");
+    }
+
+    #[tokio::test]
+    async fn test_bash_cancellation() {
+        let cancel = tokio_util::sync::CancellationToken::new();
+        let ctx = ToolContext::default().with_cancellation(cancel.clone());
+        cancel.cancel();
+
+        let result = BashTool::new().execute(json!({"command": "sleep 10"}), &ctx).await;
+
+        assert!(matches!(result, Err(ToolError::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn test_bash_denied_in_read_only_mode() {
+        let ctx = ToolContext::default().with_permission(crate::PermissionState::ReadOnly);
+        let result = BashTool::new().execute(json!({"command": "echo hi"}), &ctx).await;
+
+        assert!(matches!(result, Err(ToolError::PermissionDenied(_))));
+    }
 }
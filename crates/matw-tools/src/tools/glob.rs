@@ -1,6 +1,6 @@
-use super::super::{Tool, ToolError, ToolOutput};
+use super::super::{Tool, ToolContext, ToolError, ToolOutput};
 use async_trait::async_trait;
-use ignore::{Walk, WalkBuilder};
+use ignore::WalkBuilder;
 use serde::Deserialize;
 use serde_json::json;
 use glob::Pattern;
@@ -21,7 +21,6 @@ impl Default for GlobTool {
 
 #[derive(Debug, Deserialize)]
 struct GlobInput {
-    #[serde(default)]
     pattern: String,
     #[serde(default)]
     path: String,
@@ -43,61 +42,58 @@ impl Tool for GlobTool {
             "properties": {
                 "pattern": {
                     "type": "string",
+                    "minLength": 1,
                     "description": "Glob pattern (e.g., **/*.rs)"
                 },
                 "path": {
                     "type": "string",
-                    "description": "Root directory to search (defaults to current directory)"
+                    "description": "Root directory to search (defaults to the working directory)"
                 }
-            }
+            },
+            "required": ["pattern"]
         })
     }
 
-    async fn execute(&self, input: serde_json::Value) -> Result<ToolOutput, ToolError> {
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, input: serde_json::Value, ctx: &ToolContext) -> Result<ToolOutput, ToolError> {
         let input: GlobInput = serde_json::from_value(input)
             .map_err(|e| ToolError::InvalidParameters(e.to_string()))?;
 
         let search_path = if input.path.is_empty() {
-            "."
+            ctx.working_dir().to_path_buf()
         } else {
-            &input.path
+            ctx.resolve_path(&input.path)?.into_path_buf()
         };
 
+        if input.pattern.is_empty() {
+            return Err(ToolError::InvalidParameters("`pattern`: must not be empty".to_string()));
+        }
+
+        // Compile glob pattern
+        let pattern = Pattern::new(&input.pattern)
+            .map_err(|e| ToolError::InvalidParameters(format!("Invalid glob pattern: {}", e)))?;
+
         let mut results = Vec::new();
 
-        if !input.pattern.is_empty() {
-            // Compile glob pattern
-            let pattern = Pattern::new(&input.pattern)
-                .map_err(|e| ToolError::InvalidParameters(format!("Invalid glob pattern: {}", e)))?;
-
-            // Use gitignore-aware walk
-            let walker = WalkBuilder::new(search_path)
-                .git_ignore(true)
-                .git_global(true)
-                .build();
-
-            for entry in walker {
-                if let Ok(entry) = entry {
-                    if entry.file_type().map_or(false, |t| t.is_file()) {
-                        let path = entry.path();
-                        // Match against the pattern relative to search path
-                        let relative_path = path.strip_prefix(search_path)
-                            .unwrap_or(path)
-                            .to_string_lossy();
-
-                        if pattern.matches(&relative_path) {
-                            results.push(path.to_string_lossy().to_string());
-                        }
-                    }
-                }
-            }
-        } else {
-            // List all files if no pattern
-            for entry in Walk::new(search_path) {
-                if let Ok(entry) = entry {
-                    if entry.file_type().map_or(false, |t| t.is_file()) {
-                        results.push(entry.path().to_string_lossy().to_string());
-                    }
+        // Use gitignore-aware walk
+        let walker = WalkBuilder::new(&search_path)
+            .git_ignore(true)
+            .git_global(true)
+            .build();
+
+        for entry in walker.flatten() {
+            if entry.file_type().is_some_and(|t| t.is_file()) {
+                let path = entry.path();
+                // Match against the pattern relative to search path
+                let relative_path = path.strip_prefix(&search_path)
+                    .unwrap_or(path)
+                    .to_string_lossy();
+
+                if pattern.matches(&relative_path) {
+                    results.push(path.to_string_lossy().to_string());
                 }
             }
         }
@@ -111,6 +107,7 @@ impl Tool for GlobTool {
         Ok(ToolOutput {
             content: output,
             is_error: false,
+            usage: None,
         })
     }
 }
@@ -132,10 +129,17 @@ mod tests {
             "pattern": "**/*.rs",
             "path": temp.path().to_str().unwrap()
         });
-        let result = tool.execute(input).await.unwrap();
+        let result = tool.execute(input, &ToolContext::default()).await.unwrap();
 
         assert!(result.content.contains("test.rs"));
         assert!(result.content.contains("main.rs"));
         assert!(!result.content.contains("test.txt"));
     }
+
+    #[test]
+    fn test_glob_requires_pattern() {
+        let tool = GlobTool::new();
+        let err = tool.validate(&serde_json::json!({"path": "."})).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid parameters: `pattern`: is required");
+    }
 }
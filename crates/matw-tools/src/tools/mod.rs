@@ -2,11 +2,13 @@
 pub mod bash;
 pub mod glob;
 pub mod read;
+pub mod todo_write;
 pub mod write;
 
 pub use bash::BashTool;
 pub use glob::GlobTool;
 pub use read::ReadTool;
+pub use todo_write::TodoWriteTool;
 pub use write::WriteTool;
 
 /// Get all available tools
@@ -15,6 +17,7 @@ pub fn all_tools() -> Vec<Box<dyn crate::Tool>> {
         Box::new(BashTool::new()),
         Box::new(GlobTool::new()),
         Box::new(ReadTool::new()),
+        Box::new(TodoWriteTool::new()),
         Box::new(WriteTool::new()),
     ]
 }
@@ -1,9 +1,8 @@
-use super::super::{Tool, ToolError, ToolOutput};
+use super::super::{Tool, ToolContext, ToolError, ToolOutput};
 use async_trait::async_trait;
 use serde::Deserialize;
 use serde_json::json;
 use std::fs;
-use std::path::Path;
 
 pub struct ReadTool;
 
@@ -47,11 +46,16 @@ impl Tool for ReadTool {
         })
     }
 
-    async fn execute(&self, input: serde_json::Value) -> Result<ToolOutput, ToolError> {
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, input: serde_json::Value, ctx: &ToolContext) -> Result<ToolOutput, ToolError> {
         let input: ReadInput = serde_json::from_value(input)
             .map_err(|e| ToolError::InvalidParameters(e.to_string()))?;
 
-        let path = Path::new(&input.path);
+        let resolved = ctx.resolve_path(&input.path)?;
+        let path = resolved.path();
 
         if !path.exists() {
             return Err(ToolError::NotFound(input.path));
@@ -60,9 +64,12 @@ impl Tool for ReadTool {
         let content = fs::read_to_string(path)
             .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
 
+        ctx.file_state().record_read(path, content.as_bytes());
+
         Ok(ToolOutput {
             content,
             is_error: false,
+            usage: None,
         })
     }
 }
@@ -79,7 +86,7 @@ mod tests {
 
         let tool = ReadTool::new();
         let input = serde_json::json!({"path": file_path.to_str().unwrap()});
-        let result = tool.execute(input).await.unwrap();
+        let result = tool.execute(input, &ToolContext::default()).await.unwrap();
 
         assert_eq!(result.content, "hello world");
         assert!(!result.is_error);
@@ -89,11 +96,22 @@ mod tests {
     async fn test_read_nonexistent_file() {
         let tool = ReadTool::new();
         let input = serde_json::json!({"path": "/nonexistent/file.txt"});
-        let result = tool.execute(input).await;
+        let result = tool.execute(input, &ToolContext::default()).await;
 
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_read_relative_to_context() {
+        let temp = tempfile::TempDir::new().unwrap();
+        fs::write(temp.path().join("notes.md"), "relative").unwrap();
+
+        let ctx = ToolContext::new(temp.path().to_path_buf());
+        let result = ReadTool::new().execute(serde_json::json!({"path": "notes.md"}), &ctx).await.unwrap();
+
+        assert_eq!(result.content, "relative");
+    }
+
     #[tokio::test]
     async fn test_read_tool_name() {
         let tool = ReadTool::new();
@@ -0,0 +1,165 @@
+use super::super::{Tool, ToolContext, ToolError, ToolOutput};
+use async_trait::async_trait;
+use matw_core::{Todo, TodoStatus};
+use serde::Deserialize;
+use serde_json::json;
+
+/// Most tasks a list may hold
+const MAX_TODOS: usize = 50;
+
+pub struct TodoWriteTool;
+
+impl TodoWriteTool {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for TodoWriteTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TodoWriteInput {
+    todos: Vec<Todo>,
+}
+
+#[async_trait]
+impl Tool for TodoWriteTool {
+    fn name(&self) -> &str {
+        "todo_write"
+    }
+
+    fn description(&self) -> &str {
+        "Replace the session's task list to plan and track multi-step work\n\n\
+         Send the whole list every time. Mark a task in_progress before starting it and completed as soon as it \
+         is done; keep at most one task in progress."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "todos": {
+                    "type": "array",
+                    "maxItems": MAX_TODOS,
+                    "description": "The complete task list",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "content": {
+                                "type": "string",
+                                "minLength": 1,
+                                "description": "What the task is, in a few words"
+                            },
+                            "status": {
+                                "type": "string",
+                                "enum": ["pending", "in_progress", "completed"]
+                            }
+                        },
+                        "required": ["content", "status"]
+                    }
+                }
+            },
+            "required": ["todos"]
+        })
+    }
+
+    /// Only changes the session's task list, never the project
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, input: serde_json::Value, ctx: &ToolContext) -> Result<ToolOutput, ToolError> {
+        let input: TodoWriteInput = serde_json::from_value(input)
+            .map_err(|e| ToolError::InvalidParameters(e.to_string()))?;
+
+        let in_progress = input.todos.iter().filter(|t| t.status == TodoStatus::InProgress).count();
+        if in_progress > 1 {
+            return Err(ToolError::InvalidParameters(format!(
+                "{} tasks are in_progress; at most one may be",
+                in_progress
+            )));
+        }
+        if let Some(empty) = input.todos.iter().position(|t| t.content.trim().is_empty()) {
+            return Err(ToolError::InvalidParameters(format!("task {} has no content", empty + 1)));
+        }
+
+        let content = summarize(&input.todos);
+        ctx.todos().replace(input.todos);
+
+        Ok(ToolOutput {
+            content,
+            is_error: false,
+            usage: None,
+        })
+    }
+}
+
+/// The list as the model sees it after an update
+fn summarize(todos: &[Todo]) -> String {
+    if todos.is_empty() {
+        return "Task list cleared".to_string();
+    }
+
+    let count = |status| todos.iter().filter(|t| t.status == status).count();
+    let mut lines = vec![format!(
+        "Task list updated: {} completed, {} in progress, {} pending",
+        count(TodoStatus::Completed),
+        count(TodoStatus::InProgress),
+        count(TodoStatus::Pending)
+    )];
+    for todo in todos {
+        let marker = match todo.status {
+            TodoStatus::Completed => "[x]",
+            TodoStatus::InProgress => "[>]",
+            TodoStatus::Pending => "[ ]",
+        };
+        lines.push(format!("{} {}", marker, todo.content));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_replaces_list() {
+        let tool = TodoWriteTool::new();
+        let ctx = ToolContext::default();
+        let input = json!({"todos": [
+            {"content": "Read the parser", "status": "completed"},
+            {"content": "Fix the bug", "status": "in_progress"},
+            {"content": "Add a test", "status": "pending"}
+        ]});
+        tool.validate(&input).unwrap();
+
+        let result = tool.execute(input, &ctx).await.unwrap();
+
+        assert_eq!(
+            result.content,
+            "Task list updated: 1 completed, 1 in progress, 1 pending\n\
+             [x] Read the parser\n[>] Fix the bug\n[ ] Add a test"
+        );
+        assert_eq!(ctx.todos().get()[1], Todo::new("Fix the bug", TodoStatus::InProgress));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_two_in_progress() {
+        let tool = TodoWriteTool::new();
+        let ctx = ToolContext::default();
+        ctx.todos().replace(vec![Todo::new("Keep me", TodoStatus::Pending)]);
+        let input = json!({"todos": [
+            {"content": "One", "status": "in_progress"},
+            {"content": "Two", "status": "in_progress"}
+        ]});
+
+        let err = tool.execute(input, &ctx).await.unwrap_err();
+
+        assert!(err.to_string().contains("at most one"));
+        assert_eq!(ctx.todos().get(), vec![Todo::new("Keep me", TodoStatus::Pending)]);
+    }
+}
@@ -1,9 +1,8 @@
-use super::super::{Tool, ToolError, ToolOutput};
+use super::super::{Tool, ToolContext, ToolError, ToolOutput};
 use async_trait::async_trait;
 use serde::Deserialize;
 use serde_json::json;
 use std::fs;
-use std::path::Path;
 
 pub struct WriteTool;
 
@@ -52,11 +51,21 @@ impl Tool for WriteTool {
         })
     }
 
-    async fn execute(&self, input: serde_json::Value) -> Result<ToolOutput, ToolError> {
+    async fn execute(&self, input: serde_json::Value, ctx: &ToolContext) -> Result<ToolOutput, ToolError> {
         let input: WriteInput = serde_json::from_value(input)
             .map_err(|e| ToolError::InvalidParameters(e.to_string()))?;
 
-        let path = Path::new(&input.path);
+        ctx.ensure_writable("Writing files")?;
+
+        let resolved = ctx.resolve_path(&input.path)?;
+        let path = resolved.path();
+
+        // Existing files must have been read, and not changed since
+        ctx.file_state().check_writable(path)?;
+
+        ctx.checkpoints()
+            .record(path)
+            .map_err(|e| ToolError::ExecutionFailed(format!("Failed to back up {}: {}", input.path, e)))?;
 
         // Create parent directories if they don't exist
         if let Some(parent) = path.parent() {
@@ -67,9 +76,12 @@ impl Tool for WriteTool {
         fs::write(path, &input.content)
             .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
 
+        ctx.file_state().record_write(path, input.content.as_bytes());
+
         Ok(ToolOutput {
             content: format!("Wrote {} bytes to {}", input.content.len(), input.path),
             is_error: false,
+            usage: None,
         })
     }
 }
@@ -88,7 +100,7 @@ mod tests {
             "path": file_path.to_str().unwrap(),
             "content": "hello world"
         });
-        let result = tool.execute(input).await.unwrap();
+        let result = tool.execute(input, &ToolContext::default()).await.unwrap();
 
         assert!(result.content.contains("Wrote"));
         assert!(!result.is_error);
@@ -107,9 +119,47 @@ mod tests {
             "path": file_path.to_str().unwrap(),
             "content": "content"
         });
-        let result = tool.execute(input).await.unwrap();
+        let result = tool.execute(input, &ToolContext::default()).await.unwrap();
 
         assert!(!result.is_error);
         assert!(file_path.exists());
     }
+
+    #[tokio::test]
+    async fn test_overwrite_requires_read() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let file_path = temp.path().join("existing.txt");
+        fs::write(&file_path, "original").unwrap();
+        let ctx = ToolContext::new(temp.path().to_path_buf());
+        let input = serde_json::json!({"path": "existing.txt", "content": "replaced"});
+
+        let result = WriteTool::new().execute(input.clone(), &ctx).await;
+        assert!(matches!(result, Err(ToolError::FileState(_))));
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "original");
+
+        crate::tools::ReadTool::new()
+            .execute(serde_json::json!({"path": "existing.txt"}), &ctx)
+            .await
+            .unwrap();
+        WriteTool::new().execute(input, &ctx).await.unwrap();
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "replaced");
+    }
+
+    #[tokio::test]
+    async fn test_write_outside_roots_denied() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let project = temp.path().join("project");
+        fs::create_dir(&project).unwrap();
+
+        let ctx = ToolContext::new(project).with_path_policy(crate::PathPolicy::Deny);
+        let tool = WriteTool::new();
+        let input = serde_json::json!({
+            "path": "../escape.txt",
+            "content": "content"
+        });
+        let result = tool.execute(input, &ctx).await;
+
+        assert!(matches!(result, Err(ToolError::PermissionDenied(_))));
+        assert!(!temp.path().join("escape.txt").exists());
+    }
 }
@@ -1,9 +1,10 @@
-use matw_tools::{Tool, tools::{ReadTool, WriteTool, GlobTool, BashTool}};
+use matw_tools::{Tool, ToolContext, tools::{ReadTool, WriteTool, GlobTool, BashTool}};
 use tempfile::TempDir;
 use std::fs;
 
 #[tokio::test]
 async fn test_write_and_read_file() {
+    let ctx = ToolContext::default();
     let temp_dir = TempDir::new().unwrap();
     let file_path = temp_dir.path().join("test.txt");
 
@@ -13,7 +14,7 @@ async fn test_write_and_read_file() {
         "path": file_path.to_str().unwrap(),
         "content": "Hello, World!"
     });
-    let write_result = write_tool.execute(write_input).await.unwrap();
+    let write_result = write_tool.execute(write_input, &ctx).await.unwrap();
     assert!(!write_result.is_error);
     assert!(file_path.exists());
 
@@ -22,13 +23,14 @@ async fn test_write_and_read_file() {
     let read_input = serde_json::json!({
         "path": file_path.to_str().unwrap()
     });
-    let read_result = read_tool.execute(read_input).await.unwrap();
+    let read_result = read_tool.execute(read_input, &ctx).await.unwrap();
     assert!(!read_result.is_error);
     assert_eq!(read_result.content, "Hello, World!");
 }
 
 #[tokio::test]
 async fn test_nested_directory_creation() {
+    let ctx = ToolContext::default();
     let temp_dir = TempDir::new().unwrap();
     let nested_path = temp_dir.path().join("a/b/c/d/file.txt");
 
@@ -37,7 +39,7 @@ async fn test_nested_directory_creation() {
         "path": nested_path.to_str().unwrap(),
         "content": "nested content"
     });
-    let result = write_tool.execute(input).await.unwrap();
+    let result = write_tool.execute(input, &ctx).await.unwrap();
 
     assert!(!result.is_error);
     assert!(nested_path.exists());
@@ -46,6 +48,7 @@ async fn test_nested_directory_creation() {
 
 #[tokio::test]
 async fn test_glob_with_real_files() {
+    let ctx = ToolContext::default();
     let temp_dir = TempDir::new().unwrap();
 
     // Create test files
@@ -60,7 +63,7 @@ async fn test_glob_with_real_files() {
         "pattern": "*.rs",
         "path": temp_dir.path().to_str().unwrap()
     });
-    let result = glob_tool.execute(input).await.unwrap();
+    let result = glob_tool.execute(input, &ctx).await.unwrap();
 
     assert!(!result.is_error);
     assert!(result.content.contains("main.rs"));
@@ -71,13 +74,14 @@ async fn test_glob_with_real_files() {
 
 #[tokio::test]
 async fn test_bash_command_chain() {
+    let ctx = ToolContext::default();
     let bash_tool = BashTool::new();
 
     // Test command chaining with pipe
     let input = serde_json::json!({
         "command": "echo \"hello world\" | tr a-z A-Z"
     });
-    let result = bash_tool.execute(input).await.unwrap();
+    let result = bash_tool.execute(input, &ctx).await.unwrap();
 
     assert!(!result.is_error);
     assert!(result.content.contains("HELLO WORLD"));
@@ -85,6 +89,7 @@ async fn test_bash_command_chain() {
 
 #[tokio::test]
 async fn test_tool_error_handling() {
+    let ctx = ToolContext::default();
     let temp_dir = TempDir::new().unwrap();
     let nonexistent = temp_dir.path().join("does_not_exist.txt");
 
@@ -93,7 +98,7 @@ async fn test_tool_error_handling() {
     let input = serde_json::json!({
         "path": nonexistent.to_str().unwrap()
     });
-    let result = read_tool.execute(input).await;
+    let result = read_tool.execute(input, &ctx).await;
 
     assert!(result.is_err());
     assert!(result.unwrap_err().to_string().contains("Not found"));
@@ -101,6 +106,7 @@ async fn test_tool_error_handling() {
 
 #[tokio::test]
 async fn test_glob_recursive_pattern() {
+    let ctx = ToolContext::default();
     let temp_dir = TempDir::new().unwrap();
 
     // Create nested structure
@@ -115,7 +121,7 @@ async fn test_glob_recursive_pattern() {
         "pattern": "**/*.rs",
         "path": temp_dir.path().to_str().unwrap()
     });
-    let result = glob_tool.execute(input).await.unwrap();
+    let result = glob_tool.execute(input, &ctx).await.unwrap();
 
     assert!(!result.is_error);
     // Should find all three .rs files
@@ -125,6 +131,7 @@ async fn test_glob_recursive_pattern() {
 
 #[tokio::test]
 async fn test_bash_timeout_enforcement() {
+    let ctx = ToolContext::default();
     let bash_tool = BashTool::new();
 
     // Command that sleeps longer than timeout
@@ -132,7 +139,7 @@ async fn test_bash_timeout_enforcement() {
         "command": "sleep 5",
         "timeout_ms": 100
     });
-    let result = bash_tool.execute(input).await;
+    let result = bash_tool.execute(input, &ctx).await;
 
     assert!(result.is_err());
     assert!(result.unwrap_err().to_string().contains("timed out"));
@@ -140,6 +147,7 @@ async fn test_bash_timeout_enforcement() {
 
 #[tokio::test]
 async fn test_workflow_edit_file() {
+    let ctx = ToolContext::default();
     let temp_dir = TempDir::new().unwrap();
     let file_path = temp_dir.path().join("config.toml");
 
@@ -148,13 +156,13 @@ async fn test_workflow_edit_file() {
     write_tool.execute(serde_json::json!({
         "path": file_path.to_str().unwrap(),
         "content": "name = \"old\"\nversion = \"1.0\""
-    })).await.unwrap();
+    }), &ctx).await.unwrap();
 
     // Read it back
     let read_tool = ReadTool::new();
     let read_result = read_tool.execute(serde_json::json!({
         "path": file_path.to_str().unwrap()
-    })).await.unwrap();
+    }), &ctx).await.unwrap();
 
     assert_eq!(read_result.content, "name = \"old\"\nversion = \"1.0\"");
 
@@ -162,12 +170,12 @@ async fn test_workflow_edit_file() {
     write_tool.execute(serde_json::json!({
         "path": file_path.to_str().unwrap(),
         "content": "name = \"new\"\nversion = \"2.0\""
-    })).await.unwrap();
+    }), &ctx).await.unwrap();
 
     // Verify update
     let updated = read_tool.execute(serde_json::json!({
         "path": file_path.to_str().unwrap()
-    })).await.unwrap();
+    }), &ctx).await.unwrap();
 
     assert_eq!(updated.content, "name = \"new\"\nversion = \"2.0\"");
 }
@@ -2,12 +2,33 @@
 //!
 //! Manages the application state including session, input, messages, and UI state.
 
-use matw_agent::Agent;
+use crate::approval::{PendingApproval, PendingPlan, PlanInput, TuiApprovalHandler, TuiPlanReviewer};
+use crate::mcp;
+use matw_agent::{
+    Agent, AgentError, AgentEvent, AgentObserver, ApprovalDecision, ApprovalRequest, PlanDecision, RewindMode,
+};
 use matw_ai::AIProvider;
 use matw_core::Message;
-use matw_core::Session;
-use matw_tools::Tool;
+use matw_core::{Content, Role, Session, SessionMode, Todo};
+use matw_mcp::{tool_name, McpManager};
+use matw_tools::ToolRegistry;
+use std::path::PathBuf;
 use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+/// Updates sent from a running agent turn to the UI
+pub enum AppUpdate {
+    /// The agent needs the user's decision on a tool call
+    ApprovalRequested(ApprovalRequest, oneshot::Sender<ApprovalDecision>),
+    /// The model proposed a plan for the user to review
+    PlanProposed(String, oneshot::Sender<PlanDecision>),
+    /// Progress of the running agent turn
+    Agent(AgentEvent),
+    /// The agent turn finished with the updated session
+    TurnFinished(Box<Session>, Result<(), AgentError>),
+}
 
 /// Main application state
 pub struct App<P: AIProvider> {
@@ -20,33 +41,109 @@ pub struct App<P: AIProvider> {
     /// Whether the app should quit
     pub should_quit: bool,
     /// Available tools
-    pub tools: Vec<Arc<dyn Tool>>,
+    pub tools: Arc<ToolRegistry>,
     /// Current status message
     pub status: String,
     /// Optional agent for AI processing
-    pub agent: Option<Agent<P>>,
+    pub agent: Option<Arc<Agent<P>>>,
+    /// MCP servers whose resources and prompts the user can use
+    pub mcp: Option<Arc<McpManager>>,
+    /// Tool call waiting for approval
+    pub pending_approval: Option<PendingApproval>,
+    /// Plan waiting for review
+    pub pending_plan: Option<PendingPlan>,
+    /// Whether an agent turn is running
+    pub busy: bool,
+    /// Text streamed so far during the running turn
+    pub streaming: String,
+    /// The session's task list, updated live during a turn
+    pub todos: Vec<Todo>,
+    /// Where the session is saved after each turn
+    pub sessions_dir: Option<PathBuf>,
+    /// Cancels the running turn
+    turn_cancel: Option<CancellationToken>,
+    update_tx: mpsc::UnboundedSender<AppUpdate>,
+    update_rx: mpsc::UnboundedReceiver<AppUpdate>,
 }
 
 impl<P: AIProvider> App<P> {
-    /// Create a new application
-    pub fn new(session: Session, tools: Vec<Arc<dyn Tool>>) -> Self {
+    /// Create a new application, showing the conversation so far when the
+    /// session is resumed
+    pub fn new(session: Session, tools: Arc<ToolRegistry>) -> Self {
+        let (update_tx, update_rx) = mpsc::unbounded_channel();
+        let status = match session.context().instructions().len() {
+            0 => "Ready".to_string(),
+            1 => "Ready (1 instruction file loaded)".to_string(),
+            n => format!("Ready ({} instruction files loaded)", n),
+        };
+        let todos = session.context().todos().to_vec();
+        let messages = session.messages().to_vec();
         Self {
             session,
             input: String::new(),
-            messages: Vec::new(),
+            messages,
             should_quit: false,
             tools,
-            status: "Ready".to_string(),
+            status,
             agent: None,
+            mcp: None,
+            pending_approval: None,
+            pending_plan: None,
+            busy: false,
+            streaming: String::new(),
+            todos,
+            sessions_dir: None,
+            turn_cancel: None,
+            update_tx,
+            update_rx,
         }
     }
 
     /// Set the agent for AI processing
     pub fn with_agent(mut self, agent: Agent<P>) -> Self {
-        self.agent = Some(agent);
+        self.agent = Some(Arc::new(agent));
+        self
+    }
+
+    /// Offer the resources and prompts of these MCP servers
+    pub fn with_mcp(mut self, mcp: Arc<McpManager>) -> Self {
+        self.mcp = Some(mcp);
+        self
+    }
+
+    /// Save the session in `dir` after each turn
+    pub fn with_sessions_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.sessions_dir = Some(dir.into());
         self
     }
 
+    /// Create an approval handler that prompts in this app
+    pub fn approval_handler(&self) -> Arc<TuiApprovalHandler> {
+        Arc::new(TuiApprovalHandler::new(self.update_tx.clone()))
+    }
+
+    /// Create a plan reviewer that prompts in this app
+    pub fn plan_reviewer(&self) -> Arc<TuiPlanReviewer> {
+        Arc::new(TuiPlanReviewer::new(self.update_tx.clone()))
+    }
+
+    /// Switch plan mode on or off between turns
+    pub fn toggle_plan_mode(&mut self) {
+        if self.busy {
+            return;
+        }
+        let context = self.session.context_mut();
+        let mode = match context.mode() {
+            SessionMode::Normal => SessionMode::Plan,
+            SessionMode::Plan => SessionMode::Normal,
+        };
+        context.set_mode(mode);
+        self.status = match mode {
+            SessionMode::Plan => "Plan mode: read-only tools until you approve a plan".to_string(),
+            SessionMode::Normal => "Plan mode off".to_string(),
+        };
+    }
+
     /// Handle character input
     pub fn handle_input(&mut self, c: char) {
         self.input.push(c);
@@ -58,27 +155,423 @@ impl<P: AIProvider> App<P> {
     }
 
     /// Submit the current input
-    pub async fn submit_input(&mut self) {
-        if self.input.is_empty() {
+    pub async fn submit_input(&mut self)
+    where
+        P: 'static,
+    {
+        if self.input.is_empty() || self.busy {
+            return;
+        }
+
+        if let Some(args) = self.input.strip_prefix("/rewind") {
+            if args.is_empty() || args.starts_with(' ') {
+                let args = args.trim().to_string();
+                self.input.clear();
+                self.rewind_command(&args);
+                return;
+            }
+        }
+
+        match self.input.trim() {
+            "/resources" => {
+                self.input.clear();
+                self.list_resources().await;
+                return;
+            }
+            "/prompts" => {
+                self.input.clear();
+                self.list_prompts().await;
+                return;
+            }
+            _ => {}
+        }
+        if self.input.starts_with("/mcp__") {
+            let command = std::mem::take(&mut self.input);
+            if let Some(prompt) = self.run_prompt(&command).await {
+                self.start_turn(prompt);
+            }
             return;
         }
 
-        let msg = Message::new_user(self.input.clone());
-        self.messages.push(msg.clone());
-        self.session.add_message(msg);
-        self.input.clear();
+        let prompt = std::mem::take(&mut self.input);
+        match self.expand_mentions(&prompt).await {
+            Ok(prompt) => self.start_turn(prompt),
+            // Leave the prompt for the user to fix the mention
+            Err(status) => {
+                self.input = prompt;
+                self.status = status;
+            }
+        }
+    }
+
+    /// Send a prompt to the agent
+    fn start_turn(&mut self, prompt: String)
+    where
+        P: 'static,
+    {
+        self.messages.push(Message::new_user(prompt.clone()));
         self.status = "Processing...".to_string();
 
-        // Run agent if available
-        if let Some(ref agent) = self.agent {
-            if let Err(e) = agent.process(&mut self.session).await {
+        // Run the prompt hooks and the agent in the background so the UI can
+        // keep drawing and answer approval prompts while it works
+        let Some(agent) = &self.agent else {
+            self.session.add_message(Message::new_user(prompt));
+            return;
+        };
+        let agent = agent.clone();
+        let mut session = self.session.clone();
+        let updates = self.update_tx.clone();
+        let cancel = CancellationToken::new();
+        self.turn_cancel = Some(cancel.clone());
+        self.busy = true;
+
+        tokio::spawn(async move {
+            let result = match agent.submit_prompt(&mut session, prompt).await {
+                Ok(()) => agent.process_streaming(&mut session, &cancel).await,
+                Err(e) => Err(e),
+            };
+            updates.send(AppUpdate::TurnFinished(Box::new(session), result)).ok();
+        });
+    }
+
+    /// Append the contents of each `@server:uri` resource mentioned in the
+    /// prompt, or say which one could not be read
+    async fn expand_mentions(&self, prompt: &str) -> Result<String, String> {
+        let Some(servers) = &self.mcp else {
+            return Ok(prompt.to_string());
+        };
+        let mut expanded = prompt.to_string();
+        for (server, uri) in mcp::mentions(prompt, &servers.server_names()) {
+            let contents = servers
+                .read_resource(&server, &uri)
+                .await
+                .map_err(|e| format!("Could not read @{}:{}: {}", server, uri, e))?;
+            expanded.push_str("\n\n");
+            expanded.push_str(&mcp::render_resource(&server, &uri, &contents));
+        }
+        Ok(expanded)
+    }
+
+    /// `/resources` lists what can be mentioned
+    async fn list_resources(&mut self) {
+        let Some(servers) = &self.mcp else {
+            self.status = "No MCP servers".to_string();
+            return;
+        };
+        let resources = servers.resources().await;
+        let mut listing = vec!["Mention a resource in a prompt with @server:uri:".to_string()];
+        for (server, resource) in &resources {
+            listing.push(format!("@{}:{} ({})", server, resource.uri, resource.name));
+        }
+        self.messages.push(Message::new_system(listing.join("\n")));
+        self.status = format!("{} MCP resources", resources.len());
+    }
+
+    /// `/prompts` lists the prompts that can be run
+    async fn list_prompts(&mut self) {
+        let Some(servers) = &self.mcp else {
+            self.status = "No MCP servers".to_string();
+            return;
+        };
+        let prompts = servers.prompts().await;
+        let mut listing = vec!["Run a prompt with its command:".to_string()];
+        for (server, prompt) in &prompts {
+            let usage = mcp::prompt_usage(&tool_name(server, &prompt.name), &prompt.arguments);
+            match &prompt.description {
+                Some(description) => listing.push(format!("{} - {}", usage, description)),
+                None => listing.push(usage),
+            }
+        }
+        self.messages.push(Message::new_system(listing.join("\n")));
+        self.status = format!("{} MCP prompts", prompts.len());
+    }
+
+    /// `/mcp__<server>__<prompt> [args...]` fills in a server's prompt,
+    /// returning the text to send
+    async fn run_prompt(&mut self, command: &str) -> Option<String> {
+        let (name, args) = command[1..].split_once(char::is_whitespace).unwrap_or((&command[1..], ""));
+        let Some(servers) = &self.mcp else {
+            self.status = format!("Unknown command /{}", name);
+            return None;
+        };
+        let Some((server, prompt)) = servers
+            .prompts()
+            .await
+            .into_iter()
+            .find(|(server, prompt)| tool_name(server, &prompt.name) == name)
+        else {
+            self.status = format!("Unknown command /{}; /prompts lists them", name);
+            return None;
+        };
+
+        let Some(arguments) = mcp::prompt_arguments(&prompt.arguments, args) else {
+            self.status = format!("Usage: {}", mcp::prompt_usage(name, &prompt.arguments));
+            self.input = command.to_string();
+            return None;
+        };
+        match servers.get_prompt(&server, &prompt.name, &arguments).await {
+            Ok(result) => Some(mcp::prompt_text(&result)),
+            Err(e) => {
                 self.status = format!("Error: {}", e);
-                self.messages.push(Message::new_assistant(format!("Error: {}", e)));
-            } else {
-                self.status = "Ready".to_string();
-                // Update messages from session
+                self.input = command.to_string();
+                None
+            }
+        }
+    }
+
+    /// Create an observer that forwards agent events to this app
+    pub fn event_observer(&self) -> Arc<dyn AgentObserver> {
+        let sender = self.update_tx.clone();
+        Arc::new(move |event: &AgentEvent| {
+            sender.send(AppUpdate::Agent(event.clone())).ok();
+        })
+    }
+
+    /// Wait for the next update from a running agent turn
+    pub async fn next_update(&mut self) -> Option<AppUpdate> {
+        self.update_rx.recv().await
+    }
+
+    /// Apply an update from a running agent turn
+    pub fn handle_update(&mut self, update: AppUpdate) {
+        match update {
+            AppUpdate::ApprovalRequested(request, responder) => {
+                self.status = format!("Approve {}?", request.tool_name);
+                self.pending_approval = Some(PendingApproval::new(request, responder));
+            }
+            AppUpdate::PlanProposed(plan, responder) => {
+                self.status = "Review the plan".to_string();
+                self.pending_plan = Some(PendingPlan::new(plan, responder));
+            }
+            AppUpdate::Agent(event) => self.handle_agent_event(event),
+            AppUpdate::TurnFinished(session, result) => {
+                // A blocked prompt goes back to the input for editing
+                if let Err(AgentError::PromptBlocked(_)) = &result {
+                    if let Some(Content::Text(prompt)) = self.messages.last().map(|m| m.content()) {
+                        if self.input.is_empty() {
+                            self.input = prompt.clone();
+                        }
+                    }
+                }
+                self.streaming.clear();
+                self.session = *session;
                 self.messages = self.session.messages().to_vec();
+                self.todos = self.session.context().todos().to_vec();
+                self.busy = false;
+                self.turn_cancel = None;
+                self.pending_approval = None;
+                self.pending_plan = None;
+                match result {
+                    Ok(()) => self.status = "Ready".to_string(),
+                    Err(AgentError::Cancelled) => self.status = "Interrupted".to_string(),
+                    // Limits end a turn on purpose; report them without calling them errors
+                    Err(e @ AgentError::PromptBlocked(_)) => self.status = e.to_string(),
+                    Err(e @ AgentError::LimitReached(_)) => {
+                        self.status = e.to_string();
+                        self.messages.push(Message::new_assistant(e.to_string()));
+                    }
+                    Err(e) => {
+                        self.status = format!("Error: {}", e);
+                        self.messages.push(Message::new_assistant(format!("Error: {}", e)));
+                    }
+                }
+                self.save_session();
+            }
+        }
+    }
+
+    fn save_session(&mut self) {
+        let Some(dir) = &self.sessions_dir else {
+            return;
+        };
+        if let Err(e) = self.session.save(dir) {
+            self.messages.push(Message::new_system(format!("Could not save the session: {}", e)));
+        }
+    }
+
+    fn handle_agent_event(&mut self, event: AgentEvent) {
+        match event {
+            AgentEvent::TextDelta { text } => self.streaming.push_str(&text),
+            AgentEvent::ToolCallRequested { name, .. } => {
+                if !self.streaming.is_empty() && !self.streaming.ends_with('\n') {
+                    self.streaming.push('\n');
+                }
+                self.streaming.push_str(&format!("Using tool: {}\n", name));
+            }
+            AgentEvent::ToolStarted { name, .. } => self.status = format!("Running {}...", name),
+            AgentEvent::ToolProgress { message, .. } => self.status = message,
+            AgentEvent::ToolFinished { name, duration_ms, .. } => {
+                self.status = format!("{} finished in {} ms", name, duration_ms);
+            }
+            AgentEvent::TodosUpdated { todos } => self.todos = todos,
+            AgentEvent::ModeChanged { mode: SessionMode::Normal } => {
+                self.status = "Plan approved, carrying it out...".to_string();
+            }
+            AgentEvent::HookFailed { message, .. } => self.status = message,
+            AgentEvent::InstructionsLoaded { path } => {
+                self.status = format!("Loaded instructions from {}", path.display());
+            }
+            _ => {}
+        }
+    }
+
+    /// Cancel the running turn, returning whether there was one
+    pub fn cancel_turn(&mut self) -> bool {
+        let Some(cancel) = &self.turn_cancel else {
+            return false;
+        };
+        cancel.cancel();
+        // The agent drops its approval request when it unwinds
+        self.pending_approval = None;
+        self.status = "Interrupting...".to_string();
+        true
+    }
+
+    /// Answer the pending approval prompt
+    pub fn resolve_approval(&mut self, decision: ApprovalDecision) {
+        if let Some(pending) = self.pending_approval.take() {
+            pending.resolve(decision);
+            self.status = "Processing...".to_string();
+        }
+    }
+
+    /// Start typing feedback for denying the pending tool call
+    pub fn begin_denial_feedback(&mut self) {
+        if let Some(pending) = &mut self.pending_approval {
+            pending.entering_feedback = true;
+            self.input.clear();
+        }
+    }
+
+    /// Deny the pending tool call with the typed feedback
+    pub fn submit_denial_feedback(&mut self) {
+        let feedback = std::mem::take(&mut self.input);
+        let feedback = (!feedback.trim().is_empty()).then_some(feedback);
+        self.resolve_approval(ApprovalDecision::Deny { feedback });
+    }
+
+    /// Answer the pending plan review
+    pub fn resolve_plan(&mut self, decision: PlanDecision) {
+        if let Some(pending) = self.pending_plan.take() {
+            pending.resolve(decision);
+            self.input.clear();
+            self.status = "Processing...".to_string();
+        }
+    }
+
+    /// Start typing in the input box while reviewing a plan: the plan itself
+    /// when editing, feedback when rejecting
+    pub fn begin_plan_input(&mut self, input: PlanInput) {
+        if let Some(pending) = &mut self.pending_plan {
+            self.input = match input {
+                PlanInput::Editing => pending.plan.clone(),
+                PlanInput::None | PlanInput::Feedback => String::new(),
+            };
+            pending.input = input;
+        }
+    }
+
+    /// Approve the plan as edited, or reject it with the typed feedback
+    pub fn submit_plan_input(&mut self) {
+        let Some(input) = self.pending_plan.as_ref().map(|p| p.input) else {
+            return;
+        };
+        let text = std::mem::take(&mut self.input);
+        match input {
+            PlanInput::Editing if !text.trim().is_empty() => self.resolve_plan(PlanDecision::Approve { plan: text }),
+            PlanInput::Feedback => {
+                let feedback = (!text.trim().is_empty()).then_some(text);
+                self.resolve_plan(PlanDecision::Reject { feedback });
+            }
+            _ => {}
+        }
+    }
+
+    /// User prompts that can be rewound to, oldest first
+    fn rewind_points(&self) -> Vec<(Uuid, String)> {
+        self.session
+            .messages()
+            .iter()
+            .filter(|m| m.role() == Role::User)
+            .filter_map(|m| match m.content() {
+                Content::Text(text) => Some((m.id(), text.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// `/rewind` lists the user's prompts; `/rewind <n> [files|conversation]`
+    /// goes back to just before prompt n, restoring files and/or dropping
+    /// the conversation from there and putting the prompt back in the input
+    fn rewind_command(&mut self, args: &str) {
+        let points = self.rewind_points();
+        let mut args = args.split_whitespace();
+        let Some(choice) = args.next() else {
+            let mut listing = vec![
+                "Rewind to before a prompt with /rewind <n> [files|conversation]:".to_string(),
+                "File changes are only tracked for prompts sent since matw started.".to_string(),
+            ];
+            for (i, (id, text)) in points.iter().enumerate() {
+                let first_line = text.lines().next().unwrap_or_default();
+                let changed = match &self.agent {
+                    Some(agent) if agent.has_checkpoint(&self.session, *id) => {
+                        format!("{} files changed since", agent.changed_files(&self.session, *id).len())
+                    }
+                    _ => "files not tracked".to_string(),
+                };
+                listing.push(format!("{}. {} ({})", i + 1, first_line, changed));
+            }
+            self.messages.push(Message::new_system(listing.join("\n")));
+            self.status = format!("{} rewind points", points.len());
+            return;
+        };
+
+        let Some((id, text)) = choice
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .and_then(|i| points.get(i).cloned())
+        else {
+            self.status = format!("No prompt {}; /rewind lists them", choice);
+            return;
+        };
+        let mode = match args.next() {
+            None => RewindMode::Both,
+            Some("files") => RewindMode::Files,
+            Some("conversation") => RewindMode::Conversation,
+            Some(other) => {
+                self.status = format!("Unknown rewind mode {}; use files or conversation", other);
+                return;
+            }
+        };
+
+        let tracked = self.agent.as_ref().is_some_and(|agent| agent.has_checkpoint(&self.session, id));
+        let files = match &self.agent {
+            Some(agent) => match agent.rewind(&mut self.session, id, mode) {
+                Ok(rewound) => rewound.files.len(),
+                Err(e) => {
+                    self.status = format!("Error: {}", e);
+                    return;
+                }
+            },
+            None => {
+                if mode != RewindMode::Files {
+                    self.session.truncate_from(id);
+                }
+                0
             }
+        };
+        self.messages = self.session.messages().to_vec();
+        self.status = match mode {
+            RewindMode::Files => format!("Restored {} files", files),
+            RewindMode::Conversation => "Rewound the conversation; files unchanged".to_string(),
+            RewindMode::Both => format!("Rewound the conversation and restored {} files", files),
+        };
+        if mode != RewindMode::Conversation && !tracked {
+            self.status.push_str("; changes from before matw started are not restored");
+        }
+        if mode != RewindMode::Files {
+            self.input = text;
         }
     }
 
@@ -97,14 +590,13 @@ impl<P: AIProvider> App<P> {
 mod tests {
     use super::*;
     use matw_ai::providers::GLMProvider;
-    use matw_tools::tools::ReadTool;
     use tempfile::TempDir;
 
     #[test]
     fn test_app_creation() {
         let temp = TempDir::new().unwrap();
         let session = Session::new(temp.path().to_path_buf());
-        let tools: Vec<Arc<dyn Tool>> = vec![Arc::new(ReadTool::new())];
+        let tools = Arc::new(ToolRegistry::with_builtins());
 
         let app: App<GLMProvider> = App::new(session, tools);
 
@@ -118,7 +610,7 @@ mod tests {
     fn test_handle_input() {
         let temp = TempDir::new().unwrap();
         let session = Session::new(temp.path().to_path_buf());
-        let mut app: App<GLMProvider> = App::new(session, vec![]);
+        let mut app: App<GLMProvider> = App::new(session, Arc::new(ToolRegistry::new()));
 
         app.handle_input('h');
         app.handle_input('i');
@@ -131,7 +623,7 @@ mod tests {
     fn test_handle_backspace() {
         let temp = TempDir::new().unwrap();
         let session = Session::new(temp.path().to_path_buf());
-        let mut app: App<GLMProvider> = App::new(session, vec![]);
+        let mut app: App<GLMProvider> = App::new(session, Arc::new(ToolRegistry::new()));
 
         app.input = "hello".to_string();
         app.handle_backspace();
@@ -143,7 +635,7 @@ mod tests {
     async fn test_submit_input() {
         let temp = TempDir::new().unwrap();
         let session = Session::new(temp.path().to_path_buf());
-        let mut app: App<GLMProvider> = App::new(session, vec![]);
+        let mut app: App<GLMProvider> = App::new(session, Arc::new(ToolRegistry::new()));
 
         app.input = "test message".to_string();
         app.submit_input().await;
@@ -154,11 +646,131 @@ mod tests {
         assert_eq!(app.session.message_count(), 1);
     }
 
+    #[tokio::test]
+    async fn test_approval_prompt_round_trip() {
+        let temp = TempDir::new().unwrap();
+        let session = Session::new(temp.path().to_path_buf());
+        let mut app: App<GLMProvider> = App::new(session, Arc::new(ToolRegistry::new()));
+        let (responder, response) = oneshot::channel();
+        let request = ApprovalRequest {
+            tool_name: "bash".to_string(),
+            input: serde_json::json!({"command": "rm -rf target"}),
+            reason: "bash command `rm -rf target` requires approval".to_string(),
+        };
+
+        app.handle_update(AppUpdate::ApprovalRequested(request, responder));
+        assert!(app.pending_approval.is_some());
+
+        app.begin_denial_feedback();
+        app.input = "use cargo clean".to_string();
+        app.submit_denial_feedback();
+
+        assert!(app.pending_approval.is_none());
+        assert_eq!(
+            response.await.unwrap(),
+            ApprovalDecision::Deny {
+                feedback: Some("use cargo clean".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn test_cancel_turn() {
+        let temp = TempDir::new().unwrap();
+        let session = Session::new(temp.path().to_path_buf());
+        let mut app: App<GLMProvider> = App::new(session.clone(), Arc::new(ToolRegistry::new()));
+        assert!(!app.cancel_turn());
+
+        let cancel = CancellationToken::new();
+        app.turn_cancel = Some(cancel.clone());
+        app.busy = true;
+        assert!(app.cancel_turn());
+        assert!(cancel.is_cancelled());
+
+        app.handle_update(AppUpdate::TurnFinished(Box::new(session), Err(AgentError::Cancelled)));
+        assert!(!app.busy);
+        assert_eq!(app.status, "Interrupted");
+        assert!(app.messages.is_empty());
+    }
+
+    #[test]
+    fn test_turn_saves_session() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path().join("sessions");
+        let mut session = Session::new(temp.path().to_path_buf());
+        session.add_message(Message::new_user("hi".to_string()));
+        session.context_mut().set_mode(SessionMode::Plan);
+        let id = session.id();
+        let mut app: App<GLMProvider> =
+            App::new(session.clone(), Arc::new(ToolRegistry::new())).with_sessions_dir(&dir);
+
+        app.handle_update(AppUpdate::TurnFinished(Box::new(session), Ok(())));
+        let saved = Session::load_from(&dir, id).unwrap();
+        assert_eq!(saved.message_count(), 1);
+        assert_eq!(saved.context().mode(), SessionMode::Plan);
+    }
+
+    #[test]
+    fn test_resumed_session_shows_conversation() {
+        let temp = TempDir::new().unwrap();
+        let mut session = Session::new(temp.path().to_path_buf());
+        session.add_message(Message::new_user("plan the refactor".to_string()));
+        session.add_message(Message::new_assistant("Here is the plan.".to_string()));
+
+        let app: App<GLMProvider> = App::new(session.clone(), Arc::new(ToolRegistry::new()));
+
+        let contents: Vec<&Content> = app.messages.iter().map(|m| m.content()).collect();
+        assert_eq!(
+            contents,
+            vec![
+                &Content::Text("plan the refactor".to_string()),
+                &Content::Text("Here is the plan.".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_turn_finished_replaces_session() {
+        let temp = TempDir::new().unwrap();
+        let session = Session::new(temp.path().to_path_buf());
+        let mut app: App<GLMProvider> = App::new(session, Arc::new(ToolRegistry::new()));
+        app.busy = true;
+        app.handle_update(AppUpdate::Agent(AgentEvent::TextDelta { text: "hel".to_string() }));
+        app.handle_update(AppUpdate::Agent(AgentEvent::TextDelta { text: "lo".to_string() }));
+        assert_eq!(app.streaming, "hello");
+
+        let mut finished = Session::new(temp.path().to_path_buf());
+        finished.add_message(Message::new_user("hi".to_string()));
+        finished.add_message(Message::new_assistant("hello".to_string()));
+        app.handle_update(AppUpdate::TurnFinished(Box::new(finished), Ok(())));
+
+        assert!(!app.busy);
+        assert_eq!(app.status, "Ready");
+        assert_eq!(app.messages.len(), 2);
+        assert!(app.streaming.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_mcp_commands_without_servers() {
+        let temp = TempDir::new().unwrap();
+        let session = Session::new(temp.path().to_path_buf());
+        let mut app: App<GLMProvider> = App::new(session, Arc::new(ToolRegistry::new()));
+
+        app.input = "/prompts".to_string();
+        app.submit_input().await;
+        assert_eq!(app.status, "No MCP servers");
+
+        app.input = "/mcp__docs__review main.rs".to_string();
+        app.submit_input().await;
+        assert_eq!(app.status, "Unknown command /mcp__docs__review");
+        assert!(app.messages.is_empty());
+    }
+
     #[test]
     fn test_quit() {
         let temp = TempDir::new().unwrap();
         let session = Session::new(temp.path().to_path_buf());
-        let mut app: App<GLMProvider> = App::new(session, vec![]);
+        let mut app: App<GLMProvider> = App::new(session, Arc::new(ToolRegistry::new()));
 
         app.quit();
 
@@ -169,11 +781,99 @@ mod tests {
     fn test_cursor_position() {
         let temp = TempDir::new().unwrap();
         let session = Session::new(temp.path().to_path_buf());
-        let mut app: App<GLMProvider> = App::new(session, vec![]);
+        let mut app: App<GLMProvider> = App::new(session, Arc::new(ToolRegistry::new()));
 
         assert_eq!(app.cursor_position(), 0);
 
         app.input = "hello".to_string();
         assert_eq!(app.cursor_position(), 5);
     }
+
+    #[test]
+    fn test_plan_review_edit_and_toggle() {
+        let temp = TempDir::new().unwrap();
+        let session = Session::new(temp.path().to_path_buf());
+        let mut app: App<GLMProvider> = App::new(session, Arc::new(ToolRegistry::new()));
+
+        app.toggle_plan_mode();
+        assert_eq!(app.session.context().mode(), SessionMode::Plan);
+
+        let (responder, mut response) = oneshot::channel();
+        app.handle_update(AppUpdate::PlanProposed("1. Fix".to_string(), responder));
+        app.begin_plan_input(PlanInput::Editing);
+        assert_eq!(app.input, "1. Fix");
+        app.handle_input('!');
+        app.submit_plan_input();
+
+        assert!(app.pending_plan.is_none());
+        assert_eq!(
+            response.try_recv().unwrap(),
+            PlanDecision::Approve {
+                plan: "1. Fix!".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_todos_follow_events() {
+        let temp = TempDir::new().unwrap();
+        let session = Session::new(temp.path().to_path_buf());
+        let mut app: App<GLMProvider> = App::new(session.clone(), Arc::new(ToolRegistry::new()));
+        let todos = vec![Todo::new("Fix the bug", matw_core::TodoStatus::InProgress)];
+
+        app.handle_update(AppUpdate::Agent(AgentEvent::TodosUpdated { todos: todos.clone() }));
+        assert_eq!(app.todos, todos);
+
+        app.handle_update(AppUpdate::TurnFinished(Box::new(session), Ok(())));
+        assert!(app.todos.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rewind_command() {
+        let temp = TempDir::new().unwrap();
+        let mut session = Session::new(temp.path().to_path_buf());
+        session.add_message(Message::new_user("first".to_string()));
+        session.add_message(Message::new_assistant("reply".to_string()));
+        session.add_message(Message::new_user("second".to_string()));
+        let mut app: App<GLMProvider> = App::new(session, Arc::new(ToolRegistry::new()));
+
+        app.input = "/rewind".to_string();
+        app.submit_input().await;
+        assert_eq!(app.status, "2 rewind points");
+        let Content::Text(listing) = app.messages.last().unwrap().content() else {
+            panic!("the listing is text");
+        };
+        assert!(listing.contains("1. first (files not tracked)"), "{}", listing);
+        assert_eq!(app.session.message_count(), 3);
+
+        app.input = "/rewind 3".to_string();
+        app.submit_input().await;
+        assert_eq!(app.status, "No prompt 3; /rewind lists them");
+
+        app.input = "/rewind 2 conversation".to_string();
+        app.submit_input().await;
+        assert_eq!(app.session.message_count(), 2);
+        assert_eq!(app.messages.len(), 2);
+        assert_eq!(app.input, "second");
+
+        // Prompts from before this run have no checkpoint to restore files from
+        app.input = "/rewind 1 files".to_string();
+        app.submit_input().await;
+        assert_eq!(app.status, "Restored 0 files; changes from before matw started are not restored");
+    }
+
+    #[test]
+    fn test_blocked_prompt_returns_to_input() {
+        let temp = TempDir::new().unwrap();
+        let session = Session::new(temp.path().to_path_buf());
+        let mut app: App<GLMProvider> = App::new(session.clone(), Arc::new(ToolRegistry::new()));
+        app.messages.push(Message::new_user("deploy to prod".to_string()));
+
+        let blocked = AgentError::PromptBlocked("deploys go through CI".to_string());
+        app.handle_update(AppUpdate::TurnFinished(Box::new(session), Err(blocked)));
+
+        assert_eq!(app.input, "deploy to prod");
+        assert!(app.messages.is_empty());
+        assert_eq!(app.status, "Prompt blocked by hook: deploys go through CI");
+    }
 }
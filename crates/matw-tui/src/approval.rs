@@ -0,0 +1,123 @@
+//! Tool-call approval and plan review prompts
+//!
+//! Bridges the agent's approval requests and proposed plans to the UI thread
+//! over a channel.
+
+use crate::app::AppUpdate;
+use async_trait::async_trait;
+use matw_agent::{ApprovalDecision, ApprovalHandler, ApprovalRequest, PlanDecision, PlanReviewer};
+use tokio::sync::{mpsc, oneshot};
+
+/// A tool call waiting for the user's answer
+pub struct PendingApproval {
+    /// The request shown to the user
+    pub request: ApprovalRequest,
+    /// Whether the user is typing feedback for a denial
+    pub entering_feedback: bool,
+    responder: oneshot::Sender<ApprovalDecision>,
+}
+
+impl PendingApproval {
+    pub fn new(request: ApprovalRequest, responder: oneshot::Sender<ApprovalDecision>) -> Self {
+        Self {
+            request,
+            entering_feedback: false,
+            responder,
+        }
+    }
+
+    /// Send the decision back to the waiting agent
+    pub fn resolve(self, decision: ApprovalDecision) {
+        self.responder.send(decision).ok();
+    }
+
+    /// One-line summary of the tool input
+    pub fn summary(&self) -> String {
+        match self.request.input.get("command").and_then(|c| c.as_str()) {
+            Some(command) => command.to_string(),
+            None => self.request.input.to_string(),
+        }
+    }
+}
+
+/// Approval handler that forwards requests to the TUI
+pub struct TuiApprovalHandler {
+    sender: mpsc::UnboundedSender<AppUpdate>,
+}
+
+impl TuiApprovalHandler {
+    pub fn new(sender: mpsc::UnboundedSender<AppUpdate>) -> Self {
+        Self { sender }
+    }
+}
+
+#[async_trait]
+impl ApprovalHandler for TuiApprovalHandler {
+    async fn request_approval(&self, request: ApprovalRequest) -> ApprovalDecision {
+        let (responder, response) = oneshot::channel();
+        if self.sender.send(AppUpdate::ApprovalRequested(request, responder)).is_err() {
+            return ApprovalDecision::Deny { feedback: None };
+        }
+
+        // A dropped prompt (e.g. the app quit) counts as a denial
+        response.await.unwrap_or(ApprovalDecision::Deny { feedback: None })
+    }
+}
+
+/// What the user is typing while reviewing a plan
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanInput {
+    /// Choosing what to do with the plan
+    None,
+    /// Editing the plan in the input box
+    Editing,
+    /// Typing feedback for a rejection
+    Feedback,
+}
+
+/// A plan waiting for the user's review
+pub struct PendingPlan {
+    /// The plan as proposed by the model
+    pub plan: String,
+    pub input: PlanInput,
+    responder: oneshot::Sender<PlanDecision>,
+}
+
+impl PendingPlan {
+    pub fn new(plan: String, responder: oneshot::Sender<PlanDecision>) -> Self {
+        Self {
+            plan,
+            input: PlanInput::None,
+            responder,
+        }
+    }
+
+    /// Send the decision back to the waiting agent
+    pub fn resolve(self, decision: PlanDecision) {
+        self.responder.send(decision).ok();
+    }
+}
+
+/// Plan reviewer that forwards plans to the TUI
+pub struct TuiPlanReviewer {
+    sender: mpsc::UnboundedSender<AppUpdate>,
+}
+
+impl TuiPlanReviewer {
+    pub fn new(sender: mpsc::UnboundedSender<AppUpdate>) -> Self {
+        Self { sender }
+    }
+}
+
+#[async_trait]
+impl PlanReviewer for TuiPlanReviewer {
+    async fn review_plan(&self, plan: String) -> PlanDecision {
+        let (responder, response) = oneshot::channel();
+        if self.sender.send(AppUpdate::PlanProposed(plan, responder)).is_err() {
+            return PlanDecision::Reject { feedback: None };
+        }
+
+        // A dropped review counts as a rejection
+        response.await.unwrap_or(PlanDecision::Reject { feedback: None })
+    }
+}
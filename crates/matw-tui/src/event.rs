@@ -20,7 +20,7 @@ pub enum Event {
 
 /// Async event handler
 pub struct EventHandler {
-    sender: tokio_mpsc::UnboundedSender<Event>,
+    _sender: tokio_mpsc::UnboundedSender<Event>,
     receiver: tokio_mpsc::UnboundedReceiver<Event>,
 }
 
@@ -42,10 +42,8 @@ impl EventHandler {
                 if timeout.is_zero() || event::poll(timeout).unwrap_or(false) {
                     if let Ok(event) = event::read() {
                         match event {
-                            CrosstermEvent::Key(key) => {
-                                if key.kind == KeyEventKind::Press {
-                                    sender_clone.send(Event::Key(key)).ok();
-                                }
+                            CrosstermEvent::Key(key) if key.kind == KeyEventKind::Press => {
+                                sender_clone.send(Event::Key(key)).ok();
                             }
                             CrosstermEvent::Mouse(mouse) => {
                                 sender_clone.send(Event::Mouse(mouse)).ok();
@@ -65,7 +63,10 @@ impl EventHandler {
             }
         });
 
-        Self { sender, receiver }
+        Self {
+            _sender: sender,
+            receiver,
+        }
     }
 
     /// Get the next event
@@ -3,11 +3,14 @@
 //! Provides a ratatui-based terminal UI for the MATW AI coding assistant.
 
 pub mod app;
+pub mod approval;
 pub mod ui;
 pub mod event;
+pub mod mcp;
 pub mod runner;
 
-pub use app::App;
+pub use app::{App, AppUpdate};
+pub use approval::{PendingApproval, PendingPlan, PlanInput, TuiApprovalHandler, TuiPlanReviewer};
 pub use event::{Event, EventHandler};
-pub use runner::run;
+pub use runner::{run, RunOptions};
 pub use ui::UI;
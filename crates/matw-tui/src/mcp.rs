@@ -0,0 +1,139 @@
+//! MCP resources and prompts in the input box
+//!
+//! `@server:uri` in a prompt mentions a resource of a connected MCP server;
+//! its contents are read and added to the prompt before it is sent. Prompts
+//! offered by servers run as `/mcp__<server>__<prompt> [args...]`, with the
+//! arguments given in the order the prompt lists them; the last one takes
+//! the rest of the line.
+
+use matw_mcp::protocol::{ContentItem, GetPromptResult, PromptArgument, ResourceContents};
+use std::collections::HashMap;
+
+/// `(server, uri)` for each `@server:uri` in `input` naming one of `servers`
+pub fn mentions(input: &str, servers: &[&str]) -> Vec<(String, String)> {
+    let mut found: Vec<(String, String)> = Vec::new();
+    for word in input.split_whitespace() {
+        let Some((server, uri)) = word.strip_prefix('@').and_then(|w| w.split_once(':')) else {
+            continue;
+        };
+        let mention = (server.to_string(), uri.to_string());
+        if servers.contains(&server) && !uri.is_empty() && !found.contains(&mention) {
+            found.push(mention);
+        }
+    }
+    found
+}
+
+/// A mentioned resource's contents, as appended to the prompt
+pub fn render_resource(server: &str, uri: &str, contents: &[ResourceContents]) -> String {
+    let body: Vec<String> = contents
+        .iter()
+        .map(|c| match (&c.text, &c.blob) {
+            (Some(text), _) => text.clone(),
+            (None, Some(blob)) => format!(
+                "[binary {} content, {} bytes base64, not included]",
+                c.mime_type.as_deref().unwrap_or("resource"),
+                blob.len()
+            ),
+            (None, None) => String::new(),
+        })
+        .collect();
+    format!("<resource server=\"{}\" uri=\"{}\">\n{}\n</resource>", server, uri, body.join("\n"))
+}
+
+/// How to run a prompt, e.g. `/mcp__docs__review <file> [focus]`
+pub fn prompt_usage(command: &str, arguments: &[PromptArgument]) -> String {
+    let mut usage = format!("/{}", command);
+    for arg in arguments {
+        if arg.required {
+            usage.push_str(&format!(" <{}>", arg.name));
+        } else {
+            usage.push_str(&format!(" [{}]", arg.name));
+        }
+    }
+    usage
+}
+
+/// Match the words of `args` to the prompt's arguments in order, the last
+/// argument taking the rest of the line. Fails if a required one is missing.
+pub fn prompt_arguments(arguments: &[PromptArgument], args: &str) -> Option<HashMap<String, String>> {
+    let mut values = HashMap::new();
+    let mut rest = args.trim();
+    for (i, arg) in arguments.iter().enumerate() {
+        let value = if i + 1 == arguments.len() {
+            std::mem::take(&mut rest)
+        } else {
+            let (word, after) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            rest = after.trim_start();
+            word
+        };
+        if value.is_empty() {
+            if arg.required {
+                return None;
+            }
+            continue;
+        }
+        values.insert(arg.name.clone(), value.to_string());
+    }
+    Some(values)
+}
+
+/// The prompt's messages as one prompt to send
+pub fn prompt_text(result: &GetPromptResult) -> String {
+    let parts: Vec<String> = result
+        .messages
+        .iter()
+        .filter_map(|message| match &message.content {
+            ContentItem::Text { text } => Some(text.clone()),
+            ContentItem::Resource { resource } => resource.text.clone(),
+            ContentItem::Image { .. } => None,
+        })
+        .collect();
+    parts.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argument(name: &str, required: bool) -> PromptArgument {
+        PromptArgument {
+            name: name.to_string(),
+            description: None,
+            required,
+        }
+    }
+
+    #[test]
+    fn test_mentions_of_known_servers() {
+        let input = "Compare @docs:file:///a.md with @docs:file:///a.md and @me:x, mail bob@docs:y";
+        assert_eq!(mentions(input, &["docs"]), vec![("docs".to_string(), "file:///a.md".to_string())]);
+        assert!(mentions("@docs: nothing", &["docs"]).is_empty());
+    }
+
+    #[test]
+    fn test_prompt_arguments_in_order() {
+        let arguments = vec![argument("file", true), argument("focus", false)];
+        let values = prompt_arguments(&arguments, " src/main.rs error handling ").unwrap();
+        assert_eq!(values["file"], "src/main.rs");
+        assert_eq!(values["focus"], "error handling");
+
+        assert_eq!(prompt_arguments(&arguments, "lib.rs").unwrap().len(), 1);
+        assert!(prompt_arguments(&arguments, "").is_none());
+        assert_eq!(prompt_usage("mcp__docs__review", &arguments), "/mcp__docs__review <file> [focus]");
+    }
+
+    #[test]
+    fn test_render_binary_resource() {
+        let contents = vec![ResourceContents {
+            uri: "file:///logo.png".to_string(),
+            mime_type: Some("image/png".to_string()),
+            text: None,
+            blob: Some("iVBOR/8=".to_string()),
+        }];
+        assert_eq!(
+            render_resource("files", "file:///logo.png", &contents),
+            "<resource server=\"files\" uri=\"file:///logo.png\">\n[binary image/png content, 8 bytes base64, not included]\n</resource>"
+        );
+    }
+}
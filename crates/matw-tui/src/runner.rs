@@ -4,19 +4,39 @@
 
 use crate::{App, Event, EventHandler, UI};
 use crossterm::{
-    event::KeyCode,
+    event::{KeyCode, KeyEvent, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use matw_agent::Agent;
+use crate::approval::PlanInput;
+use matw_agent::{
+    load_agent_types, Agent, AgentConfig, ApprovalDecision, HookRunner, PermissionEngine, PlanDecision,
+    SystemPromptBuilder, TaskTool, TracingObserver,
+};
 use matw_ai::providers::GLMProvider;
-use matw_core::Session;
-use matw_tools::all_tools;
+use matw_core::{Session, TrustStore};
+use matw_mcp::{McpConfig, McpManager};
+use matw_tools::{ToolFilter, ToolRegistry, ToolSource};
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Settings for a TUI run, collected from config and command-line flags
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Model settings and turn limits
+    pub agent: AgentConfig,
+    /// Which tools are offered to the model
+    pub tools: ToolFilter,
+    /// System prompt with the user's overrides applied
+    pub system_prompt: SystemPromptBuilder,
+    /// Where the session is saved after each turn, if anywhere
+    pub sessions_dir: Option<PathBuf>,
+}
 
-/// Run the TUI application
-pub async fn run() -> anyhow::Result<()> {
+/// Run the TUI application for a session
+pub async fn run(session: Session, options: RunOptions) -> anyhow::Result<()> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen)?;
@@ -24,30 +44,74 @@ pub async fn run() -> anyhow::Result<()> {
     let mut terminal = Terminal::new(backend)?;
 
     // Setup app
-    let session = Session::new(std::env::current_dir()?);
-    let tools_raw = all_tools();
-    let tools: Vec<_> = tools_raw
-        .into_iter()
-        .map(|t| std::sync::Arc::from(t) as std::sync::Arc<dyn matw_tools::Tool>)
-        .collect();
-
-    // Create provider and agent
-    let provider = GLMProvider::new("test-key".to_string(), None);
-    let agent = Agent::new(provider, tools.clone());
-
-    let mut app = App::new(session, tools).with_agent(agent);
+    let working_dir = session.context().working_dir().to_path_buf();
+    let tools = Arc::new(ToolRegistry::with_builtins());
+    tools.set_filter(options.tools);
+
+    // Project files that run commands are ignored until the user approves them
+    let trust = TrustStore::load_user()?;
+
+    // MCP servers run until the manager is dropped at the end of the session
+    let mut mcp = McpManager::new(tools.clone(), &working_dir);
+    let mcp_failures = mcp.start_all(&McpConfig::load_all(&working_dir, &trust)?).await;
+    let mcp = Arc::new(mcp);
+
+    // Create provider and agent; sub-agents share the provider, permissions and tool hooks
+    let provider = Arc::new(GLMProvider::new("test-key".to_string(), None));
+    let permissions = Arc::new(PermissionEngine::load(&working_dir)?);
+    let hooks = HookRunner::load(&working_dir, &trust)?;
+    let context = session.context().clone();
+    let mut app = App::new(session, tools.clone()).with_mcp(mcp);
+    let unapproved = trust.unapproved(&working_dir);
+    if !unapproved.is_empty() {
+        let files: Vec<String> = unapproved.iter().map(|file| file.display().to_string()).collect();
+        app.status = format!("Not approved, so not used: {} (run `matw trust`)", files.join(", "));
+    }
+    if !mcp_failures.is_empty() {
+        let failures: Vec<String> = mcp_failures.iter().map(|(server, e)| format!("{} ({})", server, e)).collect();
+        app.status = format!("MCP servers failed to start: {}", failures.join("; "));
+    }
+    let task = TaskTool::new(provider.clone(), &tools, load_agent_types(&working_dir)?)
+        .with_config(options.agent.clone())
+        .with_context(context)
+        .with_permissions(permissions.clone())
+        .with_hooks(&hooks)
+        .with_approval_handler(app.approval_handler());
+    tools.register(Arc::new(task), ToolSource::BuiltIn)?;
+    let agent = Agent::new(provider, tools)
+        .with_config(options.agent)
+        .with_permissions(permissions)
+        .with_hooks(hooks)
+        .with_system_prompt(options.system_prompt)
+        .with_approval_handler(app.approval_handler())
+        .with_plan_reviewer(app.plan_reviewer())
+        .with_observer(app.event_observer())
+        .with_observer(Arc::new(TracingObserver));
+
+    let mut app = app.with_agent(agent);
+    if let Some(dir) = options.sessions_dir {
+        app = app.with_sessions_dir(dir);
+    }
     let mut events = EventHandler::new(250);
 
     // Main loop
     loop {
         terminal.draw(|f| UI::draw(f, &app))?;
 
-        if let Some(event) = events.next().await {
-            match event {
+        tokio::select! {
+            Some(event) = events.next() => match event {
+                // Ctrl-C interrupts a running turn, or quits when idle
+                Event::Key(key) if is_interrupt(&key) => interrupt(&mut app),
+                Event::Key(key) if app.pending_approval.is_some() => handle_approval_key(&mut app, key.code),
+                Event::Key(key) if app.pending_plan.is_some() => handle_plan_key(&mut app, key.code),
                 Event::Key(key) => {
                     match key.code {
                         KeyCode::Char('q') if app.input.is_empty() => app.quit(),
+                        KeyCode::Esc if app.busy => {
+                            app.cancel_turn();
+                        }
                         KeyCode::Esc => app.quit(),
+                        KeyCode::BackTab => app.toggle_plan_mode(),
                         KeyCode::Char(c) => app.handle_input(c),
                         KeyCode::Backspace => app.handle_backspace(),
                         KeyCode::Enter => app.submit_input().await,
@@ -58,7 +122,8 @@ pub async fn run() -> anyhow::Result<()> {
                     // Periodic updates (e.g., status changes)
                 }
                 _ => {}
-            }
+            },
+            Some(update) = app.next_update() => app.handle_update(update),
         }
 
         if app.should_quit {
@@ -71,3 +136,61 @@ pub async fn run() -> anyhow::Result<()> {
     execute!(io::stdout(), LeaveAlternateScreen)?;
     Ok(())
 }
+
+fn is_interrupt(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+fn interrupt<P: matw_ai::AIProvider>(app: &mut App<P>) {
+    if !app.cancel_turn() {
+        app.quit();
+    }
+}
+
+/// Keys while a tool call waits for approval
+fn handle_approval_key<P: matw_ai::AIProvider>(app: &mut App<P>, code: KeyCode) {
+    let entering_feedback = app
+        .pending_approval
+        .as_ref()
+        .is_some_and(|p| p.entering_feedback);
+
+    if entering_feedback {
+        match code {
+            KeyCode::Char(c) => app.handle_input(c),
+            KeyCode::Backspace => app.handle_backspace(),
+            KeyCode::Enter => app.submit_denial_feedback(),
+            KeyCode::Esc => app.resolve_approval(ApprovalDecision::Deny { feedback: None }),
+            _ => {}
+        }
+        return;
+    }
+
+    match code {
+        KeyCode::Char('y') => app.resolve_approval(ApprovalDecision::AllowOnce),
+        KeyCode::Char('a') => app.resolve_approval(ApprovalDecision::AllowForSession),
+        KeyCode::Char('n') => app.begin_denial_feedback(),
+        KeyCode::Esc => app.resolve_approval(ApprovalDecision::Deny { feedback: None }),
+        _ => {}
+    }
+}
+
+/// Keys while a plan waits for review
+fn handle_plan_key<P: matw_ai::AIProvider>(app: &mut App<P>, code: KeyCode) {
+    let input = app.pending_plan.as_ref().map_or(PlanInput::None, |p| p.input);
+
+    match (input, code) {
+        (PlanInput::None, KeyCode::Char('y')) => {
+            let plan = app.pending_plan.as_ref().map(|p| p.plan.clone()).unwrap_or_default();
+            app.resolve_plan(PlanDecision::Approve { plan });
+        }
+        (PlanInput::None, KeyCode::Char('e')) => app.begin_plan_input(PlanInput::Editing),
+        (PlanInput::None, KeyCode::Char('n')) => app.begin_plan_input(PlanInput::Feedback),
+        (PlanInput::None | PlanInput::Feedback, KeyCode::Esc) => app.resolve_plan(PlanDecision::Reject { feedback: None }),
+        // Leaving the editor goes back to the choices, keeping the proposed plan
+        (PlanInput::Editing, KeyCode::Esc) => app.begin_plan_input(PlanInput::None),
+        (PlanInput::Editing | PlanInput::Feedback, KeyCode::Char(c)) => app.handle_input(c),
+        (PlanInput::Editing | PlanInput::Feedback, KeyCode::Backspace) => app.handle_backspace(),
+        (PlanInput::Editing | PlanInput::Feedback, KeyCode::Enter) => app.submit_plan_input(),
+        _ => {}
+    }
+}
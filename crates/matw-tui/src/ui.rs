@@ -2,9 +2,10 @@
 //!
 //! Provides ratatui-based rendering for the terminal UI.
 
+use crate::approval::PlanInput;
 use crate::App;
 use matw_ai::AIProvider;
-use matw_core::{Content, Role};
+use matw_core::{Content, Role, SessionMode, TodoStatus};
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
@@ -19,14 +20,116 @@ pub struct UI;
 impl UI {
     /// Draw the complete UI
     pub fn draw<P: AIProvider>(f: &mut Frame, app: &App<P>) {
+        let approval_height = if app.pending_approval.is_some() { 5 } else { 0 };
+        let plan_height = app
+            .pending_plan
+            .as_ref()
+            .map_or(0, |pending| (pending.plan.lines().count() as u16 + 3).min(f.area().height / 2));
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .margin(1)
-            .constraints([Constraint::Min(0), Constraint::Length(3)])
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(approval_height),
+                Constraint::Length(plan_height),
+                Constraint::Length(3),
+            ])
             .split(f.area());
 
-        Self::draw_messages(f, app, chunks[0]);
-        Self::draw_input(f, app, chunks[1]);
+        if app.todos.is_empty() {
+            Self::draw_messages(f, app, chunks[0]);
+        } else {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(0), Constraint::Percentage(30)])
+                .split(chunks[0]);
+            Self::draw_messages(f, app, columns[0]);
+            Self::draw_todos(f, app, columns[1]);
+        }
+        Self::draw_approval(f, app, chunks[1]);
+        Self::draw_plan(f, app, chunks[2]);
+        Self::draw_input(f, app, chunks[3]);
+    }
+
+    /// Draw the approval prompt for a pending tool call
+    fn draw_approval<P: AIProvider>(f: &mut Frame, app: &App<P>, area: Rect) {
+        let Some(pending) = &app.pending_approval else {
+            return;
+        };
+
+        let choices = if pending.entering_feedback {
+            "Type feedback for the model, Enter to deny, Esc to deny without feedback"
+        } else {
+            "[y] allow once  [a] always for this session  [n] deny with feedback"
+        };
+
+        let lines = vec![
+            Line::from(Span::styled(
+                pending.request.reason.clone(),
+                Style::default().fg(Color::Yellow),
+            )),
+            Line::from(vec![Span::raw("  "), Span::raw(pending.summary())]),
+            Line::from(Span::styled(choices, Style::default().add_modifier(Modifier::BOLD))),
+        ];
+
+        let prompt = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("Allow {}?", pending.request.tool_name))
+            )
+            .wrap(Wrap { trim: true });
+
+        f.render_widget(prompt, area);
+    }
+
+    /// Draw the session's task list
+    fn draw_todos<P: AIProvider>(f: &mut Frame, app: &App<P>, area: Rect) {
+        let done = app.todos.iter().filter(|t| t.status == TodoStatus::Completed).count();
+        let lines: Vec<Line> = app
+            .todos
+            .iter()
+            .map(|todo| {
+                let (marker, style) = match todo.status {
+                    TodoStatus::Completed => ("[x] ", Style::default().fg(Color::DarkGray)),
+                    TodoStatus::InProgress => ("[>] ", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                    TodoStatus::Pending => ("[ ] ", Style::default()),
+                };
+                Line::from(vec![Span::styled(marker, style), Span::styled(todo.content.as_str(), style)])
+            })
+            .collect();
+
+        let list = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("Tasks {}/{}", done, app.todos.len()))
+            )
+            .wrap(Wrap { trim: true });
+
+        f.render_widget(list, area);
+    }
+
+    /// Draw the plan waiting for review
+    fn draw_plan<P: AIProvider>(f: &mut Frame, app: &App<P>, area: Rect) {
+        let Some(pending) = &app.pending_plan else {
+            return;
+        };
+
+        let choices = match pending.input {
+            PlanInput::None => "[y] approve  [e] edit  [n] reject with feedback",
+            PlanInput::Editing => "Edit the plan below, Enter to approve it, Esc to go back",
+            PlanInput::Feedback => "Type feedback for the model, Enter to reject, Esc to reject without feedback",
+        };
+
+        let mut lines = vec![Line::from(Span::styled(choices, Style::default().add_modifier(Modifier::BOLD)))];
+        lines.extend(pending.plan.lines().map(|line| Line::from(vec![Span::raw("  "), Span::raw(line)])));
+
+        let prompt = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("Approve this plan?"))
+            .wrap(Wrap { trim: false });
+
+        f.render_widget(prompt, area);
     }
 
     /// Draw messages area
@@ -79,6 +182,17 @@ impl UI {
             lines.push(Line::from(""));
         }
 
+        // Response still being streamed by the running turn
+        if !app.streaming.is_empty() {
+            lines.push(Line::from(vec![Span::styled(
+                "[Assistant]",
+                Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD),
+            )]));
+            for line in app.streaming.lines() {
+                lines.push(Line::from(vec![Span::raw("  "), Span::raw(line)]));
+            }
+        }
+
         let paragraph = Paragraph::new(lines)
             .block(
                 Block::default()
@@ -92,11 +206,15 @@ impl UI {
 
     /// Draw input area
     fn draw_input<P: AIProvider>(f: &mut Frame, app: &App<P>, area: Rect) {
+        let mode = match app.session.context().mode() {
+            SessionMode::Plan => " | Plan mode",
+            SessionMode::Normal => "",
+        };
         let input = Paragraph::new(app.input.as_str())
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title(format!("Input | Status: {}", app.status))
+                    .title(format!("Input{} | Status: {}", mode, app.status))
             );
 
         f.render_widget(input, area);